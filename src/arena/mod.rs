@@ -0,0 +1,308 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::board::fen::{FenError, STARTING_FEN};
+use crate::board::pieces::PieceColor;
+use crate::board::Board;
+use crate::computer::book::{BookSelection, OpeningBook};
+use crate::computer::difficulty::Difficulty;
+use crate::computer::engine::{Engine, SearchLimits};
+use crate::computer::external::{ExternalEngine, ExternalEngineError};
+use crate::computer::search::{SearchEngine, DEFAULT_BOOK_DEPTH};
+use crate::computer::{selfplay, ComputerEngine};
+use crate::game::Game;
+use crate::Turn;
+
+pub mod sprt;
+pub mod stats;
+pub mod tournament;
+
+use sprt::{Sprt, SprtStatus};
+use stats::MatchStats;
+
+/// how a player of a match is described on the command line
+///
+/// the search can be given a book after a comma: "novice,book=<file>"; "bookdepth=<n>" and
+/// "bookselection=best" or "weighted" go along with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineSpec {
+    /// "random"; the random capture picker
+    Random,
+    /// a difficulty level by name; eg. "novice"
+    Level(Difficulty, Option<BookSpec>),
+    /// "full"; the search at full strength
+    Full(Option<BookSpec>),
+    /// "uci:<path>"; an external UCI engine
+    Uci(String),
+}
+
+/// the opening book of a built-in engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookSpec {
+    pub path: String,
+    pub selection: BookSelection,
+    /// the book is consulted for this many of the engine's moves of a game
+    pub depth: usize,
+}
+
+impl BookSpec {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            selection: BookSelection::default(),
+            depth: DEFAULT_BOOK_DEPTH,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseEngineSpecError {
+    UnknownEngine(String),
+    /// an option that isn't known or has a bad value, or one the engine has no use for
+    BadOption(String),
+}
+
+impl fmt::Display for ParseEngineSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseEngineSpecError::UnknownEngine(engine) => write!(
+                f,
+                "unknown engine \"{}\"; expected random, full, uci:<path> or a level ({})",
+                engine,
+                Difficulty::ALL
+                    .map(|difficulty| difficulty.to_string().to_lowercase())
+                    .join(", ")
+            ),
+            ParseEngineSpecError::BadOption(option) => write!(
+                f,
+                "bad engine option \"{option}\"; the search takes book=<file>, \
+                 bookdepth=<n> and bookselection=best or weighted"
+            ),
+        }
+    }
+}
+
+impl FromStr for EngineSpec {
+    type Err = ParseEngineSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the path may have commas of its own
+        if let Some(path) = s.strip_prefix("uci:") {
+            return Ok(EngineSpec::Uci(path.to_string()));
+        }
+        let mut parts = s.split(',');
+        let engine = parts.next().unwrap_or_default();
+        let mut book: Option<BookSpec> = None;
+        let (mut depth, mut selection) = (None, None);
+        for option in parts {
+            let bad_option = || ParseEngineSpecError::BadOption(option.to_string());
+            let (name, value) = option.split_once('=').ok_or_else(bad_option)?;
+            match name.trim().to_lowercase().as_str() {
+                "book" => book = Some(BookSpec::new(value.trim())),
+                "bookdepth" => depth = Some(value.trim().parse().map_err(|_| bad_option())?),
+                "bookselection" => {
+                    selection = Some(match value.trim().to_lowercase().as_str() {
+                        "best" => BookSelection::Best,
+                        "weighted" => BookSelection::Weighted,
+                        _ => return Err(bad_option()),
+                    })
+                }
+                _ => return Err(bad_option()),
+            }
+        }
+        if book.is_none() && (depth.is_some() || selection.is_some()) {
+            return Err(ParseEngineSpecError::BadOption(
+                "bookdepth and bookselection without a book".to_string(),
+            ));
+        }
+        if let Some(book) = &mut book {
+            book.depth = depth.unwrap_or(book.depth);
+            book.selection = selection.unwrap_or(book.selection);
+        }
+        match engine.to_lowercase().as_str() {
+            "random" if book.is_some() => Err(ParseEngineSpecError::BadOption(
+                "the random player has no use for a book".to_string(),
+            )),
+            "random" => Ok(EngineSpec::Random),
+            "full" => Ok(EngineSpec::Full(book)),
+            level => level
+                .parse()
+                .map(|difficulty| EngineSpec::Level(difficulty, book))
+                .map_err(|_| ParseEngineSpecError::UnknownEngine(engine.to_string())),
+        }
+    }
+}
+
+/// why an engine couldn't be built
+#[derive(Debug)]
+pub enum BuildEngineError {
+    External(ExternalEngineError),
+    Book(String, io::Error),
+}
+
+impl fmt::Display for BuildEngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildEngineError::External(err) => write!(f, "{err}"),
+            BuildEngineError::Book(path, err) => write!(f, "can't read the book {path}: {err}"),
+        }
+    }
+}
+
+impl EngineSpec {
+    pub fn build(&self) -> Result<Box<dyn Engine>, BuildEngineError> {
+        let search = |mut engine: SearchEngine, book: &Option<BookSpec>| {
+            if let Some(book) = book {
+                let opened = OpeningBook::open(&book.path)
+                    .map_err(|err| BuildEngineError::Book(book.path.clone(), err))?;
+                engine.set_book(Some(Arc::new(opened)));
+                engine.set_book_selection(book.selection);
+                engine.set_book_depth(book.depth);
+            }
+            Ok(Box::new(engine) as Box<dyn Engine>)
+        };
+        match self {
+            EngineSpec::Random => Ok(Box::new(ComputerEngine::new())),
+            EngineSpec::Level(difficulty, book) => search(SearchEngine::new(*difficulty), book),
+            EngineSpec::Full(book) => search(SearchEngine::full_strength(), book),
+            EngineSpec::Uci(path) => ExternalEngine::new(path, &[])
+                .map(|engine| Box::new(engine) as Box<dyn Engine>)
+                .map_err(BuildEngineError::External),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    pub games: usize,
+    /// every opening is played twice, once with each engine as white;
+    /// the standard starting position when there are none
+    pub openings: Vec<(Board, Turn)>,
+    pub limits: SearchLimits,
+    /// games still going after this many plies are adjudicated a draw
+    pub max_plies: usize,
+    /// game i is played with seed + i
+    pub seed: u64,
+    /// stops the match early once the test is decided
+    pub sprt: Option<Sprt>,
+}
+
+/// a finished game of a match
+#[derive(Debug, Clone)]
+pub struct MatchGame {
+    /// counts from 1
+    pub round: usize,
+    pub game: Game,
+    /// the color the first engine played
+    pub first_color: PieceColor,
+}
+
+/// plays a match between two engines and returns the score from the first one's point of view
+///
+/// `on_game` is called after every game with the score so far. with an SPRT the match
+/// ends after the pair of games that decides the test
+pub fn run_match(
+    first: &mut dyn Engine,
+    second: &mut dyn Engine,
+    config: &MatchConfig,
+    on_game: &mut dyn FnMut(&MatchGame, &MatchStats),
+) -> MatchStats {
+    let default_opening = [Board::parse_fen(STARTING_FEN).unwrap()];
+    let openings = if config.openings.is_empty() {
+        &default_opening[..]
+    } else {
+        &config.openings[..]
+    };
+
+    let mut stats = MatchStats::default();
+    let mut pair_half_points = 0;
+    for i in 0..config.games {
+        let (board, turn) = openings[(i / 2) % openings.len()].clone();
+        let first_color = if i % 2 == 0 {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let play = |white: &mut dyn Engine, black: &mut dyn Engine| {
+            let seed = config.seed.wrapping_add(i as u64);
+            selfplay::play_game(
+                board,
+                turn,
+                white,
+                black,
+                &config.limits,
+                seed,
+                config.max_plies,
+            )
+        };
+        let game = match first_color {
+            PieceColor::White => play(first, second),
+            PieceColor::Black => play(second, first),
+        };
+        let result = game.outcome().expect("games are played to the end").result;
+        pair_half_points += stats.add(result, first_color);
+        if i % 2 == 1 {
+            stats.pairs.add(pair_half_points);
+            pair_half_points = 0;
+        }
+        on_game(
+            &MatchGame {
+                round: i + 1,
+                game,
+                first_color,
+            },
+            &stats,
+        );
+        if let Some(sprt) = config.sprt {
+            if i % 2 == 1 && sprt.status(&stats.pairs) != SprtStatus::Continue {
+                break;
+            }
+        }
+    }
+    stats
+}
+
+/// reads one position per line, as FEN or EPD; empty lines and lines starting with '#' are skipped
+pub fn read_openings(text: &str) -> Result<Vec<(Board, Turn)>, FenError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        // an EPD's operations come where a FEN has its move counters, which are ignored
+        .map(Board::parse_fen)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_specs_take_a_book() {
+        let spec: EngineSpec = "novice,book=openings.bin,bookdepth=4,bookselection=best"
+            .parse()
+            .unwrap();
+        let book = BookSpec {
+            path: "openings.bin".to_string(),
+            selection: BookSelection::Best,
+            depth: 4,
+        };
+        assert_eq!(spec, EngineSpec::Level(Difficulty::Novice, Some(book)));
+        assert_eq!(
+            "full".parse::<EngineSpec>().unwrap(),
+            EngineSpec::Full(None)
+        );
+        assert_eq!(
+            "uci:engines/a,b".parse::<EngineSpec>().unwrap(),
+            EngineSpec::Uci("engines/a,b".to_string())
+        );
+        for bad in [
+            "random,book=openings.bin",
+            "full,bookdepth=4",
+            "full,hash=16",
+        ] {
+            assert!(bad.parse::<EngineSpec>().is_err(), "{bad}");
+        }
+    }
+}
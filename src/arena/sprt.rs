@@ -0,0 +1,171 @@
+use std::fmt;
+
+/// what an outcome no pair has had yet counts as
+const PRIOR_PAIRS: f64 = 1e-3;
+
+/// how many game pairs ended with each score for the first engine: 0, 0.5, 1, 1.5 and 2 points
+///
+/// both games of a pair start from the same opening with the colors swapped, so counting
+/// pairs instead of games cancels out most of the luck of the opening
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pentanomial(pub [u32; 5]);
+
+impl Pentanomial {
+    /// counts a pair; `half_points` is the first engine's score in half points (0 to 4)
+    pub fn add(&mut self, half_points: usize) {
+        self.0[half_points] += 1;
+    }
+
+    pub fn pairs(&self) -> u32 {
+        self.0.iter().sum()
+    }
+
+    /// the same pairs from the opponent's point of view
+    pub fn reversed(&self) -> Self {
+        let mut counts = self.0;
+        counts.reverse();
+        Self(counts)
+    }
+}
+
+impl fmt::Display for Pentanomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e] = self.0;
+        write!(f, "[{a}, {b}, {c}, {d}, {e}]")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtStatus {
+    Continue,
+    /// the change is no better than elo0
+    AcceptH0,
+    /// the change is at least elo1 better
+    AcceptH1,
+}
+
+impl fmt::Display for SprtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            SprtStatus::Continue => "inconclusive",
+            SprtStatus::AcceptH0 => "H0 accepted",
+            SprtStatus::AcceptH1 => "H1 accepted",
+        };
+        f.write_str(status)
+    }
+}
+
+/// a sequential probability ratio test of H0: elo = elo0 against H1: elo = elo1
+///
+/// the match goes on until the log-likelihood ratio leaves the bounds set by `alpha`, the
+/// chance of accepting H1 when H0 holds, and `beta`, the chance of the opposite
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Self {
+        Self {
+            elo0,
+            elo1,
+            alpha,
+            beta,
+        }
+    }
+
+    /// the ratio below which H0 is accepted and the one above which H1 is
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// the log-likelihood ratio of the pairs played so far
+    ///
+    /// uses the normal approximation of the generalized SPRT: with the mean and the
+    /// variance of the pair scores, LLR = n (s1 - s0) (2 mean - s0 - s1) / (2 var)
+    ///
+    /// like fishtest, every count gets a tiny prior so the variance isn't 0 when all the
+    /// pairs end the same way; eg. all 2-0
+    pub fn llr(&self, pairs: &Pentanomial) -> f64 {
+        if pairs.pairs() == 0 {
+            return 0.0;
+        }
+        let counts = pairs.0.map(|count| (count as f64).max(PRIOR_PAIRS));
+        let n = counts.iter().sum::<f64>();
+        // a pair's score scaled to 0..1
+        let scores = [0.0, 0.25, 0.5, 0.75, 1.0];
+        let mean = (0..5).map(|i| counts[i] * scores[i]).sum::<f64>() / n;
+        let variance = (0..5)
+            .map(|i| counts[i] * (scores[i] - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        n * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn status(&self, pairs: &Pentanomial) -> SprtStatus {
+        let llr = self.llr(pairs);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            SprtStatus::AcceptH0
+        } else if llr >= upper {
+            SprtStatus::AcceptH1
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+/// the score the stronger side is expected to make with an Elo advantage of `elo`
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprt() -> Sprt {
+        Sprt::new(0.0, 5.0, 0.05, 0.05)
+    }
+
+    #[test]
+    fn no_pairs_is_inconclusive() {
+        assert_eq!(sprt().llr(&Pentanomial::default()), 0.0);
+        assert_eq!(sprt().status(&Pentanomial::default()), SprtStatus::Continue);
+    }
+
+    #[test]
+    fn pairs_that_all_end_the_same_conclude() {
+        let wins = Pentanomial([0, 0, 0, 0, 20]);
+        assert!(sprt().llr(&wins) > 0.0);
+        assert_eq!(sprt().status(&wins), SprtStatus::AcceptH1);
+        assert_eq!(sprt().status(&wins.reversed()), SprtStatus::AcceptH0);
+        // a few pairs aren't enough even then
+        assert_eq!(
+            sprt().status(&Pentanomial([0, 0, 0, 0, 1])),
+            SprtStatus::Continue
+        );
+    }
+
+    #[test]
+    fn all_draws_favor_h0() {
+        let draws = Pentanomial([0, 0, 2000, 0, 0]);
+        assert!(sprt().llr(&draws) < 0.0);
+        assert_eq!(sprt().status(&draws), SprtStatus::AcceptH0);
+    }
+
+    #[test]
+    fn an_even_match_is_closer_to_h0() {
+        let even = Pentanomial([50, 200, 500, 200, 50]);
+        assert!(sprt().llr(&even) < 0.0);
+        let better = Pentanomial([40, 180, 500, 230, 60]);
+        assert!(sprt().llr(&better) > sprt().llr(&even));
+    }
+}
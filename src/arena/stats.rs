@@ -0,0 +1,105 @@
+use std::fmt;
+
+use crate::arena::sprt::Pentanomial;
+use crate::board::pieces::PieceColor;
+use crate::game::GameResult;
+
+/// the z-score of a two-sided 95% confidence interval
+const Z_95: f64 = 1.959964;
+
+/// wins, draws and losses from one engine's point of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// the scores of the finished game pairs
+    pub pairs: Pentanomial,
+}
+
+/// an Elo difference with the half-width of its 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub diff: f64,
+    pub error: f64,
+}
+
+impl MatchStats {
+    /// counts a game the engine played as `color` and returns the half points it scored
+    pub fn add(&mut self, result: GameResult, color: PieceColor) -> usize {
+        match result.winner() {
+            None => {
+                self.draws += 1;
+                1
+            }
+            Some(winner) if winner == color => {
+                self.wins += 1;
+                2
+            }
+            Some(_) => {
+                self.losses += 1;
+                0
+            }
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// points scored; a draw is worth half a point
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    /// the same games from the opponent's point of view
+    pub fn reversed(&self) -> Self {
+        Self {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+            pairs: self.pairs.reversed(),
+        }
+    }
+
+    /// the Elo difference the score suggests; None before the first game
+    ///
+    /// a score of 0% or 100% gives an infinite difference
+    pub fn elo(&self) -> Option<EloEstimate> {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return None;
+        }
+        let score = self.points() / games;
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let margin = Z_95 * (variance / games).sqrt();
+        let (low, high) = (
+            elo_from_score((score - margin).max(0.0)),
+            elo_from_score((score + margin).min(1.0)),
+        );
+        Some(EloEstimate {
+            diff: elo_from_score(score),
+            error: (high - low) / 2.0,
+        })
+    }
+}
+
+impl fmt::Display for EloEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:+.1}", self.diff)?;
+        // a perfect score has no finite bounds
+        if self.error.is_finite() {
+            write!(f, " +/- {:.1}", self.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// the Elo difference at which the stronger side is expected to score `score` (0 to 1)
+pub fn elo_from_score(score: f64) -> f64 {
+    // adding zero turns the -0 of an even score into 0
+    -400.0 * (1.0 / score - 1.0).log10() + 0.0
+}
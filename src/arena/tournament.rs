@@ -0,0 +1,631 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::arena::stats::MatchStats;
+use crate::board::fen::STARTING_FEN;
+use crate::board::pieces::PieceColor;
+use crate::board::Board;
+use crate::computer::engine::{Engine, SearchLimits};
+use crate::computer::selfplay;
+use crate::game::pgn::PgnGame;
+use crate::game::{Game, GameResult};
+use crate::Turn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentMode {
+    /// everyone plays everyone
+    RoundRobin,
+    /// the first player plays everyone else; eg. a new version against the old ones
+    Gauntlet,
+    /// players with similar scores meet; nobody meets the same opponent twice if it can
+    /// be helped
+    Swiss { rounds: usize },
+}
+
+impl fmt::Display for TournamentMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TournamentMode::RoundRobin => write!(f, "round-robin"),
+            TournamentMode::Gauntlet => write!(f, "gauntlet"),
+            TournamentMode::Swiss { rounds } => write!(f, "swiss, {rounds} rounds"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentConfig {
+    /// the PGN's Event tag
+    pub event: String,
+    pub mode: TournamentMode,
+    /// games each pairing plays, with the colors alternating
+    pub games_per_pairing: usize,
+    /// the pairings go through the openings in turn; the standard starting position
+    /// when there are none
+    pub openings: Vec<(Board, Turn)>,
+    pub limits: SearchLimits,
+    /// games still going after this many plies are adjudicated a draw
+    pub max_plies: usize,
+    /// game i of the tournament is played with seed + i
+    pub seed: u64,
+}
+
+impl TournamentConfig {
+    /// the tags every game of the tournament gets besides Round, White and Black; games of
+    /// a PGN with other values come from another tournament and can't be resumed
+    pub fn tags(&self) -> Vec<(&'static str, String)> {
+        let limits = &self.limits;
+        let limits = [
+            limits.depth.map(|depth| format!("depth {depth}")),
+            limits.nodes.map(|nodes| format!("nodes {nodes}")),
+            limits
+                .movetime
+                .map(|movetime| format!("movetime {}ms", movetime.as_millis())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+        // the openings go by their number and a digest of their keys; a FEN tag only shows
+        // the one a game started from
+        let digest = self.openings.iter().fold(0u64, |digest, (board, turn)| {
+            digest.rotate_left(5) ^ board.zobrist_hash(*turn)
+        });
+        vec![
+            ("Event", self.event.clone()),
+            ("Mode", self.mode.to_string()),
+            ("GamesPerPairing", self.games_per_pairing.to_string()),
+            ("Limits", limits),
+            ("MaxPlies", self.max_plies.to_string()),
+            (
+                "Openings",
+                format!("{} ({digest:016x})", self.openings.len()),
+            ),
+            ("Seed", self.seed.to_string()),
+        ]
+    }
+}
+
+/// a game of a PGN that doesn't belong to the tournament being resumed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeError {
+    pub round: String,
+    pub tag: &'static str,
+    pub expected: String,
+    /// None when the game doesn't have the tag
+    pub found: Option<String>,
+}
+
+impl fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the game of round {} has {} {} rather than \"{}\"",
+            self.round,
+            self.tag,
+            match &self.found {
+                Some(found) => format!("\"{found}\""),
+                None => "missing".to_string(),
+            },
+            self.expected
+        )
+    }
+}
+
+/// a finished game; players are indices into the tournament's names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TournamentGame {
+    /// counts from 1
+    pub round: usize,
+    /// the game's number within its round, from 1
+    pub number: usize,
+    pub white: usize,
+    pub black: usize,
+    pub result: GameResult,
+}
+
+impl TournamentGame {
+    /// the PGN's Round tag; eg. "3.2" for the second game of the third round
+    pub fn round_tag(&self) -> String {
+        format!("{}.{}", self.round, self.number)
+    }
+}
+
+pub struct Tournament {
+    names: Vec<String>,
+    config: TournamentConfig,
+    games: Vec<TournamentGame>,
+    /// the players that sat out a round of a swiss tournament; a bye is worth a win of
+    /// every game of the pairing
+    byes: Vec<usize>,
+}
+
+impl Tournament {
+    pub fn new(names: Vec<String>, config: TournamentConfig) -> Self {
+        Self {
+            names,
+            config,
+            games: vec![],
+            byes: vec![],
+        }
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn games(&self) -> &[TournamentGame] {
+        &self.games
+    }
+
+    pub fn rounds(&self) -> usize {
+        let players = self.names.len();
+        match self.config.mode {
+            TournamentMode::RoundRobin if players.is_multiple_of(2) => players - 1,
+            TournamentMode::RoundRobin => players,
+            TournamentMode::Gauntlet => players.saturating_sub(1),
+            TournamentMode::Swiss { rounds } => rounds,
+        }
+    }
+
+    /// checks that every game of `finished` was played with the config of this tournament
+    pub fn check_resumable(&self, finished: &[PgnGame]) -> Result<(), ResumeError> {
+        let tags = self.config.tags();
+        for game in finished {
+            for (tag, expected) in &tags {
+                let found = game.tag(tag);
+                if found != Some(expected.as_str()) {
+                    return Err(ResumeError {
+                        round: game.tag("Round").unwrap_or("?").to_string(),
+                        tag,
+                        expected: expected.clone(),
+                        found: found.map(str::to_string),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// plays every game of the tournament with `engines`, which are in the order of the names
+    ///
+    /// games found in `finished` (matched by their Round, White and Black tags, the tags of
+    /// the config and the opening) aren't played again unless they have no result, so an interrupted tournament can go
+    /// on from its PGN; see `check_resumable`. `on_game` gets every game's result along with
+    /// the game itself, or None if it came from `finished`
+    pub fn run(
+        &mut self,
+        engines: &mut [Box<dyn Engine>],
+        finished: &[PgnGame],
+        on_game: &mut dyn FnMut(&TournamentGame, Option<&Game>),
+    ) {
+        assert_eq!(engines.len(), self.names.len());
+        let default_opening = [Board::parse_fen(STARTING_FEN).unwrap()];
+        let openings = if self.config.openings.is_empty() {
+            default_opening.to_vec()
+        } else {
+            self.config.openings.clone()
+        };
+
+        let mut pairing_index = 0;
+        let mut game_index = 0;
+        for round in 1..=self.rounds() {
+            let (pairs, bye) = self.pairings(round);
+            // in a round robin everyone sits out once, which evens out
+            if let (TournamentMode::Swiss { .. }, Some(bye)) = (self.config.mode, bye) {
+                self.byes.push(bye);
+            }
+            let mut number = 0;
+
+            for (a, b) in pairs {
+                for i in 0..self.config.games_per_pairing {
+                    number += 1;
+                    let (white, black) = if i % 2 == 0 { (a, b) } else { (b, a) };
+                    let seed = self.config.seed.wrapping_add(game_index);
+                    game_index += 1;
+                    let mut played = TournamentGame {
+                        round,
+                        number,
+                        white,
+                        black,
+                        result: GameResult::Draw,
+                    };
+
+                    let (board, turn) = openings[(pairing_index + i / 2) % openings.len()].clone();
+                    if let Some(result) = self.find_finished(finished, &played, &board.to_fen(turn))
+                    {
+                        played.result = result;
+                        self.games.push(played);
+                        on_game(&played, None);
+                        continue;
+                    }
+
+                    let (white_engine, black_engine) = pair_mut(engines, white, black);
+                    let game = selfplay::play_game(
+                        board,
+                        turn,
+                        white_engine.as_mut(),
+                        black_engine.as_mut(),
+                        &self.config.limits,
+                        seed,
+                        self.config.max_plies,
+                    );
+                    played.result = game.outcome().expect("games are played to the end").result;
+                    self.games.push(played);
+                    on_game(&played, Some(&game));
+                }
+                pairing_index += 1;
+            }
+        }
+    }
+
+    fn find_finished(
+        &self,
+        finished: &[PgnGame],
+        played: &TournamentGame,
+        opening: &str,
+    ) -> Option<GameResult> {
+        let round = played.round_tag();
+        let tags = self.config.tags();
+        // a game without a result was cut off; it's played again
+        finished
+            .iter()
+            .filter(|game| game.result.is_some())
+            .find(|game| {
+                game.tag("Round") == Some(round.as_str())
+                    && game.tag("White") == Some(self.names[played.white].as_str())
+                    && game.tag("Black") == Some(self.names[played.black].as_str())
+                    && game.tag("FEN").unwrap_or(STARTING_FEN) == opening
+                    && tags
+                        .iter()
+                        .all(|(tag, value)| game.tag(tag) == Some(value.as_str()))
+            })
+            .and_then(|game| game.result)
+    }
+
+    /// who plays whom in `round` (from 1), and who sits out, if anyone
+    fn pairings(&self, round: usize) -> (Vec<(usize, usize)>, Option<usize>) {
+        let players = self.names.len();
+        match self.config.mode {
+            TournamentMode::RoundRobin => {
+                // the circle method: the first player stays put and the rest rotate; with an
+                // odd number of players the one paired with the empty seat has a bye
+                let seats = players + players % 2;
+                let mut order: Vec<usize> = vec![0];
+                order.extend((0..seats - 1).map(|i| 1 + (i + round - 1) % (seats - 1)));
+                let mut pairs = vec![];
+                let mut bye = None;
+                for i in 0..seats / 2 {
+                    let (a, b) = (order[i], order[seats - 1 - i]);
+                    if a >= players || b >= players {
+                        bye = Some(a.min(b));
+                        continue;
+                    }
+                    // alternate who gets the white pieces first
+                    pairs.push(if (round + i).is_multiple_of(2) {
+                        (a, b)
+                    } else {
+                        (b, a)
+                    });
+                }
+                (pairs, bye)
+            }
+            TournamentMode::Gauntlet if round % 2 == 1 => (vec![(0, round)], None),
+            TournamentMode::Gauntlet => (vec![(round, 0)], None),
+            TournamentMode::Swiss { .. } => self.swiss_pairings(),
+        }
+    }
+
+    /// pairs the players from the top of the standings down, each with the best placed
+    /// player it hasn't met yet
+    fn swiss_pairings(&self) -> (Vec<(usize, usize)>, Option<usize>) {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        // the sort is stable so equal scores keep the order the players were given in
+        order.sort_by(|a, b| self.points(*b).total_cmp(&self.points(*a)));
+
+        let mut bye = None;
+        if order.len() % 2 == 1 {
+            // the lowest placed player that hasn't had a bye yet
+            let position = order
+                .iter()
+                .rposition(|player| !self.byes.contains(player))
+                .unwrap_or(order.len() - 1);
+            bye = Some(order.remove(position));
+        }
+
+        let met: HashSet<(usize, usize)> = self
+            .games
+            .iter()
+            .flat_map(|game| [(game.white, game.black), (game.black, game.white)])
+            .collect();
+        let mut pairs = vec![];
+        while let Some(player) = order.first().copied() {
+            order.remove(0);
+            let position = order
+                .iter()
+                .position(|other| !met.contains(&(player, *other)))
+                .unwrap_or(0);
+            let opponent = order.remove(position);
+            // whoever had white less often gets it
+            if self.white_games(player) <= self.white_games(opponent) {
+                pairs.push((player, opponent));
+            } else {
+                pairs.push((opponent, player));
+            }
+        }
+        (pairs, bye)
+    }
+
+    fn white_games(&self, player: usize) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.white == player)
+            .count()
+    }
+
+    /// points scored, byes included
+    pub fn points(&self, player: usize) -> f64 {
+        let byes = self.byes.iter().filter(|bye| **bye == player).count();
+        self.stats(player).points() + (byes * self.config.games_per_pairing) as f64
+    }
+
+    /// the games `player` played, against the whole field
+    pub fn stats(&self, player: usize) -> MatchStats {
+        self.stats_against(player, None)
+    }
+
+    fn stats_against(&self, player: usize, opponent: Option<usize>) -> MatchStats {
+        let mut stats = MatchStats::default();
+        for game in &self.games {
+            let (color, other) = if game.white == player {
+                (PieceColor::White, game.black)
+            } else if game.black == player {
+                (PieceColor::Black, game.white)
+            } else {
+                continue;
+            };
+            if opponent.is_none() || opponent == Some(other) {
+                stats.add(game.result, color);
+            }
+        }
+        stats
+    }
+
+    /// the standings with every player's score against every other player, and an Elo
+    /// estimate against the field
+    pub fn crosstable(&self) -> String {
+        let mut ranking: Vec<usize> = (0..self.names.len()).collect();
+        ranking.sort_by(|a, b| self.points(*b).total_cmp(&self.points(*a)));
+        let width = self.names.iter().map(String::len).max().unwrap_or(0).max(6);
+
+        let mut table = format!("{:>3}  {:width$}", "#", "player");
+        for rank in 1..=ranking.len() {
+            table += &format!("  {rank:>5}");
+        }
+        table += &format!("  {:>7}  {:>5}  elo\n", "points", "games");
+
+        for (rank, &player) in ranking.iter().enumerate() {
+            table += &format!("{:>3}  {:width$}", rank + 1, self.names[player]);
+            for &opponent in &ranking {
+                let cell = if opponent == player {
+                    "*".to_string()
+                } else {
+                    let stats = self.stats_against(player, Some(opponent));
+                    if stats.games() == 0 {
+                        "-".to_string()
+                    } else {
+                        stats.points().to_string()
+                    }
+                };
+                table += &format!("  {cell:>5}");
+            }
+            let stats = self.stats(player);
+            let elo = stats.elo().map_or(String::new(), |elo| elo.to_string());
+            table += &format!(
+                "  {:>7}  {:>5}  {elo}\n",
+                self.points(player),
+                stats.games()
+            );
+        }
+        table
+    }
+}
+
+/// mutable references to two different items of a slice
+fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b);
+    if a < b {
+        let (left, right) = items.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = items.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(event: &str) -> TournamentConfig {
+        TournamentConfig {
+            event: event.to_string(),
+            mode: TournamentMode::RoundRobin,
+            games_per_pairing: 2,
+            openings: vec![Board::parse_fen(STARTING_FEN).unwrap()],
+            limits: SearchLimits {
+                depth: Some(1),
+                ..SearchLimits::default()
+            },
+            max_plies: 200,
+            seed: 0,
+        }
+    }
+
+    fn finished(config: &TournamentConfig) -> PgnGame {
+        let mut tags = vec![
+            ("Round".to_string(), "1.1".to_string()),
+            ("White".to_string(), "a".to_string()),
+            ("Black".to_string(), "b".to_string()),
+        ];
+        tags.extend(
+            config
+                .tags()
+                .into_iter()
+                .map(|(tag, value)| (tag.to_string(), value)),
+        );
+        PgnGame {
+            tags,
+            moves: vec![],
+            result: Some(GameResult::Draw),
+        }
+    }
+
+    #[test]
+    fn resumes_only_games_of_the_same_tournament() {
+        let names = vec!["a".to_string(), "b".to_string()];
+        let tournament = Tournament::new(names.clone(), config("Club"));
+        assert_eq!(
+            tournament.check_resumable(&[finished(&config("Club"))]),
+            Ok(())
+        );
+
+        let err = tournament
+            .check_resumable(&[finished(&config("Open"))])
+            .unwrap_err();
+        assert_eq!((err.tag, err.found.as_deref()), ("Event", Some("Open")));
+
+        let mut deeper = config("Club");
+        deeper.limits.depth = Some(2);
+        let err = tournament
+            .check_resumable(&[finished(&deeper)])
+            .unwrap_err();
+        assert_eq!(err.tag, "Limits");
+
+        let mut fewer = finished(&config("Club"));
+        fewer.tags.retain(|(tag, _)| tag != "Seed");
+        let err = Tournament::new(names, config("Club"))
+            .check_resumable(&[fewer])
+            .unwrap_err();
+        assert_eq!((err.tag, err.found), ("Seed", None));
+    }
+
+    #[test]
+    fn games_without_a_result_are_played_again() {
+        let tournament = Tournament::new(vec!["a".to_string(), "b".to_string()], config("Club"));
+        let played = TournamentGame {
+            round: 1,
+            number: 1,
+            white: 0,
+            black: 1,
+            result: GameResult::Draw,
+        };
+        let finished_game = finished(&config("Club"));
+        let cut_off = PgnGame {
+            result: None,
+            ..finished_game.clone()
+        };
+        assert_eq!(
+            tournament.find_finished(std::slice::from_ref(&cut_off), &played, STARTING_FEN),
+            None
+        );
+        assert_eq!(
+            tournament.find_finished(&[cut_off, finished_game], &played, STARTING_FEN),
+            Some(GameResult::Draw)
+        );
+    }
+
+    fn tournament(players: usize, mode: TournamentMode) -> Tournament {
+        let names = (0..players).map(|player| player.to_string()).collect();
+        Tournament::new(
+            names,
+            TournamentConfig {
+                mode,
+                ..config("Club")
+            },
+        )
+    }
+
+    /// the pairs of every round, without their colors
+    fn meetings(tournament: &Tournament) -> Vec<(usize, usize)> {
+        (1..=tournament.rounds())
+            .flat_map(|round| tournament.pairings(round).0)
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        for players in [4, 5, 6, 7] {
+            let tournament = tournament(players, TournamentMode::RoundRobin);
+            let mut meetings = meetings(&tournament);
+            meetings.sort();
+            let everyone: Vec<(usize, usize)> = (0..players)
+                .flat_map(|a| (a + 1..players).map(move |b| (a, b)))
+                .collect();
+            assert_eq!(meetings, everyone, "{players} players");
+
+            let mut byes = vec![];
+            for round in 1..=tournament.rounds() {
+                let (pairs, bye) = tournament.pairings(round);
+                let mut seated: Vec<usize> = pairs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+                seated.extend(bye);
+                seated.sort();
+                assert_eq!(seated, (0..players).collect::<Vec<_>>());
+                byes.extend(bye);
+            }
+            // with an odd number of players everyone sits out once
+            byes.sort();
+            if players % 2 == 1 {
+                assert_eq!(byes, (0..players).collect::<Vec<_>>());
+            } else {
+                assert!(byes.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn the_gauntlet_alternates_the_colors_of_the_first_player() {
+        let tournament = tournament(4, TournamentMode::Gauntlet);
+        assert_eq!(tournament.rounds(), 3);
+        let pairs: Vec<(usize, usize)> = (1..=3)
+            .flat_map(|round| tournament.pairings(round).0)
+            .collect();
+        assert_eq!(pairs, [(0, 1), (2, 0), (0, 3)]);
+    }
+
+    #[test]
+    fn swiss_avoids_playing_the_same_opponent_twice() {
+        let mut tournament = tournament(4, TournamentMode::Swiss { rounds: 3 });
+        let (pairs, bye) = tournament.pairings(1);
+        assert_eq!((pairs, bye), (vec![(0, 1), (2, 3)], None));
+
+        // everyone won once, so the standings stay in order and 0 would meet 1 again
+        for (white, black, result) in [
+            (0, 1, GameResult::WhiteWins),
+            (1, 0, GameResult::WhiteWins),
+            (2, 3, GameResult::WhiteWins),
+            (3, 2, GameResult::WhiteWins),
+        ] {
+            tournament.games.push(TournamentGame {
+                round: 1,
+                number: 1,
+                white,
+                black,
+                result,
+            });
+        }
+        let (pairs, _) = tournament.pairings(2);
+        let mut met: Vec<(usize, usize)> =
+            pairs.iter().map(|(a, b)| (*a.min(b), *a.max(b))).collect();
+        met.sort();
+        assert_eq!(met, [(0, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn swiss_gives_the_bye_to_the_lowest_player_without_one() {
+        let mut tournament = tournament(3, TournamentMode::Swiss { rounds: 3 });
+        assert_eq!(tournament.pairings(1).1, Some(2));
+        tournament.byes.push(2);
+        assert_eq!(tournament.pairings(2).1, Some(1));
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::process;
+
+use chess::computer::book::BookBuilder;
+use chess::game::pgn::parse_pgn;
+
+const USAGE: &str = "\
+usage: makebook --pgn <file> [--pgn <file>...] [options]
+
+builds a Polyglot opening book from the games of PGN files; games without a result are skipped
+
+options:
+  --out <file>        where the book goes (default book.bin)
+  --max-moves <n>     only the first n moves of every game go in the book (default 20)
+  --min-games <n>     leave out moves played in fewer games (default 1)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args).unwrap_or_else(|err| exit_with(&err));
+
+    let mut builder = BookBuilder::new(options.max_plies);
+    let (mut added, mut skipped) = (0, 0);
+    for path in &options.pgns {
+        let text =
+            fs::read_to_string(path).unwrap_or_else(|err| exit_with(&format!("{path}: {err}")));
+        for mut pgn_game in parse_pgn(&text) {
+            let Some(result) = pgn_game.result else {
+                skipped += 1;
+                continue;
+            };
+            // the rest of the game wouldn't go in the book anyway
+            pgn_game.moves.truncate(options.max_plies);
+            match pgn_game.to_game() {
+                Ok(game) => {
+                    builder.add_game(&game, result);
+                    added += 1;
+                }
+                Err(err) => {
+                    eprintln!("{path}: skipping a game: {err}");
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    let book = builder.build(options.min_games);
+    if let Err(err) = book.save(&options.out) {
+        exit_with(&format!("{}: {err}", options.out));
+    }
+    println!(
+        "{} entries from {added} games written to {} ({skipped} games skipped)",
+        book.len(),
+        options.out
+    );
+}
+
+struct Options {
+    pgns: Vec<String>,
+    out: String,
+    max_plies: usize,
+    min_games: u32,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        pgns: vec![],
+        out: "book.bin".to_string(),
+        max_plies: 40,
+        min_games: 1,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let number = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("{arg} needs a number, not \"{value}\""))
+        };
+        match arg.as_str() {
+            "--pgn" => options.pgns.push(value.clone()),
+            "--out" => options.out = value.clone(),
+            "--max-moves" => options.max_plies = number()? as usize * 2,
+            "--min-games" => options.min_games = number()?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    if options.pgns.is_empty() {
+        return Err("no PGN files given".to_string());
+    }
+    Ok(options)
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    process::exit(1);
+}
@@ -0,0 +1,221 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::process;
+use std::time::Duration;
+
+use chess::arena::sprt::Sprt;
+use chess::arena::stats::MatchStats;
+use chess::arena::{read_openings, run_match, EngineSpec, MatchConfig};
+use chess::board::pieces::PieceColor;
+use chess::board::Board;
+use chess::computer::engine::{SearchLimits, DEFAULT_NODES};
+
+const USAGE: &str = "\
+usage: match --engine1 <engine> --engine2 <engine> [options]
+
+engines: random, full, uci:<path>, or a level: beginner, novice, intermediate, advanced, strong;
+full and the levels take a book after a comma: full,book=<file>[,bookdepth=<n>][,bookselection=best]
+
+options:
+  --games <n>         number of games (default 2, or 20000 with --sprt)
+  --openings <file>   FEN or EPD positions to start from, one per line
+  --fen <fen>         a single position to start from
+  --depth <plies>     search depth limit
+  --nodes <n>         node limit per move (default 20000 when no other limit is given)
+  --movetime <ms>     time per move; the games depend on the speed of the machine
+                      and can't be replayed
+  --max-moves <n>     adjudicate a draw after this many moves (default 200)
+  --pgn <file>        write the games to this file
+  --seed <n>          seed of the first game (default 0)
+  --sprt <elo0>,<elo1>  run a sequential probability ratio test; stops once it's decided
+  --alpha <p>         chance of a false positive of the test (default 0.05)
+  --beta <p>          chance of a false negative of the test (default 0.05)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args).unwrap_or_else(|err| exit_with(&err));
+
+    let mut first = options
+        .engines
+        .0
+        .build()
+        .unwrap_or_else(|err| exit_with(&err.to_string()));
+    let mut second = options
+        .engines
+        .1
+        .build()
+        .unwrap_or_else(|err| exit_with(&err.to_string()));
+    let names = (first.name(), second.name());
+
+    let mut pgn = options
+        .pgn
+        .as_ref()
+        .map(|path| File::create(path).unwrap_or_else(|err| exit_with(&format!("{path}: {err}"))));
+
+    let stats = run_match(
+        &mut *first,
+        &mut *second,
+        &options.config,
+        &mut |played, stats| {
+            let (white, black) = match played.first_color {
+                PieceColor::White => (&names.0, &names.1),
+                PieceColor::Black => (&names.1, &names.0),
+            };
+            let outcome = played.game.outcome().unwrap();
+            println!(
+                "game {}: {white} vs {black}: {} ({}); {} - {}",
+                played.round,
+                outcome.result,
+                outcome.termination,
+                stats.points(),
+                stats.reversed().points(),
+            );
+            if let (Some(sprt), 0) = (options.config.sprt, played.round % 2) {
+                let (lower, upper) = sprt.bounds();
+                println!(
+                    "pair {}: LLR {:.2} [{lower:.2}, {upper:.2}], pentanomial {}",
+                    played.round / 2,
+                    sprt.llr(&stats.pairs),
+                    stats.pairs,
+                );
+            }
+            if let Some(file) = &mut pgn {
+                let round = played.round.to_string();
+                let text = played.game.to_pgn(&[
+                    ("Event", "Engine match"),
+                    ("Round", &round),
+                    ("White", white),
+                    ("Black", black),
+                ]);
+                if let Err(err) = writeln!(file, "{text}") {
+                    exit_with(&format!("couldn't write the PGN: {err}"));
+                }
+            }
+        },
+    );
+
+    println!();
+    print_table(&names, &stats);
+    if let Some(sprt) = options.config.sprt {
+        println!(
+            "SPRT elo0 {} elo1 {} alpha {} beta {}: LLR {:.2}, {}",
+            sprt.elo0,
+            sprt.elo1,
+            sprt.alpha,
+            sprt.beta,
+            sprt.llr(&stats.pairs),
+            sprt.status(&stats.pairs),
+        );
+    }
+}
+
+struct Options {
+    engines: (EngineSpec, EngineSpec),
+    config: MatchConfig,
+    pgn: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut engines = (None, None);
+    let mut config = MatchConfig {
+        games: 0,
+        openings: vec![],
+        limits: SearchLimits::default(),
+        max_plies: 400,
+        seed: 0,
+        sprt: None,
+    };
+    let mut pgn = None;
+    let mut elos = None;
+    let (mut alpha, mut beta) = (0.05, 0.05);
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let probability = || match value.parse::<f64>() {
+            Ok(p) if p > 0.0 && p < 1.0 => Ok(p),
+            _ => Err(format!("{arg} needs a probability, not \"{value}\"")),
+        };
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{arg} needs a number, not \"{value}\""))
+        };
+        match arg.as_str() {
+            "--engine1" => engines.0 = Some(value.parse().map_err(|err| format!("{err}"))?),
+            "--engine2" => engines.1 = Some(value.parse().map_err(|err| format!("{err}"))?),
+            "--games" => config.games = number()? as usize,
+            "--openings" => {
+                let text = fs::read_to_string(value).map_err(|err| format!("{value}: {err}"))?;
+                config.openings = read_openings(&text).map_err(|err| format!("{value}: {err}"))?;
+            }
+            "--fen" => {
+                config.openings = vec![Board::parse_fen(value).map_err(|err| err.to_string())?]
+            }
+            "--depth" => config.limits.depth = Some(number()?.min(u8::MAX as u64) as u8),
+            "--nodes" => config.limits.nodes = Some(number()?),
+            "--movetime" => config.limits.movetime = Some(Duration::from_millis(number()?)),
+            "--max-moves" => config.max_plies = number()? as usize * 2,
+            "--pgn" => pgn = Some(value.clone()),
+            "--seed" => config.seed = number()?,
+            "--sprt" => {
+                let parsed = value
+                    .split_once(',')
+                    .and_then(|(elo0, elo1)| Some((elo0.parse().ok()?, elo1.parse().ok()?)));
+                match parsed {
+                    Some((elo0, elo1)) if elo0 < elo1 => elos = Some((elo0, elo1)),
+                    _ => return Err(format!("{arg} needs <elo0>,<elo1> with elo0 < elo1")),
+                }
+            }
+            "--alpha" => alpha = probability()?,
+            "--beta" => beta = probability()?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    config.sprt = elos.map(|(elo0, elo1)| Sprt::new(elo0, elo1, alpha, beta));
+    if config.games == 0 {
+        config.games = if config.sprt.is_some() { 20_000 } else { 2 };
+    }
+    let limits = &mut config.limits;
+    if limits.depth.is_none() && limits.nodes.is_none() && limits.movetime.is_none() {
+        limits.nodes = Some(DEFAULT_NODES);
+    }
+    let (Some(first), Some(second)) = engines else {
+        return Err("both --engine1 and --engine2 are needed".to_string());
+    };
+    Ok(Options {
+        engines: (first, second),
+        config,
+        pgn,
+    })
+}
+
+fn print_table(names: &(String, String), stats: &MatchStats) {
+    let width = names.0.len().max(names.1.len()).max("engine".len());
+    println!(
+        "{:width$}  {:>5}  {:>5}  {:>5}  {:>7}",
+        "engine", "W", "D", "L", "score"
+    );
+    for (name, stats) in [(&names.0, *stats), (&names.1, stats.reversed())] {
+        println!(
+            "{name:width$}  {:>5}  {:>5}  {:>5}  {:>7}",
+            stats.wins,
+            stats.draws,
+            stats.losses,
+            format!("{}/{}", stats.points(), stats.games()),
+        );
+    }
+    if let Some(elo) = stats.elo() {
+        println!("elo difference: {elo} (95%)");
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    process::exit(1);
+}
@@ -0,0 +1,226 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use chess::arena::tournament::{Tournament, TournamentConfig, TournamentMode};
+use chess::arena::{read_openings, EngineSpec};
+use chess::board::Board;
+use chess::computer::engine::{SearchLimits, DEFAULT_NODES};
+use chess::game::pgn::parse_pgn;
+
+const USAGE: &str = "\
+usage: tournament --engine <engine> --engine <engine> [--engine <engine>...] [options]
+
+engines: random, full, uci:<path>, or a level: beginner, novice, intermediate, advanced, strong;
+full and the levels take a book after a comma: full,book=<file>[,bookdepth=<n>][,bookselection=best]
+
+options:
+  --mode <mode>       round-robin (default), gauntlet or swiss; a gauntlet pits the
+                      first engine against the others
+  --rounds <n>        rounds of a swiss tournament (default 5)
+  --games <n>         games per pairing, with alternating colors (default 2)
+  --openings <file>   FEN or EPD positions to start from, one per line
+  --fen <fen>         a single position to start from
+  --depth <plies>     search depth limit
+  --nodes <n>         node limit per move (default 20000 when no other limit is given)
+  --movetime <ms>     time per move; the games depend on the speed of the machine
+                      and can't be replayed
+  --max-moves <n>     adjudicate a draw after this many moves (default 200)
+  --event <name>      the PGN's Event tag (default \"Engine tournament\")
+  --pgn <file>        where the games go (default tournament.pgn); when the file already
+                      has games of the same tournament it goes on where it stopped
+  --seed <n>          seed of the first game (default 0)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args).unwrap_or_else(|err| exit_with(&err));
+
+    let mut engines = vec![];
+    let mut names: Vec<String> = vec![];
+    for spec in &options.engines {
+        let engine = spec
+            .build()
+            .unwrap_or_else(|err| exit_with(&err.to_string()));
+        // the names tell the players apart in the PGN, so they need to be unique
+        let mut name = engine.name();
+        let mut copy = 1;
+        while names.contains(&name) {
+            copy += 1;
+            name = format!("{} #{copy}", engine.name());
+        }
+        names.push(name);
+        engines.push(engine);
+    }
+
+    let finished = if Path::new(&options.pgn).exists() {
+        let text = fs::read_to_string(&options.pgn)
+            .unwrap_or_else(|err| exit_with(&format!("{}: {err}", options.pgn)));
+        let (text, dropped) = drop_unfinished(&text);
+        if dropped > 0 {
+            // otherwise the game played again would be in the PGN twice
+            eprintln!(
+                "{}: dropping {dropped} game(s) without a result; they are played again",
+                options.pgn
+            );
+            fs::write(&options.pgn, &text)
+                .unwrap_or_else(|err| exit_with(&format!("{}: {err}", options.pgn)));
+        }
+        parse_pgn(&text)
+    } else {
+        vec![]
+    };
+    let mut pgn = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&options.pgn)
+        .unwrap_or_else(|err| exit_with(&format!("{}: {err}", options.pgn)));
+
+    let tags = options.config.tags();
+    let mut tournament = Tournament::new(names.clone(), options.config);
+    if let Err(err) = tournament.check_resumable(&finished) {
+        eprintln!(
+            "{} holds games of another tournament: {err}; pick another --pgn to start over",
+            options.pgn
+        );
+        process::exit(1);
+    }
+    tournament.run(&mut engines, &finished, &mut |played, game| {
+        let (white, black) = (&names[played.white], &names[played.black]);
+        let Some(game) = game else {
+            println!(
+                "round {}: {white} vs {black}: {} (already played)",
+                played.round_tag(),
+                played.result
+            );
+            return;
+        };
+        let outcome = game.outcome().unwrap();
+        println!(
+            "round {}: {white} vs {black}: {} ({})",
+            played.round_tag(),
+            outcome.result,
+            outcome.termination
+        );
+        let round = played.round_tag();
+        let mut game_tags = vec![
+            ("Round", round.as_str()),
+            ("White", white),
+            ("Black", black),
+        ];
+        game_tags.extend(tags.iter().map(|(tag, value)| (*tag, value.as_str())));
+        let text = game.to_pgn(&game_tags);
+        if let Err(err) = writeln!(pgn, "{text}") {
+            exit_with(&format!("couldn't write the PGN: {err}"));
+        }
+    });
+
+    println!();
+    print!("{}", tournament.crosstable());
+}
+
+struct Options {
+    engines: Vec<EngineSpec>,
+    config: TournamentConfig,
+    pgn: String,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut engines = vec![];
+    let mut mode = "round-robin".to_string();
+    let mut rounds = 5;
+    let mut config = TournamentConfig {
+        event: "Engine tournament".to_string(),
+        mode: TournamentMode::RoundRobin,
+        games_per_pairing: 2,
+        openings: vec![],
+        limits: SearchLimits::default(),
+        max_plies: 400,
+        seed: 0,
+    };
+    let mut pgn = "tournament.pgn".to_string();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{arg} needs a number, not \"{value}\""))
+        };
+        match arg.as_str() {
+            "--engine" => engines.push(value.parse().map_err(|err| format!("{err}"))?),
+            "--mode" => mode = value.clone(),
+            "--rounds" => rounds = number()? as usize,
+            "--games" => config.games_per_pairing = number()?.max(1) as usize,
+            "--openings" => {
+                let text = fs::read_to_string(value).map_err(|err| format!("{value}: {err}"))?;
+                config.openings = read_openings(&text).map_err(|err| format!("{value}: {err}"))?;
+            }
+            "--fen" => {
+                config.openings = vec![Board::parse_fen(value).map_err(|err| err.to_string())?]
+            }
+            "--depth" => config.limits.depth = Some(number()?.min(u8::MAX as u64) as u8),
+            "--nodes" => config.limits.nodes = Some(number()?),
+            "--movetime" => config.limits.movetime = Some(Duration::from_millis(number()?)),
+            "--max-moves" => config.max_plies = number()? as usize * 2,
+            "--event" => config.event = value.clone(),
+            "--pgn" => pgn = value.clone(),
+            "--seed" => config.seed = number()?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    config.mode = match mode.as_str() {
+        "round-robin" => TournamentMode::RoundRobin,
+        "gauntlet" => TournamentMode::Gauntlet,
+        "swiss" => TournamentMode::Swiss { rounds },
+        _ => return Err(format!("unknown mode {mode}")),
+    };
+    let limits = &mut config.limits;
+    if limits.depth.is_none() && limits.nodes.is_none() && limits.movetime.is_none() {
+        limits.nodes = Some(DEFAULT_NODES);
+    }
+    if engines.len() < 2 {
+        return Err("a tournament needs at least two engines".to_string());
+    }
+    Ok(Options {
+        engines,
+        config,
+        pgn,
+    })
+}
+
+/// the PGN without the games that have no result, eg. one cut off when the tournament was
+/// interrupted, and how many were left out; every game starts with its Event tag
+fn drop_unfinished(text: &str) -> (String, usize) {
+    let mut games: Vec<String> = vec![];
+    for line in text.lines() {
+        if games.is_empty() || line.starts_with("[Event ") {
+            games.push(String::new());
+        }
+        let game = games.last_mut().unwrap();
+        game.push_str(line);
+        game.push('\n');
+    }
+    let mut kept = String::new();
+    let mut dropped = 0;
+    for game in games {
+        if parse_pgn(&game).iter().all(|parsed| parsed.result.is_some()) {
+            kept += &game;
+        } else {
+            dropped += 1;
+        }
+    }
+    (kept, dropped)
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    process::exit(1);
+}
@@ -0,0 +1,5 @@
+use std::io;
+
+fn main() {
+    chess::xboard::run(io::stdin().lock(), io::stdout());
+}
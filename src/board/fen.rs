@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::board::pieces::{Piece, PieceColor, PieceKind};
+use crate::board::position::{Position, PositionNotation};
+use crate::board::{Board, CastlingFiles};
+use crate::Turn;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// the piece placement doesn't describe 8 ranks of 8 squares
+    InvalidPlacement,
+    InvalidTurn,
+    InvalidCastling,
+    InvalidEnPassant,
+    /// each side needs exactly one king
+    InvalidKings,
+    InvalidHalfmoveClock,
+    InvalidFullmoveNumber,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            FenError::InvalidPlacement => "invalid piece placement",
+            FenError::InvalidTurn => "invalid side to move",
+            FenError::InvalidCastling => "invalid castling rights",
+            FenError::InvalidEnPassant => "invalid en-passant square",
+            FenError::InvalidKings => "each side needs exactly one king",
+            FenError::InvalidHalfmoveClock => "invalid halfmove clock",
+            FenError::InvalidFullmoveNumber => "invalid fullmove number",
+        };
+        write!(f, "invalid FEN: {msg}")
+    }
+}
+
+impl Board {
+    /// parses a complete FEN; unlike from_fen it also reads the side to move,
+    /// the castling rights and the en-passant square. missing fields take their defaults
+    pub fn parse_fen(fen: &str) -> Result<(Board, Turn), FenError> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or(FenError::InvalidPlacement)?;
+        let turn = fields.next().unwrap_or("w");
+        let castling = fields.next().unwrap_or("-");
+        let en_passant = fields.next().unwrap_or("-");
+
+        let mut cells: HashMap<Position, Option<Piece>> = HashMap::new();
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::InvalidPlacement);
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            let i = 7 - row as i8;
+            let mut j = 0_i8;
+            for c in rank.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    if !(1..=8).contains(&digit) {
+                        return Err(FenError::InvalidPlacement);
+                    }
+                    for _ in 0..digit {
+                        cells.insert(Position::new(i, j), None);
+                        j += 1;
+                    }
+                    continue;
+                }
+                let color = if c.is_ascii_uppercase() {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                let kind = match c.to_ascii_lowercase() {
+                    'p' => PieceKind::Pawn,
+                    'n' => PieceKind::Knight,
+                    'b' => PieceKind::Bishop,
+                    'r' => PieceKind::Rook,
+                    'q' => PieceKind::Queen,
+                    'k' => PieceKind::King,
+                    _ => return Err(FenError::InvalidPlacement),
+                };
+                cells.insert(Position::new(i, j), Some(Piece::new(kind, color)));
+                j += 1;
+            }
+            if j != 8 {
+                return Err(FenError::InvalidPlacement);
+            }
+        }
+        let mut board = Board::new(cells);
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            let kings = board
+                .pieces()
+                .filter(|(_, piece)| piece.kind == PieceKind::King && piece.color == color)
+                .count();
+            if kings != 1 {
+                return Err(FenError::InvalidKings);
+            }
+        }
+
+        let turn = match turn {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::InvalidTurn),
+        };
+
+        // the board has no castling rights of its own; they follow from whether the king
+        // and the rooks have moved, so every piece that lost its right is marked as moved.
+        // KQkq stand for the outermost rooks; Chess960 positions may also name the file of
+        // the rook, eg. "HAha"
+        if castling != "-"
+            && !castling
+                .chars()
+                .all(|c| "KQkq".contains(c) || ('A'..='H').contains(&c.to_ascii_uppercase()))
+        {
+            return Err(FenError::InvalidCastling);
+        }
+        let mut files = CastlingFiles::STANDARD;
+        let mut rights = vec![];
+        for (color, c) in castling.chars().filter(|c| *c != '-').map(|c| {
+            let color = if c.is_ascii_uppercase() {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            (color, c.to_ascii_uppercase())
+        }) {
+            let i = Position::new(0, 0).adaptive(color).i();
+            let is_own = |kind: PieceKind, j: i8| {
+                matches!(
+                    board.look_up_cell(Position::new(i, j)),
+                    Some(Some(piece)) if piece.kind == kind && piece.color == color
+                )
+            };
+            // a right without its king or rook on the back rank is left out
+            let Some(king_j) = (0..8).find(|&j| is_own(PieceKind::King, j)) else {
+                continue;
+            };
+            let rook_j = match c {
+                'K' => (king_j + 1..8).rev().find(|&j| is_own(PieceKind::Rook, j)),
+                'Q' => (0..king_j).find(|&j| is_own(PieceKind::Rook, j)),
+                file => Some(file as i8 - 'A' as i8).filter(|&j| is_own(PieceKind::Rook, j)),
+            };
+            let Some(rook_j) = rook_j else {
+                continue;
+            };
+            rights.push((color, king_j, rook_j));
+        }
+        // both sides castle from the same files; a Chess960 position mirrors white's back rank
+        for (n, &(_, king_j, rook_j)) in rights.iter().enumerate() {
+            if n > 0 && king_j != files.king {
+                return Err(FenError::InvalidCastling);
+            }
+            files.king = king_j;
+            let rook_file = if rook_j > king_j {
+                &mut files.king_side_rook
+            } else {
+                &mut files.queen_side_rook
+            };
+            if rights[..n].iter().any(|&(_, _, other)| {
+                (other > king_j) == (rook_j > king_j) && other != rook_j
+            }) {
+                return Err(FenError::InvalidCastling);
+            }
+            *rook_file = rook_j;
+        }
+        board.castling_files = files;
+        for color in [PieceColor::White, PieceColor::Black] {
+            let keeps_right = |j: i8| {
+                rights
+                    .iter()
+                    .any(|&(c, king_j, rook_j)| c == color && (j == king_j || j == rook_j))
+            };
+            for j in [files.king, files.king_side_rook, files.queen_side_rook] {
+                let pos = Position::new(0, j).adaptive(color);
+                if let Some(Some(piece)) = board.look_up_mut_cell(pos) {
+                    if piece.color == color && !keeps_right(j) {
+                        piece.moved = true;
+                    }
+                }
+            }
+        }
+
+        if en_passant != "-" {
+            let target = Position::from_notation(en_passant).ok_or(FenError::InvalidEnPassant)?;
+            // the board remembers the pawn that just moved two squares, not the square behind it
+            let pawn_pos = match turn {
+                PieceColor::White if target.i() == 5 => Position::new(4, target.j()),
+                PieceColor::Black if target.i() == 2 => Position::new(3, target.j()),
+                _ => return Err(FenError::InvalidEnPassant),
+            };
+            match board.look_up_cell(pawn_pos) {
+                Some(Some(piece)) if piece.kind == PieceKind::Pawn && piece.color != turn => {
+                    board.passant_pos = Some(pawn_pos);
+                    board.passant_tracker = 1;
+                }
+                _ => return Err(FenError::InvalidEnPassant),
+            }
+        }
+
+        Ok((board, turn))
+    }
+
+    /// the FEN of the board with `turn` to move; the board doesn't count moves
+    /// so the move counters are always "0 1"; a Game knows the real ones
+    pub fn to_fen(&self, turn: Turn) -> String {
+        let mut placement = String::new();
+        for i in (0..8).rev() {
+            let mut empty = 0;
+            for j in 0..8 {
+                match self.look_up_cell(Position::new(i, j)).unwrap() {
+                    None => empty += 1,
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let c = match piece.kind {
+                            PieceKind::Pawn => 'p',
+                            PieceKind::Knight => 'n',
+                            PieceKind::Bishop => 'b',
+                            PieceKind::Rook => 'r',
+                            PieceKind::Queen => 'q',
+                            PieceKind::King => 'k',
+                        };
+                        placement.push(match piece.color {
+                            PieceColor::White => c.to_ascii_uppercase(),
+                            PieceColor::Black => c,
+                        });
+                    }
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if i > 0 {
+                placement.push('/');
+            }
+        }
+
+        let turn_str = match turn {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+
+        // the rooks are named by their file when there's another rook further out, as in
+        // Shredder-FEN; otherwise by the side, as usual
+        let rights = self.castling_rights();
+        let files = self.castling_files;
+        let side = |color: PieceColor, rook_j: i8, allowed: bool| {
+            let i = Position::new(0, 0).adaptive(color).i();
+            let outer = if rook_j > files.king {
+                rook_j + 1..8
+            } else {
+                0..rook_j
+            };
+            let is_outermost = !outer.into_iter().any(|j| {
+                matches!(
+                    self.look_up_cell(Position::new(i, j)),
+                    Some(Some(piece)) if piece.kind == PieceKind::Rook && piece.color == color
+                )
+            });
+            let c = match (is_outermost, rook_j > files.king) {
+                (true, true) => 'K',
+                (true, false) => 'Q',
+                (false, _) => (b'A' + rook_j as u8) as char,
+            };
+            allowed.then_some(match color {
+                PieceColor::White => c,
+                PieceColor::Black => c.to_ascii_lowercase(),
+            })
+        };
+        let mut castling: String = [
+            side(PieceColor::White, files.king_side_rook, rights.white_king_side),
+            side(PieceColor::White, files.queen_side_rook, rights.white_queen_side),
+            side(PieceColor::Black, files.king_side_rook, rights.black_king_side),
+            side(PieceColor::Black, files.queen_side_rook, rights.black_queen_side),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.passant_pos {
+            // the square the pawn skipped over
+            Some(pos) => {
+                let behind = match turn {
+                    PieceColor::White => 1,
+                    PieceColor::Black => -1,
+                };
+                Position::new(pos.i() + behind, pos.j()).to_notation()
+            }
+            None => "-".to_string(),
+        };
+
+        format!("{placement} {turn_str} {castling} {en_passant} 0 1")
+    }
+}
+
+/// the number of Chess960 starting positions
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/// the FEN of Chess960 starting position `number` in Scharnagl's numbering, where 518 is the
+/// standard position; both sides can castle on both sides
+pub fn chess960_fen(number: u16) -> String {
+    let mut back_rank = [None; 8];
+    let mut n = number % CHESS960_POSITIONS;
+    // the bishops go on squares of different colors
+    back_rank[(n % 4 * 2 + 1) as usize] = Some('b');
+    n /= 4;
+    back_rank[(n % 4 * 2) as usize] = Some('b');
+    n /= 4;
+    let mut place = |piece, nth: u16| {
+        let j = (0..8)
+            .filter(|&j| back_rank[j].is_none())
+            .nth(nth as usize)
+            .unwrap();
+        back_rank[j] = Some(piece);
+    };
+    place('q', n % 6);
+    n /= 6;
+    // the ten ways to put two knights on the five squares left; the second is counted
+    // among the squares still free after the first
+    let (first, second) = [
+        (0, 0),
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (1, 1),
+        (1, 2),
+        (1, 3),
+        (2, 2),
+        (2, 3),
+        (3, 3),
+    ][n as usize];
+    place('n', first);
+    place('n', second);
+    // the king goes between the rooks
+    for piece in ['r', 'k', 'r'] {
+        place(piece, 0);
+    }
+
+    let black: String = back_rank.iter().map(|piece| piece.unwrap()).collect();
+    format!(
+        "{black}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        black.to_uppercase()
+    )
+}
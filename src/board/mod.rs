@@ -3,8 +3,11 @@ use crate::board::position::Position;
 use crate::Turn;
 use std::collections::HashMap;
 
+pub mod fen;
 pub mod pieces;
 pub mod position;
+pub mod san;
+mod zobrist;
 
 pub const BOARD_SIZE: u8 = 64;
 
@@ -17,6 +20,33 @@ pub struct Board {
     /// to support en-passant rule
     passant_pos: Option<Position>,
     passant_tracker: u8,
+    castling_files: CastlingFiles,
+}
+
+/// the files the king and the rooks castle from; in a Chess960 game they are the files
+/// the pieces started on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingFiles {
+    pub king: i8,
+    pub king_side_rook: i8,
+    pub queen_side_rook: i8,
+}
+
+impl CastlingFiles {
+    pub const STANDARD: CastlingFiles = CastlingFiles {
+        king: 4,
+        king_side_rook: 7,
+        queen_side_rook: 0,
+    };
+}
+
+/// which castlings are still possible; only depends on whether the kings and rooks moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
 }
 
 impl Board {
@@ -24,12 +54,24 @@ impl Board {
         self.is_safe_unchecked(self.look_up_king_pos(color), color)
     }
 
-    pub fn get_all_pieces_pos_by_color(&self, color: PieceColor) -> Vec<Position> {
+    /// iterates over every occupied cell of the board
+    pub fn pieces(&self) -> impl Iterator<Item = (Position, &Piece)> {
         self.cells
+            .iter()
+            .filter_map(|(pos, cell)| cell.as_ref().map(|piece| (*pos, piece)))
+    }
+
+    /// the positions are sorted; the hashmap's order changes from run to run
+    /// and the computer players need to be reproducible
+    pub fn get_all_pieces_pos_by_color(&self, color: PieceColor) -> Vec<Position> {
+        let mut positions: Vec<Position> = self
+            .cells
             .iter()
             .filter(|(_, cell)| cell.is_some() && cell.as_ref().unwrap().color == color)
             .map(|(pos, _)| *pos)
-            .collect()
+            .collect();
+        positions.sort();
+        positions
     }
 
     pub fn is_checkmate_for(&self, color: PieceColor) -> bool {
@@ -56,6 +98,7 @@ impl Board {
             cells,
             passant_pos: None,
             passant_tracker: 0,
+            castling_files: CastlingFiles::STANDARD,
         }
     }
     pub fn from_fen(s: &str) -> Self {
@@ -117,8 +160,51 @@ impl Board {
             cells,
             passant_pos: None,
             passant_tracker: 0,
+            castling_files: CastlingFiles::STANDARD,
         }
     }
+    pub fn castling_rights(&self) -> CastlingRights {
+        let is_unmoved = |pos: Position, kind: PieceKind, color: PieceColor| {
+            matches!(
+                self.look_up_cell(pos),
+                Some(Some(piece)) if piece.kind == kind && piece.color == color && !piece.moved
+            )
+        };
+        let files = self.castling_files;
+        let can_castle = |color: PieceColor, rook_j: i8| {
+            let i = Position::new(0, 0).adaptive(color).i();
+            is_unmoved(Position::new(i, files.king), PieceKind::King, color)
+                && is_unmoved(Position::new(i, rook_j), PieceKind::Rook, color)
+        };
+        CastlingRights {
+            white_king_side: can_castle(PieceColor::White, files.king_side_rook),
+            white_queen_side: can_castle(PieceColor::White, files.queen_side_rook),
+            black_king_side: can_castle(PieceColor::Black, files.king_side_rook),
+            black_queen_side: can_castle(PieceColor::Black, files.queen_side_rook),
+        }
+    }
+
+    pub fn castling_files(&self) -> CastlingFiles {
+        self.castling_files
+    }
+
+    /// whether the king and the rooks castle from other files than in standard chess; the
+    /// king then castles by moving onto its own rook, as in UCI_Chess960
+    pub fn is_chess960(&self) -> bool {
+        self.castling_files != CastlingFiles::STANDARD
+    }
+
+    /// whether moving from `fr` to `to` castles; the king moves two files or, in Chess960,
+    /// onto its own rook
+    pub fn is_castling(&self, fr: Position, to: Position) -> bool {
+        let Some(Some(king)) = self.look_up_cell(fr) else {
+            return false;
+        };
+        king.kind == PieceKind::King
+            && (fr.j().abs_diff(to.j()) > 1
+                || matches!(self.look_up_cell(to), Some(Some(rook)) if rook.color == king.color))
+    }
+
     pub fn is_safe_for_king(&self, pos: Position, color: PieceColor) -> bool {
         self.look_up_king_pos(color) != pos
     }
@@ -135,6 +221,8 @@ pub enum BoardMoveError {
     NoPieceOnCell,
     /// an illegal move; eg: out of range
     Illegal,
+    /// the game already ended; eg. in a checkmate or a draw by repetition
+    GameOver,
 }
 
 impl Board {
@@ -186,6 +274,17 @@ impl Board {
         turn: &mut Turn,
         fr: Position,
         to: Position,
+    ) -> Result<(), BoardMoveError> {
+        self.move_piece_and_promote(turn, fr, to, PieceKind::Queen)
+    }
+
+    /// same as move_piece but a pawn reaching the last rank becomes `promotion` instead of a queen
+    pub fn move_piece_and_promote(
+        &mut self,
+        turn: &mut Turn,
+        fr: Position,
+        to: Position,
+        promotion: PieceKind,
     ) -> Result<(), BoardMoveError> {
         let fr_cell = self.look_up_cell(fr).expect("No such cell exists");
 
@@ -199,27 +298,55 @@ impl Board {
             return Err(BoardMoveError::NotYourTurn);
         }
 
-        if pieces::moves::get_legal_moves(fr, self, turn).contains(&to) {
-            // to see if self.passant_pos changes after move_force
-            // if it actually changes to something else then we have
-            // to set the self.passant_tracker back to 1
-            let before_pos = self.passant_pos;
-            pieces::moves::move_force(self, fr, to);
-            *turn = match turn {
-                Turn::White => Turn::Black,
-                Turn::Black => Turn::White,
-            };
-            if self.passant_tracker == 1 && before_pos == self.passant_pos {
-                self.passant_pos = None;
-                self.passant_tracker = 0;
-            }
+        if matches!(promotion, PieceKind::King | PieceKind::Pawn) {
+            return Err(BoardMoveError::Illegal);
+        }
 
-            if self.passant_pos.is_some() {
-                self.passant_tracker = 1;
-            }
+        if pieces::moves::get_legal_moves(fr, self, turn).contains(&to) {
+            self.move_piece_and_promote_unchecked(turn, fr, to, promotion);
             Ok(())
         } else {
             Err(BoardMoveError::Illegal)
         }
     }
+
+    /// whether moving from `fr` to `to` takes a pawn to the last rank
+    pub fn is_promotion(&self, fr: Position, to: Position) -> bool {
+        matches!(
+            self.look_up_cell(fr),
+            Some(Some(piece)) if piece.kind == PieceKind::Pawn && to.adaptive(piece.color).i() == 7
+        )
+    }
+
+    /// same as move_piece but skips the legality checks;
+    /// useful when the move was already taken from get_legal_moves
+    pub(crate) fn move_piece_unchecked(&mut self, turn: &mut Turn, fr: Position, to: Position) {
+        self.move_piece_and_promote_unchecked(turn, fr, to, PieceKind::Queen)
+    }
+
+    pub(crate) fn move_piece_and_promote_unchecked(
+        &mut self,
+        turn: &mut Turn,
+        fr: Position,
+        to: Position,
+        promotion: PieceKind,
+    ) {
+        // to see if self.passant_pos changes after move_force
+        // if it actually changes to something else then we have
+        // to set the self.passant_tracker back to 1
+        let before_pos = self.passant_pos;
+        pieces::moves::move_force(self, fr, to, promotion);
+        *turn = match turn {
+            Turn::White => Turn::Black,
+            Turn::Black => Turn::White,
+        };
+        if self.passant_tracker == 1 && before_pos == self.passant_pos {
+            self.passant_pos = None;
+            self.passant_tracker = 0;
+        }
+
+        if self.passant_pos.is_some() {
+            self.passant_tracker = 1;
+        }
+    }
 }
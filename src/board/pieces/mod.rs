@@ -17,7 +17,7 @@ impl Piece {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum PieceKind {
     King = 5,
     Queen = 4,
@@ -51,21 +51,6 @@ pub fn get_legal_moves(piece_pos: Position, board: &Board, turn: &Turn) -> Vec<P
                 possible_move,
             );
 
-            if let MoveKind::Castle = move_kind {
-                let padding = if piece_pos.j() < possible_move.j() {
-                    -1
-                } else {
-                    1
-                };
-                // if king is in check or there is an enemy piece cutting off the way for the king
-                if !board.is_safe_unchecked(piece_pos, color)
-                    || !legal_moves
-                        .contains(&(possible_move.i(), possible_move.j() + padding).into())
-                {
-                    continue;
-                }
-            }
-
             let cell = board.look_up_mut_cell(possible_move).unwrap();
             let mut target_cell: Cell = cell.take(); // saves the piece at the target position
 
@@ -100,10 +85,83 @@ pub fn get_legal_moves(piece_pos: Position, board: &Board, turn: &Turn) -> Vec<P
 
     let original_piece_cell = board.look_up_mut_cell(piece_pos).unwrap();
     *original_piece_cell = mover; // restores the original position of the piece
+    if *turn == color {
+        legal_moves.extend(get_castling_moves(piece_pos, &board));
+    }
     legal_moves
 }
 
+/// the castlings of the king at `king_pos`; the king moves two files towards the rook or,
+/// in Chess960, onto the rook
+///
+/// wherever the pieces start, they end up as in standard chess: the king on the g- or c-file
+/// and the rook next to it. every square either of them crosses has to be empty and the king
+/// can't castle out of, through or into check
+fn get_castling_moves(king_pos: Position, board: &Board) -> Vec<Position> {
+    let king = board.look_up_cell(king_pos).unwrap().as_ref().unwrap();
+    let color = king.color;
+    let files = board.castling_files();
+    let i = Position::new(0, 0).adaptive(color).i();
+    if king.kind != PieceKind::King
+        || king.moved
+        || king_pos != Position::new(i, files.king)
+        || !board.is_safe_unchecked(king_pos, color)
+    {
+        return vec![];
+    }
+
+    let mut castlings = vec![];
+    for (rook_j, king_to_j, rook_to_j) in [
+        (files.king_side_rook, 6, 5),
+        (files.queen_side_rook, 2, 3),
+    ] {
+        let rook_pos = Position::new(i, rook_j);
+        if !matches!(
+            board.look_up_cell(rook_pos),
+            Some(Some(rook)) if rook.kind == PieceKind::Rook && rook.color == color && !rook.moved
+        ) {
+            continue;
+        }
+        let squares = [files.king, rook_j, king_to_j, rook_to_j];
+        let (lo, hi) = (*squares.iter().min().unwrap(), *squares.iter().max().unwrap());
+        let is_path_free = (lo..=hi)
+            .filter(|&j| j != files.king && j != rook_j)
+            .all(|j| board.look_up_cell(Position::new(i, j)).unwrap().is_none());
+        if !is_path_free {
+            continue;
+        }
+
+        // the king and the rook are lifted off so they don't block the attacks on the way
+        let mut lifted = board.clone();
+        lifted.look_up_mut_cell(king_pos).unwrap().take();
+        lifted.look_up_mut_cell(rook_pos).unwrap().take();
+        let step = if king_to_j > files.king { 1 } else { -1 };
+        let mut j = files.king;
+        let mut is_way_safe = true;
+        while j != king_to_j {
+            j += step;
+            is_way_safe &= lifted.is_safe_unchecked(Position::new(i, j), color);
+        }
+        if !is_way_safe {
+            continue;
+        }
+
+        let to = if board.is_chess960() {
+            rook_pos
+        } else {
+            Position::new(i, king_to_j)
+        };
+        let mut after = board.clone();
+        move_force(&mut after, king_pos, to, PieceKind::Queen);
+        if after.is_king_safe(color) {
+            castlings.push(to);
+        }
+    }
+    castlings
+}
+
 /// returns the legal moves without considering king's safety
+#[allow(clippy::needless_return)]
 pub fn get_legal_moves_unchecked(piece_pos: Position, board: &Board) -> Vec<Position> {
     let piece = board.look_up_cell(piece_pos).unwrap().as_ref().unwrap();
     let color = piece.color;
@@ -123,38 +181,6 @@ pub fn get_legal_moves_unchecked(piece_pos: Position, board: &Board) -> Vec<Posi
                 Position::new(i - 1, j + 1),
                 Position::new(i - 1, j - 1),
             ]);
-            if !piece.moved {
-                let king_side_rook_pos = Position::new(i, 7);
-                let queen_side_rook_pos = Position::new(i, 0);
-
-                let king_side_rook = board.look_up_cell(king_side_rook_pos).unwrap();
-                let queen_side_rook = board.look_up_cell(queen_side_rook_pos).unwrap();
-                let is_king_side_path_free = [Position::new(i, j + 1), Position::new(i, j + 2)]
-                    .into_iter()
-                    .map(|pos| board.look_up_cell(pos))
-                    .all(|cell| cell.unwrap().is_none());
-                let is_queen_side_path_free = [
-                    Position::new(i, j - 1),
-                    Position::new(i, j - 2),
-                    Position::new(i, j - 3),
-                ]
-                .into_iter()
-                .map(|pos| board.look_up_cell(pos))
-                .all(|cell| cell.unwrap().is_none());
-
-                if let Some(rook) = king_side_rook {
-                    if !rook.moved && is_king_side_path_free {
-                        selector.custom(vec![Position::new(i, j + 2)]);
-                    }
-                }
-
-                if let Some(rook) = queen_side_rook {
-                    if !rook.moved && is_queen_side_path_free {
-                        selector.custom(vec![Position::new(i, j - 2)]);
-                    }
-                }
-            }
-
             selector.build_positions()
         }
         PieceKind::Queen => {
@@ -254,7 +280,8 @@ pub fn get_legal_moves_unchecked(piece_pos: Position, board: &Board) -> Vec<Posi
 }
 
 // assume moving from "fr" to "to" is legal
-pub(crate) fn move_force(board: &mut Board, fr: Position, to: Position) {
+// a pawn reaching the last rank turns into "promotion"
+pub(crate) fn move_force(board: &mut Board, fr: Position, to: Position, promotion: PieceKind) {
     match get_move_kind(
         board.look_up_cell(fr).unwrap().as_ref().unwrap(),
         board.look_up_cell(to).unwrap().as_ref(),
@@ -262,33 +289,31 @@ pub(crate) fn move_force(board: &mut Board, fr: Position, to: Position) {
         to,
     ) {
         MoveKind::Castle => {
-            let padding = if fr.j() > to.j() { 1 } else { -1 };
-            let king_new_pos: Position = (to.i(), to.j()).into();
-            let rook_new_pos: Position = (fr.i(), king_new_pos.j() + padding).into();
-
-            {
-                let fr_cell = board.look_up_mut_cell(fr).unwrap();
-                let temp = fr_cell.take();
-
-                let to_cell = board.look_up_mut_cell(king_new_pos).unwrap();
-                *to_cell = temp;
-                to_cell.as_mut().unwrap().moved = true;
-            }
-
-            {
-                let rook_pos = Position::new(fr.i(), if padding > 0 { 0 } else { 7 });
-                let fr_cell = board.look_up_mut_cell(rook_pos).unwrap();
-                let temp = fr_cell.take();
-                let to_cell = board.look_up_mut_cell(rook_new_pos).unwrap();
-                *to_cell = temp;
-                to_cell.as_mut().unwrap().moved = true;
-            }
+            // the king and the rook may have to swap squares; both are lifted off first
+            let files = board.castling_files();
+            let (rook_j, king_to_j, rook_to_j) = if to.j() > fr.j() {
+                (files.king_side_rook, 6, 5)
+            } else {
+                (files.queen_side_rook, 2, 3)
+            };
+            let mut king = board.look_up_mut_cell(fr).unwrap().take().unwrap();
+            let mut rook = board
+                .look_up_mut_cell(Position::new(fr.i(), rook_j))
+                .unwrap()
+                .take()
+                .unwrap();
+            king.moved = true;
+            rook.moved = true;
+            *board.look_up_mut_cell(Position::new(fr.i(), king_to_j)).unwrap() = Some(king);
+            *board.look_up_mut_cell(Position::new(fr.i(), rook_to_j)).unwrap() = Some(rook);
         }
         MoveKind::Promote => {
             let fr_cell = board.look_up_mut_cell(fr).unwrap().take();
             let color = fr_cell.unwrap().color;
             let to_cell = board.look_up_mut_cell(to).unwrap();
-            *to_cell = Some(Piece::new(PieceKind::Queen, color))
+            let mut promoted = Piece::new(promotion, color);
+            promoted.moved = true; // a promoted rook can't castle
+            *to_cell = Some(promoted)
         }
         MoveKind::Regular => {
             let fr_cell = board.look_up_mut_cell(fr).unwrap();
@@ -329,7 +354,9 @@ fn get_move_kind(
     fr: Position,
     to: Position,
 ) -> MoveKind {
-    if piece_fr.kind == PieceKind::King && fr.j().abs_diff(to.j()) > 1 {
+    if piece_fr.kind == PieceKind::King
+        && (fr.j().abs_diff(to.j()) > 1 || piece_to.is_some_and(|piece| piece.color == piece_fr.color))
+    {
         return MoveKind::Castle;
     }
     if piece_fr.kind == Pawn && to.adaptive(piece_fr.color).i() == 7 {
@@ -341,3 +368,143 @@ fn get_move_kind(
     }
      MoveKind::Regular
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::fen::{chess960_fen, CHESS960_POSITIONS, STARTING_FEN};
+    use crate::board::position::PositionNotation;
+
+    fn pos(notation: &str) -> Position {
+        Position::from_notation(notation).unwrap()
+    }
+
+    /// plays `moves` from `fen` and returns the FEN reached
+    fn play(fen: &str, moves: &[(&str, &str)]) -> String {
+        let (mut board, mut turn) = Board::parse_fen(fen).unwrap();
+        for (fr, to) in moves {
+            board.move_piece(&mut turn, pos(fr), pos(to)).unwrap();
+        }
+        board.to_fen(turn)
+    }
+
+    #[test]
+    fn the_king_castles_two_files_in_standard_chess() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let (board, turn) = Board::parse_fen(fen).unwrap();
+        assert!(!board.is_chess960());
+        let king_moves = get_legal_moves(pos("e1"), &board, &turn);
+        assert!(king_moves.contains(&pos("g1")) && king_moves.contains(&pos("c1")));
+        assert!(!king_moves.contains(&pos("h1")) && !king_moves.contains(&pos("a1")));
+
+        assert_eq!(play(fen, &[("e1", "g1")]), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 0 1");
+        assert_eq!(play(fen, &[("e1", "c1")]), "r3k2r/8/8/8/8/8/8/2KR3R b kq - 0 1");
+    }
+
+    #[test]
+    fn the_king_cant_castle_out_of_through_or_into_check() {
+        for fen in [
+            "k3r3/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+            "k4r2/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+            "k5r1/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+        ] {
+            let (board, turn) = Board::parse_fen(fen).unwrap();
+            assert!(!get_legal_moves(pos("e1"), &board, &turn).contains(&pos("g1")), "{fen}");
+        }
+        // only the king has to be safe; the rook may cross an attacked square
+        let (board, turn) = Board::parse_fen("kr6/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert!(get_legal_moves(pos("e1"), &board, &turn).contains(&pos("c1")));
+    }
+
+    #[test]
+    fn only_an_unmoved_king_on_its_file_castles() {
+        // an unmoved king off the e-file, as Board::from_fen sets up any placement
+        let board = Board::from_fen("k7/8/8/8/8/8/8/3K3R");
+        assert!(!board.is_chess960());
+        assert!(!board.castling_rights().white_king_side);
+        let king_moves = get_legal_moves(pos("d1"), &board, &PieceColor::White);
+        assert!(!king_moves.contains(&pos("f1")) && !king_moves.contains(&pos("h1")));
+        let mut turn = PieceColor::White;
+        assert!(board.clone().move_piece(&mut turn, pos("d1"), pos("f1")).is_err());
+
+        // a king back on its file after moving
+        let (mut board, mut turn) = Board::parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for (fr, to) in [("e1", "e2"), ("e8", "e7"), ("e2", "e1"), ("e7", "e8")] {
+            board.move_piece(&mut turn, pos(fr), pos(to)).unwrap();
+        }
+        assert_eq!(board.to_fen(turn), "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1");
+        let king_moves = get_legal_moves(pos("e1"), &board, &turn);
+        assert!(!king_moves.contains(&pos("g1")) && !king_moves.contains(&pos("c1")));
+    }
+
+    #[test]
+    fn the_standard_chess960_position_is_the_standard_start() {
+        assert_eq!(chess960_fen(518), STARTING_FEN);
+        let (board, _) = Board::parse_fen(&chess960_fen(518)).unwrap();
+        assert!(!board.is_chess960());
+
+        for number in 0..CHESS960_POSITIONS {
+            let fen = chess960_fen(number);
+            let (board, turn) = Board::parse_fen(&fen).unwrap();
+            let rights = board.castling_rights();
+            assert!(rights.white_king_side && rights.black_queen_side, "{fen}");
+            assert_eq!(board.to_fen(turn), fen);
+        }
+    }
+
+    #[test]
+    fn chess960_castling_moves_the_king_onto_its_rook() {
+        // the king on g1 between rooks on b1 and h1
+        let fen = "1r4kr/pppppppp/8/8/8/8/PPPPPPPP/1R4KR w KQkq - 0 1";
+        let (board, turn) = Board::parse_fen(fen).unwrap();
+        assert!(board.is_chess960());
+        let king_moves = get_legal_moves(pos("g1"), &board, &turn);
+        assert!(king_moves.contains(&pos("h1")) && king_moves.contains(&pos("b1")));
+        assert!(board.is_castling(pos("g1"), pos("h1")));
+        assert_eq!(board.to_san(turn, pos("g1"), pos("b1"), PieceKind::Queen), "O-O-O");
+
+        // the king stays where it is and the rook jumps over to f1
+        assert_eq!(
+            play(fen, &[("g1", "h1")]),
+            "1r4kr/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b kq - 0 1"
+        );
+        assert_eq!(
+            play(fen, &[("g1", "b1")]),
+            "1r4kr/pppppppp/8/8/8/8/PPPPPPPP/2KR3R b kq - 0 1"
+        );
+        assert_eq!(
+            play(fen, &[("g1", "b1"), ("g8", "h8")]),
+            "1r3rk1/pppppppp/8/8/8/8/PPPPPPPP/2KR3R w - - 0 1"
+        );
+    }
+
+    #[test]
+    fn chess960_castling_needs_every_square_on_the_way_free() {
+        // the king on b1 castles to c1 and the rook from a1 to d1; the knight on e1 is in
+        // the way of neither, the one on d1 is
+        let (board, turn) = Board::parse_fen("rk6/8/8/8/8/8/8/RK2N3 w Qq - 0 1").unwrap();
+        assert_eq!(board.castling_files().king, 1);
+        assert!(get_legal_moves(pos("b1"), &board, &turn).contains(&pos("a1")));
+        let (board, turn) = Board::parse_fen("rk6/8/8/8/8/8/8/RK1N4 w Qq - 0 1").unwrap();
+        assert!(!get_legal_moves(pos("b1"), &board, &turn).contains(&pos("a1")));
+        // the rook lands on an attacked square, the king doesn't
+        let (board, turn) = Board::parse_fen("rk1r4/8/8/8/8/8/8/RK6 w Qq - 0 1").unwrap();
+        assert!(get_legal_moves(pos("b1"), &board, &turn).contains(&pos("a1")));
+        let (board, turn) = Board::parse_fen("rkr5/8/8/8/8/8/8/RK6 w Qq - 0 1").unwrap();
+        assert!(!get_legal_moves(pos("b1"), &board, &turn).contains(&pos("a1")));
+    }
+
+    #[test]
+    fn castling_rooks_may_be_named_by_their_file() {
+        let fen = "1r4kr/pppppppp/8/8/8/8/PPPPPPPP/1R4KR w HBhb - 0 1";
+        assert_eq!(
+            Board::parse_fen(fen).unwrap().0.to_fen(PieceColor::White),
+            "1r4kr/pppppppp/8/8/8/8/PPPPPPPP/1R4KR w KQkq - 0 1"
+        );
+        // a rook further out that can't castle any more
+        let fen = "rr4k1/8/8/8/8/8/8/RR4K1 w Bb - 0 1";
+        let (board, turn) = Board::parse_fen(fen).unwrap();
+        assert_eq!(board.castling_files().queen_side_rook, 1);
+        assert_eq!(board.to_fen(turn), fen);
+    }
+}
@@ -2,15 +2,16 @@ pub mod selector;
 
 use crate::board::pieces::PieceColor;
 
-#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq)]
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, PartialOrd, Ord)]
 pub struct Position {
     i: i8,
     j: i8,
 }
 
-trait PositionNotation<'a> {
-    fn from_notation(notation: &str) -> Self;
-    fn to_notation(self) -> &'a str;
+/// the algebraic names of the squares; "a1" is (0, 0) and "h8" is (7, 7)
+pub trait PositionNotation: Sized {
+    fn from_notation(notation: &str) -> Option<Self>;
+    fn to_notation(self) -> String;
 }
 
 impl Position {
@@ -56,12 +57,20 @@ impl From<(i8, i8)> for Position {
     }
 }
 
-impl<'a> PositionNotation<'a> for Position {
-    fn from_notation(_notation: &str) -> Self {
-        todo!()
+impl PositionNotation for Position {
+    fn from_notation(notation: &str) -> Option<Self> {
+        let mut chars = notation.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None;
+        }
+        Some(Position::new(rank as i8 - '1' as i8, file as i8 - 'a' as i8))
     }
 
-    fn to_notation(self) -> &'a str {
-        todo!()
+    fn to_notation(self) -> String {
+        let file = (b'a' + self.j as u8) as char;
+        let rank = (b'1' + self.i as u8) as char;
+        format!("{file}{rank}")
     }
 }
@@ -0,0 +1,132 @@
+use crate::board::pieces::{moves, PieceKind};
+use crate::board::position::{Position, PositionNotation};
+use crate::board::Board;
+use crate::Turn;
+
+impl Board {
+    /// the move in standard algebraic notation; eg. "Nf3", "exd5", "e8=Q+" or "O-O-O#"
+    ///
+    /// the move is assumed to be legal; `promotion` only matters for pawns reaching the last rank
+    pub fn to_san(&self, turn: Turn, fr: Position, to: Position, promotion: PieceKind) -> String {
+        let piece = self
+            .look_up_cell(fr)
+            .and_then(|cell| cell.as_ref())
+            .expect("a move starts on a piece");
+        let is_capture = matches!(self.look_up_cell(to), Some(Some(_)))
+            || (piece.kind == PieceKind::Pawn && fr.j() != to.j());
+
+        let mut san = if self.is_castling(fr, to) {
+            if to.j() > fr.j() { "O-O" } else { "O-O-O" }.to_string()
+        } else if piece.kind == PieceKind::Pawn {
+            let mut san = String::new();
+            if is_capture {
+                san.push(file_char(fr));
+                san.push('x');
+            }
+            san += &to.to_notation();
+            if self.is_promotion(fr, to) {
+                san.push('=');
+                san.push(piece_char(promotion));
+            }
+            san
+        } else {
+            let mut san = piece_char(piece.kind).to_string();
+            // other pieces of the same kind that could go to the same square
+            let rivals: Vec<Position> = self
+                .get_all_pieces_pos_by_color(turn)
+                .into_iter()
+                .filter(|&pos| pos != fr)
+                .filter(|&pos| {
+                    matches!(self.look_up_cell(pos), Some(Some(other)) if other.kind == piece.kind)
+                })
+                .filter(|&pos| moves::get_legal_moves(pos, self, &turn).contains(&to))
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|pos| pos.j() != fr.j()) {
+                    san.push(file_char(fr));
+                } else if rivals.iter().all(|pos| pos.i() != fr.i()) {
+                    san.push(rank_char(fr));
+                } else {
+                    san += &fr.to_notation();
+                }
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san += &to.to_notation();
+            san
+        };
+
+        let mut after = self.clone();
+        let mut after_turn = turn;
+        after.move_piece_and_promote_unchecked(&mut after_turn, fr, to, promotion);
+        if !after.is_king_safe(after_turn) {
+            san.push(if after.has_legal_moves(after_turn) {
+                '+'
+            } else {
+                '#'
+            });
+        }
+        san
+    }
+
+    /// finds the legal move written in standard algebraic notation; check marks and
+    /// annotations like "!?" are optional
+    pub fn parse_san(&self, turn: Turn, san: &str) -> Option<(Position, Position, PieceKind)> {
+        let wanted = strip_suffixes(san).replace('0', "O");
+        for fr in self.get_all_pieces_pos_by_color(turn) {
+            for to in moves::get_legal_moves(fr, self, &turn) {
+                // writing out every legal move is slow; only the ones to the right square
+                if !wanted.starts_with("O-O") && !wanted.contains(&to.to_notation()) {
+                    continue;
+                }
+                let promotions: &[PieceKind] = if self.is_promotion(fr, to) {
+                    &[
+                        PieceKind::Queen,
+                        PieceKind::Rook,
+                        PieceKind::Bishop,
+                        PieceKind::Knight,
+                    ]
+                } else {
+                    &[PieceKind::Queen]
+                };
+                for &promotion in promotions {
+                    if strip_suffixes(&self.to_san(turn, fr, to, promotion)) == wanted {
+                        return Some((fr, to, promotion));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// whether `turn` has a move at all; false on checkmate and stalemate
+    pub fn has_legal_moves(&self, turn: Turn) -> bool {
+        self.get_all_pieces_pos_by_color(turn)
+            .into_iter()
+            .any(|pos| !moves::get_legal_moves(pos, self, &turn).is_empty())
+    }
+}
+
+fn strip_suffixes(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+fn piece_char(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+fn file_char(pos: Position) -> char {
+    (b'a' + pos.j() as u8) as char
+}
+
+fn rank_char(pos: Position) -> char {
+    (b'1' + pos.i() as u8) as char
+}
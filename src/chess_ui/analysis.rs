@@ -0,0 +1,194 @@
+use chess::board::pieces::PieceColor;
+use chess::board::Board;
+use chess::computer::analysis::{Analysis, AnalysisLine, AnalysisOptions, AnalysisUpdate};
+use chess::Turn;
+use iced::futures::channel::mpsc;
+use iced::futures::{future, StreamExt};
+use iced::pure::widget::container::{Style, StyleSheet};
+use iced::pure::{button, column, container, row, text, Element};
+use iced::{Alignment, Background, Color, Length, Subscription};
+use iced_native::subscription;
+
+/// lines shown at most, however many the player asks for
+const MAX_LINES: usize = 5;
+
+#[derive(Debug, Clone)]
+pub enum AnalysisMessage {
+    Toggle,
+    MoreLines,
+    FewerLines,
+    Update(AnalysisUpdate),
+}
+
+/// the panel beside the board with the engine's best lines, and the evaluation bar
+pub struct AnalysisPanel {
+    enabled: bool,
+    multi_pv: usize,
+    /// the last update about the position on the board
+    update: Option<AnalysisUpdate>,
+}
+
+impl AnalysisPanel {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            multi_pv: 3,
+            update: None,
+        }
+    }
+
+    pub fn update(&mut self, message: AnalysisMessage) {
+        match message {
+            AnalysisMessage::Toggle => {
+                self.enabled = !self.enabled;
+                self.update = None;
+            }
+            AnalysisMessage::MoreLines => self.multi_pv = (self.multi_pv + 1).min(MAX_LINES),
+            AnalysisMessage::FewerLines => self.multi_pv = (self.multi_pv - 1).max(1),
+            AnalysisMessage::Update(update) => self.update = Some(update),
+        }
+    }
+
+    /// the lines shown are about the previous position; the new analysis starts from scratch
+    pub fn position_changed(&mut self) {
+        self.update = None;
+    }
+
+    /// analyses the position on the board while the panel is on; a new position or number
+    /// of lines starts a new analysis and drops the old one, which stops it
+    pub fn subscription(&self, board: &Board, turn: Turn) -> Subscription<AnalysisMessage> {
+        if !self.enabled {
+            return Subscription::none();
+        }
+        let id = (board.zobrist_hash(turn), self.multi_pv);
+        let state = State::Starting(board.clone(), turn, self.multi_pv);
+        subscription::unfold(id, state, |state| async move {
+            match state {
+                State::Starting(board, turn, multi_pv) => {
+                    let (sender, receiver) = mpsc::unbounded();
+                    let options = AnalysisOptions {
+                        multi_pv,
+                        ..AnalysisOptions::default()
+                    };
+                    let analysis = Analysis::start(board, turn, options, move |update| {
+                        // the receiver is gone once the subscription is
+                        let _ = sender.unbounded_send(update);
+                    });
+                    (None, State::Running(analysis, receiver))
+                }
+                State::Running(analysis, mut receiver) => match receiver.next().await {
+                    Some(update) => (
+                        Some(AnalysisMessage::Update(update)),
+                        State::Running(analysis, receiver),
+                    ),
+                    None => (None, State::Finished),
+                },
+                // a mate was found or the search went as deep as it goes
+                State::Finished => future::pending().await,
+            }
+        })
+    }
+
+    pub fn view(&self, board: &Board, turn: Turn) -> Element<'_, AnalysisMessage> {
+        let toggle = if self.enabled {
+            "Stop analysis"
+        } else {
+            "Analyse"
+        };
+        let lines = row()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(button(text("-")).on_press(AnalysisMessage::FewerLines))
+            .push(text(format!("{} lines", self.multi_pv)))
+            .push(button(text("+")).on_press(AnalysisMessage::MoreLines));
+        let mut panel = column()
+            .spacing(12)
+            .padding(12)
+            .width(Length::Fill)
+            .push(button(text(toggle)).on_press(AnalysisMessage::Toggle))
+            .push(lines);
+
+        match &self.update {
+            Some(update) => {
+                panel = panel.push(
+                    text(format!(
+                        "depth {}  {} nodes  {:.1}s",
+                        update.depth,
+                        update.nodes,
+                        update.time.as_secs_f64()
+                    ))
+                    .size(16),
+                );
+                for line in &update.lines {
+                    panel = panel.push(
+                        text(format!(
+                            "{}  {}",
+                            line.display_score(turn),
+                            line.to_san(board, turn)
+                        ))
+                        .size(16),
+                    );
+                }
+            }
+            None if self.enabled => panel = panel.push(text("thinking...").size(16)),
+            None => {}
+        }
+        panel.into()
+    }
+
+    /// white's share of the bar grows with white's advantage; even when there is no analysis
+    pub fn eval_bar(&self, turn: Turn) -> Element<'_, AnalysisMessage> {
+        let white_share = self
+            .update
+            .as_ref()
+            .and_then(|update| update.lines.first())
+            .map_or(0.5, |line| white_share(line, turn));
+        // a portion of 0 wouldn't fill at all
+        let white = ((white_share * 1000.0).round() as u16).clamp(1, 999);
+        let part = |portion, color| {
+            container(text(""))
+                .width(Length::Fill)
+                .height(Length::FillPortion(portion))
+                .style(BarStyle(color))
+        };
+        column()
+            .width(Length::Units(20))
+            .height(Length::Fill)
+            .push(part(1000 - white, Color::from_rgb8(64, 64, 64)))
+            .push(part(white, Color::WHITE))
+            .into()
+    }
+}
+
+/// the expected score of white between 0 and 1, the way Elo turns rating differences into
+/// scores; a mate fills the bar
+fn white_share(line: &AnalysisLine, turn: Turn) -> f32 {
+    let score = match turn {
+        PieceColor::White => line.score,
+        PieceColor::Black => -line.score,
+    };
+    match line.mate_in() {
+        Some(_) if score > 0 => 1.0,
+        Some(_) => 0.0,
+        None => 1.0 / (1.0 + 10_f32.powf(-score as f32 / 400.0)),
+    }
+}
+
+enum State {
+    Starting(Board, Turn, usize),
+    Running(Analysis, mpsc::UnboundedReceiver<AnalysisUpdate>),
+    Finished,
+}
+
+struct BarStyle(Color);
+
+impl StyleSheet for BarStyle {
+    fn style(&self) -> Style {
+        Style {
+            background: Some(Background::Color(self.0)),
+            border_width: 0.5,
+            border_color: Color::from_rgb8(128, 128, 128),
+            ..Default::default()
+        }
+    }
+}
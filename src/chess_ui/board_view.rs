@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use chess::board::position::Position;
+use iced::{Color, Length, Point, Rectangle, Size};
+use iced_native::event::{self, Event};
+use iced_native::layout::{self, Layout};
+use iced_native::renderer::{self, Quad};
+use iced_native::svg::{self, Handle};
+use iced_native::{mouse, Clipboard, Shell};
+use iced_pure::widget::tree::{self, Tree};
+use iced_pure::{Element, Widget};
+
+use super::SQUARE_SIZE;
+
+/// the room between a piece and the edges of its square
+const PIECE_PADDING: f32 = 10.0;
+/// the cursor has to move this far from where it was pressed before a piece follows it
+const DRAG_THRESHOLD: f32 = 4.0;
+/// the diameter of a dot and the width of a ring as a share of the square
+const DOT_SIZE: f32 = 0.3;
+const RING_WIDTH: f32 = 0.08;
+
+/// what a square of the board shows
+pub struct Square {
+    pub color: Color,
+    /// laid over the color; eg. for the last move
+    pub highlight: Option<Color>,
+    pub marker: Option<Marker>,
+    pub piece: Option<Handle>,
+}
+
+/// shows where the selected piece can go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    /// a quiet move
+    Dot,
+    /// a capture; the ring goes around the piece taken
+    Ring,
+}
+
+/// the board as a single widget so pieces can be dragged from one square to another
+///
+/// pressing a square sends `on_press` either way, which keeps click-click moves working.
+/// releasing a dragged piece over another square sends `on_drop`
+pub struct BoardView<Message> {
+    squares: HashMap<Position, Square>,
+    /// black at the bottom
+    flipped: bool,
+    /// eg. not while the computer is thinking
+    draggable: bool,
+    marker_color: Color,
+    on_press: fn(Position) -> Message,
+    on_drop: fn(Position, Position) -> Message,
+}
+
+/// the piece being dragged and where the cursor was pressed
+#[derive(Default)]
+struct Drag(Option<(Position, Point)>);
+
+impl<Message> BoardView<Message> {
+    pub fn new(
+        squares: HashMap<Position, Square>,
+        on_press: fn(Position) -> Message,
+        on_drop: fn(Position, Position) -> Message,
+    ) -> Self {
+        Self {
+            squares,
+            flipped: false,
+            draggable: false,
+            marker_color: Color::BLACK,
+            on_press,
+            on_drop,
+        }
+    }
+
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    pub fn draggable(mut self, draggable: bool) -> Self {
+        self.draggable = draggable;
+        self
+    }
+
+    pub fn marker_color(mut self, color: Color) -> Self {
+        self.marker_color = color;
+        self
+    }
+
+    fn square_at(&self, bounds: Rectangle, point: Point) -> Option<Position> {
+        if !bounds.contains(point) {
+            return None;
+        }
+        let size = SQUARE_SIZE as f32;
+        let column = (((point.x - bounds.x) / size) as i8).min(7);
+        let row = (((point.y - bounds.y) / size) as i8).min(7);
+        Some(if self.flipped {
+            Position::new(row, 7 - column)
+        } else {
+            Position::new(7 - row, column)
+        })
+    }
+
+    fn square_bounds(&self, bounds: Rectangle, pos: Position) -> Rectangle {
+        let (row, column) = if self.flipped {
+            (pos.i(), 7 - pos.j())
+        } else {
+            (7 - pos.i(), pos.j())
+        };
+        let size = SQUARE_SIZE as f32;
+        Rectangle {
+            x: bounds.x + column as f32 * size,
+            y: bounds.y + row as f32 * size,
+            width: size,
+            height: size,
+        }
+    }
+
+    /// the square the dragged piece comes from once it's far enough from where it was
+    /// pressed to follow the cursor
+    fn dragged(&self, tree: &Tree, cursor_position: Point) -> Option<Position> {
+        match tree.state.downcast_ref::<Drag>().0 {
+            Some((from, pressed)) if pressed.distance(cursor_position) > DRAG_THRESHOLD => {
+                Some(from)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<Message, Renderer> Widget<Message, Renderer> for BoardView<Message>
+where
+    Renderer: svg::Renderer,
+{
+    fn width(&self) -> Length {
+        Length::Units(8 * SQUARE_SIZE)
+    }
+
+    fn height(&self) -> Length {
+        Length::Units(8 * SQUARE_SIZE)
+    }
+
+    fn layout(&self, _renderer: &Renderer, _limits: &layout::Limits) -> layout::Node {
+        let side = (8 * SQUARE_SIZE) as f32;
+        layout::Node::new(Size::new(side, side))
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<Drag>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(Drag::default())
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let dragged = self.dragged(tree, cursor_position);
+        let piece_bounds = |square: Rectangle| Rectangle {
+            x: square.x + PIECE_PADDING,
+            y: square.y + PIECE_PADDING,
+            width: square.width - 2.0 * PIECE_PADDING,
+            height: square.height - 2.0 * PIECE_PADDING,
+        };
+
+        for (pos, square) in &self.squares {
+            let square_bounds = self.square_bounds(bounds, *pos);
+            renderer.fill_quad(
+                Quad {
+                    bounds: square_bounds,
+                    border_radius: 0.0,
+                    border_width: 0.78,
+                    border_color: Color::BLACK,
+                },
+                square.color,
+            );
+            if let Some(highlight) = square.highlight {
+                renderer.fill_quad(quad(square_bounds, 0.0), highlight);
+            }
+            match square.marker {
+                Some(Marker::Dot) => {
+                    let size = square_bounds.width * DOT_SIZE;
+                    let dot = Rectangle {
+                        x: square_bounds.center_x() - size / 2.0,
+                        y: square_bounds.center_y() - size / 2.0,
+                        width: size,
+                        height: size,
+                    };
+                    renderer.fill_quad(quad(dot, size / 2.0), self.marker_color);
+                }
+                Some(Marker::Ring) => renderer.fill_quad(
+                    Quad {
+                        border_width: square_bounds.width * RING_WIDTH,
+                        border_color: self.marker_color,
+                        ..quad(square_bounds, square_bounds.width / 2.0)
+                    },
+                    Color::TRANSPARENT,
+                ),
+                None => {}
+            }
+            match &square.piece {
+                Some(piece) if dragged != Some(*pos) => {
+                    renderer.draw(piece.clone(), piece_bounds(square_bounds))
+                }
+                _ => {}
+            }
+        }
+
+        // a layer of its own keeps the dragged piece above the rest of the board
+        let dragged_piece = dragged.and_then(|from| self.squares[&from].piece.clone());
+        if let Some(piece) = dragged_piece {
+            let size = SQUARE_SIZE as f32;
+            let square = Rectangle {
+                x: cursor_position.x - size / 2.0,
+                y: cursor_position.y - size / 2.0,
+                width: size,
+                height: size,
+            };
+            renderer.with_layer(*viewport, |renderer| {
+                renderer.draw(piece, piece_bounds(square))
+            });
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        let drag = tree.state.downcast_mut::<Drag>();
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(pos) = self.square_at(bounds, cursor_position) else {
+                    return event::Status::Ignored;
+                };
+                shell.publish((self.on_press)(pos));
+                let has_piece = self
+                    .squares
+                    .get(&pos)
+                    .is_some_and(|square| square.piece.is_some());
+                if self.draggable && has_piece {
+                    drag.0 = Some((pos, cursor_position));
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let Some((from, _)) = drag.0.take() else {
+                    return event::Status::Ignored;
+                };
+                // letting go where it was picked up is a click
+                match self.square_at(bounds, cursor_position) {
+                    Some(to) if to != from => shell.publish((self.on_drop)(from, to)),
+                    _ => {}
+                }
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if tree.state.downcast_ref::<Drag>().0.is_some() {
+            return mouse::Interaction::Grabbing;
+        }
+        match self.square_at(layout.bounds(), cursor_position) {
+            Some(pos) if self.draggable && self.squares[&pos].piece.is_some() => {
+                mouse::Interaction::Grab
+            }
+            Some(_) => mouse::Interaction::Pointer,
+            None => mouse::Interaction::Idle,
+        }
+    }
+}
+
+/// a quad without a border
+fn quad(bounds: Rectangle, border_radius: f32) -> Quad {
+    Quad {
+        bounds,
+        border_radius,
+        border_width: 0.0,
+        border_color: Color::TRANSPARENT,
+    }
+}
+
+impl<'a, Message, Renderer> From<BoardView<Message>> for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: svg::Renderer,
+{
+    fn from(board: BoardView<Message>) -> Self {
+        Element::new(board)
+    }
+}
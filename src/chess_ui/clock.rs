@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use chess::board::pieces::PieceColor;
+use chess::game::clock::ChessClock;
+use chess::Turn;
+use iced::pure::{column, row, text, Element};
+use iced::{Color, Length, Subscription};
+
+use super::Message;
+
+/// how often the running clock is redrawn
+const TICK: Duration = Duration::from_millis(100);
+
+/// both clocks in the order of the sides of the board, the one of the side to move in
+/// black, the other one greyed out and a fallen flag in red
+pub fn view(clock: &ChessClock, turn: Turn, flipped: bool) -> Element<'_, Message> {
+    let side = |color: PieceColor, label| {
+        let remaining = clock.remaining(color);
+        let time = text(format_time(remaining)).size(28);
+        let time = if remaining == Duration::ZERO {
+            time.color(Color::from_rgb8(204, 48, 76))
+        } else if color == turn {
+            time
+        } else {
+            time.color(Color::from_rgb8(128, 128, 128))
+        };
+        row()
+            .spacing(10)
+            .push(text(label).size(28).width(Length::Units(90)))
+            .push(time)
+    };
+    let (top, bottom) = if flipped {
+        (
+            side(PieceColor::White, "White"),
+            side(PieceColor::Black, "Black"),
+        )
+    } else {
+        (
+            side(PieceColor::Black, "Black"),
+            side(PieceColor::White, "White"),
+        )
+    };
+    column()
+        .spacing(4)
+        .push(top)
+        .push(bottom)
+        .push(text(clock.time_control().to_string()).size(16))
+        .into()
+}
+
+/// a message every tick so the running clock counts down on the screen
+pub fn ticks() -> Subscription<Message> {
+    iced::time::every(TICK).map(|_| Message::Tick)
+}
+
+/// minutes and seconds; tenths of seconds too in the last ten seconds
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:04.1}", time.as_secs_f64())
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
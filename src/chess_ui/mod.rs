@@ -0,0 +1,838 @@
+use chess::board::pieces::{moves, Piece, PieceColor, PieceKind};
+use chess::board::position::Position;
+use chess::board::BoardMoveError;
+use chess::board::{Board, Cell};
+use chess::computer::engine::{EngineMove, SearchLimits, DEFAULT_NODES};
+use chess::computer::selfplay;
+use chess::computer::Move;
+use chess::game::clock::ChessClock;
+use chess::game::{Game, GameResult, PlayedMove, Termination};
+use chess::Turn;
+use iced::alignment::{Horizontal, Vertical};
+use iced::futures::channel::oneshot;
+use iced::keyboard::{self, KeyCode};
+use iced::pure::widget::Space;
+use iced::pure::widget::{Button, Column, Row};
+use iced::pure::{column, container, pick_list, row, text, Application, Element};
+use iced::{svg, Command, Length, Subscription};
+use iced_native::{event, subscription, Event};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::{ChessSettings, Player};
+use analysis::{AnalysisMessage, AnalysisPanel};
+use board_view::{BoardView, Marker, Square};
+use setup::{Setup, SetupMessage};
+use theme::Theme;
+
+mod analysis;
+mod board_view;
+mod clock;
+mod move_list;
+mod setup;
+mod theme;
+
+/// the side of a square in pixels
+const SQUARE_SIZE: u16 = 90;
+/// the room the rank and file labels take beside the board
+const COORDINATES_SIZE: u16 = 24;
+
+pub struct ChessUI {
+    game: Game,
+    /// the ply of the position shown while looking back through the game; None shows the
+    /// current position
+    viewing: Option<usize>,
+    players: (Player, Player),
+    current_selected: Option<Position>,
+    legal_moves: Vec<Position>,
+    king_state: KingState,
+    analysis: AnalysisPanel,
+    /// the computer player's search while it runs
+    thinking: Option<Thinking>,
+    /// numbers the searches so the result of a cancelled one can be told apart
+    searches: u64,
+    /// set when a search was cancelled; the computer doesn't move until it's resumed
+    paused: bool,
+    /// a pawn move to the last rank waiting for the player to pick the piece
+    promotion: Option<Move>,
+    /// the moves taken back, the last one taken back at the end; playing a move clears them
+    redo: Vec<PlayedMove>,
+    /// None for a game without clocks
+    clock: Option<ChessClock>,
+    /// black at the bottom of the board
+    flipped: bool,
+    /// the computer players' moves follow from it; shown so a game can be played again
+    seed: u64,
+    theme: Theme,
+    screen: Screen,
+    setup: Setup,
+}
+
+/// what the window shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Game,
+    /// the game behind it waits, computer and clock included, and goes on if the setup is
+    /// cancelled; there's none at the start
+    Setup {
+        can_cancel: bool,
+    },
+}
+
+/// a computer player's search running on a thread of its own
+struct Thinking {
+    id: u64,
+    stop: Arc<AtomicBool>,
+}
+
+impl ChessUI {
+    fn new(settings: ChessSettings) -> Self {
+        let ChessSettings {
+            game,
+            players,
+            seed,
+            time_control,
+            flipped,
+        } = settings;
+        // a human playing black against the computer sees the board from black's side
+        let flipped = flipped || matches!(players, (Player::Computer(_), Player::Human));
+        if let Player::Computer(engine) = &players.0 {
+            let mut engine = engine.lock().unwrap();
+            engine.set_seed(selfplay::seed_for(seed, PieceColor::White));
+        }
+        if let Player::Computer(engine) = &players.1 {
+            let mut engine = engine.lock().unwrap();
+            engine.set_seed(selfplay::seed_for(seed, PieceColor::Black));
+        }
+        let mut ui = Self {
+            clock: time_control.map(|control| ChessClock::new(control, game.turn())),
+            game,
+            flipped,
+            seed,
+            theme: Theme::default(),
+            viewing: None,
+            players,
+            current_selected: None,
+            legal_moves: Vec::new(),
+            king_state: KingState::Safe,
+            analysis: AnalysisPanel::new(),
+            thinking: None,
+            searches: 0,
+            paused: false,
+            promotion: None,
+            redo: Vec::new(),
+            screen: Screen::Game,
+            setup: Setup::new(),
+        };
+        ui.update_king_state();
+        ui
+    }
+
+    /// the game of `settings` takes the place of this one; the choices of the setup screen
+    /// and the theme are kept
+    fn new_game(&mut self, settings: ChessSettings) {
+        self.stop_thinking();
+        let (setup, theme) = (self.setup.clone(), self.theme);
+        *self = ChessUI::new(settings);
+        self.setup = setup;
+        self.theme = theme;
+    }
+
+    fn update_king_state(&mut self) {
+        let (board, turn) = (self.game.board(), self.game.turn());
+        let king_pos = board.look_up_king_pos(turn);
+        if !board.is_king_safe(turn) {
+            self.king_state = KingState::Check(king_pos);
+        } else {
+            self.king_state = KingState::Safe;
+        }
+
+        if board.is_checkmate_for(match turn {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }) {
+            self.king_state = KingState::Checkmate(king_pos);
+        }
+    }
+    fn current_player(&self) -> &Player {
+        match self.game.turn() {
+            PieceColor::White => &self.players.0,
+            PieceColor::Black => &self.players.1,
+        }
+    }
+
+    /// a human playing a computer; a take-back or redo then goes a whole move pair at a time
+    fn against_computer(&self) -> bool {
+        matches!(
+            self.players,
+            (Player::Human, Player::Computer(_)) | (Player::Computer(_), Player::Human)
+        )
+    }
+
+    fn play(&mut self, fr: Position, to: Position, kind: PieceKind) -> Result<(), BoardMoveError> {
+        self.game.play(fr, to, kind)?;
+        self.redo.clear();
+        if let Some(clock) = &mut self.clock {
+            clock.press();
+        }
+        Ok(())
+    }
+
+    /// takes back the last move, and the computer's reply before it when playing one; the
+    /// computer doesn't move again until it's resumed
+    fn take_back(&mut self) {
+        self.stop_thinking();
+        self.show_ply(self.game.moves().len());
+        let Some(played) = self.game.undo() else {
+            return;
+        };
+        self.redo.push(played);
+        if self.against_computer() && matches!(self.current_player(), Player::Computer(_)) {
+            if let Some(played) = self.game.undo() {
+                self.redo.push(played);
+            }
+        }
+        // the time spent on the moves taken back isn't given back
+        if let Some(clock) = &mut self.clock {
+            clock.switch_to(self.game.turn());
+        }
+        self.paused = matches!(self.current_player(), Player::Computer(_));
+    }
+
+    /// plays the last move taken back again, the same number of moves as the take-back; the
+    /// computer waits to be resumed here as well
+    fn replay(&mut self) {
+        self.stop_thinking();
+        self.show_ply(self.game.moves().len());
+        let Some(played) = self.redo.pop() else {
+            return;
+        };
+        self.game
+            .play(played.fr, played.to, played.promotion)
+            .unwrap();
+        if self.against_computer() && matches!(self.current_player(), Player::Computer(_)) {
+            if let Some(played) = self.redo.pop() {
+                self.game
+                    .play(played.fr, played.to, played.promotion)
+                    .unwrap();
+            }
+        }
+        if let Some(clock) = &mut self.clock {
+            clock.switch_to(self.game.turn());
+        }
+        self.paused = matches!(self.current_player(), Player::Computer(_));
+    }
+
+    /// the search of the computer player, if any, is stopped and its move thrown away
+    fn stop_thinking(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// starts the search of the computer player whose turn it is, if it's one's turn;
+    /// the move comes back as a Message::ComputerMoved
+    fn start_thinking(&mut self) -> Command<Message> {
+        let Player::Computer(engine) = self.current_player() else {
+            return Command::none();
+        };
+        if self.thinking.is_some() || self.paused || matches!(self.screen, Screen::Setup { .. }) {
+            return Command::none();
+        }
+        if self.game.outcome().is_some() {
+            return Command::none();
+        }
+        let (engine, game, turn) = (engine.clone(), self.game.clone(), self.game.turn());
+        let opponent = match turn {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        };
+        // without a clock the search is bounded the same way as in the match runner
+        let limits = SearchLimits {
+            nodes: self.clock.is_none().then_some(DEFAULT_NODES),
+            clock: self.clock.as_ref().map(|clock| clock.search_clock(turn)),
+            opponent_clock: self
+                .clock
+                .as_ref()
+                .map(|clock| clock.search_clock(opponent)),
+            ..SearchLimits::default()
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        self.searches += 1;
+        let id = self.searches;
+        self.thinking = Some(Thinking {
+            id,
+            stop: stop.clone(),
+        });
+
+        // the search runs on its own thread rather than on the executor's so it doesn't
+        // hold up the analysis
+        let (sender, receiver) = oneshot::channel();
+        thread::spawn(move || {
+            let engine_move = engine.lock().unwrap().pick_move(&game, &limits, &stop);
+            let _ = sender.send(engine_move);
+        });
+        Command::perform(async move { receiver.await.ok().flatten() }, move |mv| {
+            Message::ComputerMoved(id, mv)
+        })
+    }
+
+    /// a human player clicked a square: selects a piece or moves the selected one
+    fn click(&mut self, pos: Position) {
+        if let Some((fr, to)) = self.promotion.take() {
+            let choice = self
+                .promotion_choices(to)
+                .into_iter()
+                .find(|(square, _)| *square == pos);
+            // a click anywhere else takes the move back
+            if let Some((_, kind)) = choice {
+                self.play(fr, to, kind).unwrap();
+            }
+            self.current_selected = None;
+            self.legal_moves.clear();
+            return;
+        }
+        if let Some(fr) = self.current_selected {
+            if self.legal_moves.contains(&pos) && self.game.board().is_promotion(fr, pos) {
+                self.promotion = Some((fr, pos));
+                return;
+            }
+        }
+
+        if self.current_selected.is_none() {
+            let piece = self.game.board().look_up_cell(pos).unwrap();
+
+            if piece.is_some() {
+                self.current_selected = Some(pos);
+                self.legal_moves =
+                    moves::get_legal_moves(pos, self.game.board(), &self.game.turn());
+            } else {
+                self.legal_moves.clear();
+                self.current_selected = None;
+            }
+            return;
+        }
+        match self.play(self.current_selected.unwrap(), pos, PieceKind::Queen) {
+            Ok(_) => {
+                self.current_selected = None;
+                self.legal_moves.clear();
+            }
+            Err(err) => match err {
+                BoardMoveError::NotYourTurn | BoardMoveError::Illegal => {
+                    let piece = self.game.board().look_up_cell(pos).unwrap();
+
+                    if piece.is_some() {
+                        self.current_selected = Some(pos);
+                        self.legal_moves =
+                            moves::get_legal_moves(pos, self.game.board(), &self.game.turn());
+                    } else {
+                        self.legal_moves.clear();
+                        self.current_selected = None;
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    /// the squares the promotion picker covers with the piece each one stands for; they go
+    /// from the promotion square towards the middle of the board
+    fn promotion_choices(&self, to: Position) -> Vec<(Position, PieceKind)> {
+        let step = if to.i() == 7 { -1 } else { 1 };
+        [
+            PieceKind::Queen,
+            PieceKind::Rook,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(n, kind)| (Position::new(to.i() + step * n as i8, to.j()), kind))
+        .collect()
+    }
+
+    /// the board and the side to move of the position on the screen
+    fn shown(&self) -> (&Board, Turn) {
+        self.game
+            .position(self.viewing.unwrap_or(self.game.moves().len()))
+    }
+
+    fn show_ply(&mut self, ply: usize) {
+        self.viewing = (ply < self.game.moves().len()).then_some(ply);
+        self.current_selected = None;
+        self.legal_moves.clear();
+        self.promotion = None;
+    }
+
+    /// the side to move, and what it's doing
+    fn status(&self) -> Element<'_, Message> {
+        let side = match self.game.turn() {
+            PieceColor::White => "White",
+            PieceColor::Black => "Black",
+        };
+        let mut status = column().spacing(10).padding(12);
+        if let Some(clock) = &self.clock {
+            status = status.push(clock::view(clock, self.game.turn(), self.flipped));
+        }
+        if let Some(outcome) = self.game.outcome() {
+            status = status.push(text(format!(
+                "{} ({})",
+                outcome.result, outcome.termination
+            )));
+        } else if self.promotion.is_some() {
+            status = status
+                .push(text("Pick the piece to promote to"))
+                .push(Button::new(text("Cancel")).on_press(Message::CancelPromotion));
+        } else if self.thinking.is_some() {
+            status = status.push(text(format!("{side} is thinking..."))).push(
+                row()
+                    .spacing(10)
+                    .push(Button::new(text("Move now")).on_press(Message::MoveNow))
+                    .push(Button::new(text("Cancel")).on_press(Message::CancelThinking)),
+            );
+        } else if self.paused {
+            status = status
+                .push(text(format!("{side} to move")))
+                .push(Button::new(text("Resume computer")).on_press(Message::ResumeComputer));
+        } else {
+            status = status.push(text(format!("{side} to move")));
+        }
+
+        let mut take_back = Button::new(text("Take back"));
+        if !self.game.moves().is_empty() {
+            take_back = take_back.on_press(Message::Undo);
+        }
+        let mut redo = Button::new(text("Redo"));
+        if !self.redo.is_empty() {
+            redo = redo.on_press(Message::Redo);
+        }
+        let new_game = Button::new(text("New game")).on_press(Message::NewGame);
+        let flip = Button::new(text("Flip board")).on_press(Message::Flip);
+        status
+            .push(row().spacing(10).push(take_back).push(redo))
+            .push(row().spacing(10).push(new_game).push(flip).push(pick_list(
+                &Theme::ALL[..],
+                Some(self.theme),
+                Message::Theme,
+            )))
+            .push(text(format!("Seed: {}", self.seed)).size(16))
+            .into()
+    }
+}
+#[derive(Clone, Copy)]
+enum KingState {
+    Safe,
+    Check(Position),
+    Checkmate(Position),
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Clicked(Position),
+    /// a piece was dragged from the first square and dropped on the second
+    Dropped(Position, Position),
+    Analysis(AnalysisMessage),
+    /// the move a computer player's search came up with; None if it had none
+    ComputerMoved(u64, Option<EngineMove>),
+    /// the computer plays the best move it found so far
+    MoveNow,
+    /// the computer's search is stopped and its move thrown away
+    CancelThinking,
+    CancelPromotion,
+    ResumeComputer,
+    /// takes the last move back; the computer's reply too when playing one
+    Undo,
+    Redo,
+    /// shows the position after that many plies
+    ShowPly(usize),
+    Step(Step),
+    /// turns the board around
+    Flip,
+    Theme(Theme),
+    /// opens the setup screen
+    NewGame,
+    Setup(SetupMessage),
+    /// redraws the running clock
+    Tick,
+}
+
+/// a step through the moves of the game
+#[derive(Debug, Clone, Copy)]
+pub enum Step {
+    First,
+    Previous,
+    Next,
+    Last,
+}
+impl Application for ChessUI {
+    type Executor = iced::executor::Default;
+    type Message = Message;
+    /// None opens the setup screen
+    type Flags = Option<ChessSettings>;
+
+    fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+        let mut ui = match flags {
+            Some(settings) => ChessUI::new(settings),
+            None => {
+                let players = (Player::Human, Player::Human);
+                let mut ui = ChessUI::new(ChessSettings::new(
+                    Game::new(Board::default(), PieceColor::White),
+                    players,
+                    0,
+                    None,
+                ));
+                ui.screen = Screen::Setup { can_cancel: false };
+                ui
+            }
+        };
+        let command = ui.start_thinking();
+        (ui, command)
+    }
+
+    fn title(&self) -> String {
+        "Chess".to_string()
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let (board, turn) = self.shown();
+        let ticks = match self.clock {
+            Some(_) if self.game.outcome().is_none() => clock::ticks(),
+            _ => Subscription::none(),
+        };
+        Subscription::batch([
+            self.analysis
+                .subscription(board, turn)
+                .map(Message::Analysis),
+            subscription::events_with(shortcuts),
+            ticks,
+        ])
+    }
+
+    fn update(&mut self, message: Self::Message) -> iced::Command<Self::Message> {
+        let shown_key = |ui: &Self| {
+            let (board, turn) = ui.shown();
+            board.zobrist_hash(turn)
+        };
+        let key_before = shown_key(self);
+        match message {
+            Message::Analysis(message) => {
+                self.analysis.update(message);
+                return iced::Command::none();
+            }
+            Message::Clicked(_) | Message::Dropped(..) if self.game.outcome().is_some() => {
+                return iced::Command::none();
+            }
+            // back to the current position first; the moves are played there
+            Message::Clicked(_) if self.viewing.is_some() => self.show_ply(self.game.moves().len()),
+            Message::Dropped(fr, to) => match self.current_player() {
+                // the press that started the drag selected the piece
+                Player::Human
+                    if self.current_selected == Some(fr) && self.legal_moves.contains(&to) =>
+                {
+                    self.click(to)
+                }
+                _ => return iced::Command::none(),
+            },
+            Message::Clicked(pos) => match self.current_player() {
+                Player::Human => self.click(pos),
+                Player::Computer(_) => return iced::Command::none(),
+            },
+            Message::ComputerMoved(id, mv) => {
+                // the result of a search that was cancelled
+                if self.thinking.as_ref().map(|thinking| thinking.id) != Some(id) {
+                    return iced::Command::none();
+                }
+                self.thinking = None;
+                let turn = self.game.turn();
+                let played = mv.map(|engine_move| {
+                    let (fr, to) = engine_move.best_move;
+                    self.play(fr, to, engine_move.promotion)
+                });
+                // an engine that crashed or came up with an illegal move loses the game
+                // rather than being asked again and again
+                if !matches!(played, Some(Ok(()))) {
+                    let winner = match turn {
+                        PieceColor::White => PieceColor::Black,
+                        PieceColor::Black => PieceColor::White,
+                    };
+                    self.game
+                        .adjudicate(GameResult::win_for(winner), Termination::Forfeit);
+                }
+            }
+            Message::MoveNow => {
+                if let Some(thinking) = &self.thinking {
+                    thinking.stop.store(true, Ordering::Relaxed);
+                }
+                return iced::Command::none();
+            }
+            Message::CancelThinking => {
+                self.stop_thinking();
+                self.paused = true;
+                return iced::Command::none();
+            }
+            Message::ResumeComputer => self.paused = false,
+            Message::Tick => match self.clock.as_ref().and_then(ChessClock::flagged) {
+                Some(color) if self.game.outcome().is_none() => {
+                    self.stop_thinking();
+                    self.game.flag(color);
+                }
+                _ => return iced::Command::none(),
+            },
+            Message::Theme(theme) => {
+                self.theme = theme;
+                return iced::Command::none();
+            }
+            Message::Flip => {
+                self.flipped = !self.flipped;
+                return iced::Command::none();
+            }
+            Message::NewGame => {
+                self.stop_thinking();
+                if let Some(clock) = &mut self.clock {
+                    clock.stop();
+                }
+                self.screen = Screen::Setup { can_cancel: true };
+                return iced::Command::none();
+            }
+            Message::Setup(SetupMessage::Cancel) => {
+                self.screen = Screen::Game;
+                if let (Some(clock), None) = (&mut self.clock, self.game.outcome()) {
+                    clock.switch_to(self.game.turn());
+                }
+            }
+            Message::Setup(SetupMessage::Play) => match self.setup.settings() {
+                Some(settings) => self.new_game(settings),
+                None => return iced::Command::none(),
+            },
+            Message::Setup(message) => {
+                self.setup.update(message);
+                return iced::Command::none();
+            }
+            Message::Undo => self.take_back(),
+            Message::Redo => self.replay(),
+            Message::ShowPly(ply) => self.show_ply(ply),
+            Message::Step(step) => {
+                let last = self.game.moves().len();
+                let shown = self.viewing.unwrap_or(last);
+                self.show_ply(match step {
+                    Step::First => 0,
+                    Step::Previous => shown.saturating_sub(1),
+                    Step::Next => shown + 1,
+                    Step::Last => last,
+                });
+            }
+            Message::CancelPromotion => {
+                self.promotion = None;
+                self.current_selected = None;
+                self.legal_moves.clear();
+                return iced::Command::none();
+            }
+        }
+
+        if shown_key(self) != key_before {
+            self.analysis.position_changed();
+        }
+
+        self.update_king_state();
+        if let (Some(clock), Some(_)) = (&mut self.clock, self.game.outcome()) {
+            clock.stop();
+        }
+        self.start_thinking()
+    }
+
+    fn view(&self) -> Element<'_, Self::Message> {
+        if let Screen::Setup { can_cancel } = self.screen {
+            return container(self.setup.view(can_cancel).map(Message::Setup))
+                .width(Length::Fill)
+                .center_x()
+                .into();
+        }
+        let (board, turn) = self.shown();
+        // a check on the board of a position from earlier in the game isn't the current one
+        let king_state = match self.viewing {
+            Some(_) => KingState::Safe,
+            None => self.king_state,
+        };
+        let promotion_choices = self
+            .promotion
+            .map_or(vec![], |(_, to)| self.promotion_choices(to));
+
+        let palette = self.theme.palette();
+        let shown_ply = self.viewing.unwrap_or(self.game.moves().len());
+        let last_move = shown_ply
+            .checked_sub(1)
+            .map(|ply| self.game.moves()[ply].as_move());
+        let selected_pawn = self.current_selected.filter(|pos| {
+            matches!(board.look_up_cell(*pos), Some(Some(piece)) if piece.kind == PieceKind::Pawn)
+        });
+
+        let mut squares = HashMap::new();
+        for i in 0..8 {
+            for j in 0..8 {
+                let pos = Position::new(i, j);
+                let mut color = if (i + j) % 2 == 0 {
+                    palette.dark
+                } else {
+                    palette.light
+                };
+
+                let choice = promotion_choices.iter().find(|(square, _)| *square == pos);
+                let cell = board.look_up_cell(pos).unwrap();
+                let piece_str = match choice {
+                    Some((_, kind)) => get_piece_str(&Some(Piece::new(*kind, turn))),
+                    None => get_piece_str(cell),
+                };
+                let piece = (!piece_str.is_empty()).then(|| {
+                    svg::Handle::from_path(format!(
+                        "{}/src/icons/{piece_str}.svg",
+                        env!("CARGO_MANIFEST_DIR")
+                    ))
+                });
+
+                let mut highlight = match last_move {
+                    Some((fr, to)) if pos == fr || pos == to => Some(palette.last_move),
+                    _ => None,
+                };
+                if self.current_selected == Some(pos) {
+                    highlight = Some(palette.selected);
+                }
+
+                // a pawn moving sideways takes en passant even onto an empty square
+                let marker = self.legal_moves.contains(&pos).then(|| {
+                    let en_passant = selected_pawn.is_some_and(|fr| fr.j() != pos.j());
+                    if cell.is_some() || en_passant {
+                        Marker::Ring
+                    } else {
+                        Marker::Dot
+                    }
+                });
+
+                match king_state {
+                    KingState::Check(king) if king == pos => color = palette.check,
+                    KingState::Checkmate(king) if king == pos => color = palette.checkmate,
+                    _ => {}
+                }
+                if choice.is_some() {
+                    color = palette.promotion;
+                    highlight = None;
+                }
+
+                squares.insert(
+                    pos,
+                    Square {
+                        color,
+                        highlight,
+                        marker,
+                        piece,
+                    },
+                );
+            }
+        }
+        let draggable = self.viewing.is_none()
+            && self.promotion.is_none()
+            && self.game.outcome().is_none()
+            && matches!(self.current_player(), Player::Human);
+        let board_view = BoardView::new(squares, Message::Clicked, Message::Dropped)
+            .marker_color(palette.legal_move)
+            .flipped(self.flipped)
+            .draggable(draggable);
+
+        // white's first rank and the a-file go bottom left unless the board is flipped
+        let (ranks, files): (Vec<i8>, Vec<i8>) = if self.flipped {
+            ((0..8).collect(), (0..8).rev().collect())
+        } else {
+            ((0..8).rev().collect(), (0..8).collect())
+        };
+        let rank_labels = ranks.iter().fold(Column::new(), |labels, &i| {
+            labels.push(
+                text((i + 1).to_string())
+                    .width(Length::Units(COORDINATES_SIZE))
+                    .height(Length::Units(SQUARE_SIZE))
+                    .horizontal_alignment(Horizontal::Center)
+                    .vertical_alignment(Vertical::Center),
+            )
+        });
+        let file_labels = files.iter().fold(
+            Row::new().push(Space::with_width(Length::Units(COORDINATES_SIZE))),
+            |labels, &j| {
+                labels.push(
+                    text(((b'a' + j as u8) as char).to_string())
+                        .width(Length::Units(SQUARE_SIZE))
+                        .horizontal_alignment(Horizontal::Center),
+                )
+            },
+        );
+        let column = Column::new()
+            .push(Row::new().push(rank_labels).push(board_view))
+            .push(file_labels);
+
+        let side_panel = iced::pure::column()
+            .height(Length::Fill)
+            .push(self.status())
+            .push(move_list::view(&self.game, shown_ply))
+            .push(self.analysis.view(board, turn).map(Message::Analysis));
+        row()
+            .spacing(10)
+            .push(self.analysis.eval_bar(turn).map(Message::Analysis))
+            .push(column)
+            .push(side_panel)
+            .into()
+    }
+}
+
+/// the arrow keys and Home/End step through the moves of the game; Ctrl+Z takes a move
+/// back and Ctrl+Y or Ctrl+Shift+Z plays it again. F flips the board
+fn shortcuts(event: Event, status: event::Status) -> Option<Message> {
+    if status == event::Status::Captured {
+        return None;
+    }
+    let Event::Keyboard(keyboard::Event::KeyPressed {
+        key_code,
+        modifiers,
+    }) = event
+    else {
+        return None;
+    };
+    if modifiers.command() {
+        return match key_code {
+            KeyCode::Z if modifiers.shift() => Some(Message::Redo),
+            KeyCode::Z => Some(Message::Undo),
+            KeyCode::Y => Some(Message::Redo),
+            _ => None,
+        };
+    }
+    if key_code == KeyCode::F {
+        return Some(Message::Flip);
+    }
+    let step = match key_code {
+        KeyCode::Left => Step::Previous,
+        KeyCode::Right => Step::Next,
+        KeyCode::Home | KeyCode::Up => Step::First,
+        KeyCode::End | KeyCode::Down => Step::Last,
+        _ => return None,
+    };
+    Some(Message::Step(step))
+}
+
+fn get_piece_str(cell: &Cell) -> String {
+    let mut piece_str = "".to_string();
+
+    if let Some(piece) = cell {
+        if piece.color == PieceColor::White {
+            piece_str.push_str("white_");
+        } else {
+            piece_str.push_str("black_");
+        }
+        piece_str.push_str(match piece.kind {
+            PieceKind::King => "king",
+            PieceKind::Queen => "queen",
+            PieceKind::Knight => "knight",
+            PieceKind::Bishop => "bishop",
+            PieceKind::Rook => "rook",
+            PieceKind::Pawn => "pawn",
+        });
+    }
+    piece_str
+}
@@ -0,0 +1,82 @@
+use chess::board::pieces::PieceColor;
+use chess::game::Game;
+use iced::button::{Style, StyleSheet};
+use iced::pure::widget::Button;
+use iced::pure::{column, row, scrollable, text, Element};
+use iced::{Alignment, Background, Color, Length};
+
+use super::{Message, Step};
+
+/// the moves played in numbered pairs, the one leading to the shown position highlighted;
+/// clicking a move shows the position after it
+pub fn view(game: &Game, shown_ply: usize) -> Element<'_, Message> {
+    let step = |label, step| {
+        Button::new(text(label))
+            .width(Length::Units(50))
+            .on_press(Message::Step(step))
+    };
+    let controls = row()
+        .spacing(10)
+        .push(step("|<", Step::First))
+        .push(step("<", Step::Previous))
+        .push(step(">", Step::Next))
+        .push(step(">|", Step::Last));
+
+    // a game set up with black to move starts with "1..."
+    let first_ply = match game.start().1 {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    };
+    let mut pairs: Vec<(String, Vec<usize>)> = vec![];
+    for i in 0..game.moves().len() {
+        let ply = first_ply + i;
+        match pairs.last_mut() {
+            Some((_, moves)) if ply % 2 == 1 => moves.push(i),
+            _ if ply % 2 == 1 => pairs.push((format!("{}...", ply / 2 + 1), vec![i])),
+            _ => pairs.push((format!("{}.", ply / 2 + 1), vec![i])),
+        }
+    }
+
+    let mut list = column().spacing(4).width(Length::Fill);
+    for (number, moves) in pairs {
+        let mut line = row()
+            .spacing(6)
+            .align_items(Alignment::Center)
+            .push(text(number).size(18).width(Length::Units(50)));
+        for i in moves {
+            line = line.push(
+                Button::new(text(&game.moves()[i].san).size(18))
+                    .width(Length::Units(90))
+                    .on_press(Message::ShowPly(i + 1))
+                    .style(MoveStyle {
+                        current: i + 1 == shown_ply,
+                    }),
+            );
+        }
+        list = list.push(line);
+    }
+
+    column()
+        .spacing(10)
+        .padding(12)
+        .height(Length::Fill)
+        .push(controls)
+        .push(scrollable(list).height(Length::Fill))
+        .into()
+}
+
+struct MoveStyle {
+    current: bool,
+}
+
+impl StyleSheet for MoveStyle {
+    fn active(&self) -> Style {
+        Style {
+            background: self
+                .current
+                .then_some(Background::Color(Color::from_rgb8(195, 228, 235))),
+            border_radius: 3.0,
+            ..Default::default()
+        }
+    }
+}
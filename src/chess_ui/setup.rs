@@ -0,0 +1,336 @@
+use std::fmt;
+use std::time::Duration;
+
+use chess::arena::{BookSpec, EngineSpec};
+use chess::board::fen::{chess960_fen, CHESS960_POSITIONS};
+use chess::board::pieces::PieceColor;
+use chess::board::Board;
+use chess::computer::difficulty::Difficulty;
+use chess::game::clock::{Bonus, Stage, TimeControl};
+use chess::game::Game;
+use iced::pure::{button, column, pick_list, radio, row, text, text_input, Element};
+use iced::{Alignment, Color, Length};
+
+use crate::{ChessSettings, Player};
+
+/// the time controls to pick from: sudden death, Fischer, the two delays and the stages of
+/// classical games
+fn time_controls() -> Vec<TimeControl> {
+    let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+    let seconds = Duration::from_secs;
+    let stage = |moves, time| Stage {
+        moves,
+        time: minutes(time),
+    };
+    vec![
+        TimeControl::sudden_death(minutes(1)),
+        TimeControl::sudden_death(minutes(5)),
+        TimeControl::sudden_death(minutes(15)),
+        TimeControl::fischer(minutes(3), seconds(2)),
+        TimeControl::fischer(minutes(5), seconds(3)),
+        TimeControl::fischer(minutes(10), seconds(5)),
+        TimeControl::fischer(minutes(15), seconds(10)),
+        TimeControl::new(vec![stage(None, 5)], Bonus::SimpleDelay(seconds(3))),
+        TimeControl::new(vec![stage(None, 30)], Bonus::SimpleDelay(seconds(5))),
+        TimeControl::new(vec![stage(None, 5)], Bonus::Bronstein(seconds(3))),
+        TimeControl::new(vec![stage(None, 30)], Bonus::Bronstein(seconds(5))),
+        TimeControl::new(
+            vec![stage(Some(40), 90), stage(None, 30)],
+            Bonus::Increment(seconds(30)),
+        ),
+        TimeControl::new(vec![stage(Some(40), 120)], Bonus::None),
+    ]
+}
+
+/// who plays a side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerKind {
+    Human,
+    Random,
+    Level(Difficulty),
+    Full,
+    /// a UCI engine whose path is typed in
+    External,
+}
+
+impl PlayerKind {
+    fn all() -> Vec<PlayerKind> {
+        [PlayerKind::Human, PlayerKind::Random]
+            .into_iter()
+            .chain(Difficulty::ALL.map(PlayerKind::Level))
+            .chain([PlayerKind::Full, PlayerKind::External])
+            .collect()
+    }
+}
+
+impl fmt::Display for PlayerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerKind::Human => write!(f, "Human"),
+            PlayerKind::Random => write!(f, "Computer (random moves)"),
+            PlayerKind::Level(difficulty) => write!(f, "Computer ({difficulty})"),
+            PlayerKind::Full => write!(f, "Computer (full strength)"),
+            PlayerKind::External => write!(f, "UCI engine"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartPosition {
+    Standard,
+    Fen,
+    Chess960,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeChoice {
+    Untimed,
+    Timed(TimeControl),
+}
+
+impl fmt::Display for TimeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeChoice::Untimed => write!(f, "Untimed"),
+            TimeChoice::Timed(control) => write!(f, "{control}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SetupMessage {
+    Player(PieceColor, PlayerKind),
+    EnginePath(PieceColor, String),
+    BookPath(PieceColor, String),
+    Start(StartPosition),
+    Fen(String),
+    Chess960(String),
+    Time(TimeChoice),
+    Play,
+    Cancel,
+}
+
+/// the choices of the setup screen; they stay as they were for the next game
+#[derive(Debug, Clone)]
+pub struct Setup {
+    white: PlayerKind,
+    black: PlayerKind,
+    white_path: String,
+    black_path: String,
+    /// the opening books of the built-in engines; none when left empty
+    white_book: String,
+    black_book: String,
+    start: StartPosition,
+    fen: String,
+    /// the number of the Chess960 position; a random one when left empty
+    chess960: String,
+    time: TimeChoice,
+    /// why the last try to start a game failed
+    error: Option<String>,
+}
+
+impl Setup {
+    pub fn new() -> Self {
+        Self {
+            white: PlayerKind::Human,
+            black: PlayerKind::Level(Difficulty::default()),
+            white_path: String::new(),
+            black_path: String::new(),
+            white_book: String::new(),
+            black_book: String::new(),
+            start: StartPosition::Standard,
+            fen: String::new(),
+            chess960: String::new(),
+            time: TimeChoice::Untimed,
+            error: None,
+        }
+    }
+
+    /// Play and Cancel are up to the caller
+    pub fn update(&mut self, message: SetupMessage) {
+        match message {
+            SetupMessage::Player(PieceColor::White, kind) => self.white = kind,
+            SetupMessage::Player(PieceColor::Black, kind) => self.black = kind,
+            SetupMessage::EnginePath(PieceColor::White, path) => self.white_path = path,
+            SetupMessage::EnginePath(PieceColor::Black, path) => self.black_path = path,
+            SetupMessage::BookPath(PieceColor::White, path) => self.white_book = path,
+            SetupMessage::BookPath(PieceColor::Black, path) => self.black_book = path,
+            SetupMessage::Start(start) => self.start = start,
+            SetupMessage::Fen(fen) => self.fen = fen,
+            SetupMessage::Chess960(number) => self.chess960 = number,
+            SetupMessage::Time(time) => self.time = time,
+            SetupMessage::Play | SetupMessage::Cancel => {}
+        }
+    }
+
+    /// the settings of the game picked; starts the external engines. keeps the error to
+    /// show it when something is wrong
+    pub fn settings(&mut self) -> Option<ChessSettings> {
+        match self.build() {
+            Ok(settings) => {
+                self.error = None;
+                Some(settings)
+            }
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+
+    fn build(&self) -> Result<ChessSettings, String> {
+        let game = match self.start {
+            StartPosition::Standard => Game::new(Board::default(), PieceColor::White),
+            StartPosition::Fen => Game::from_fen(self.fen.trim()).map_err(|err| err.to_string())?,
+            StartPosition::Chess960 => {
+                let number = match self.chess960.trim() {
+                    "" => rand::random::<u16>() % CHESS960_POSITIONS,
+                    number => number
+                        .parse()
+                        .ok()
+                        .filter(|number| *number < CHESS960_POSITIONS)
+                        .ok_or("the Chess960 position is a number from 0 to 959")?,
+                };
+                Game::from_fen(&chess960_fen(number)).unwrap()
+            }
+        };
+        let white = player(self.white, &self.white_path, &self.white_book)
+            .map_err(|err| format!("White: {err}"))?;
+        let black = player(self.black, &self.black_path, &self.black_book)
+            .map_err(|err| format!("Black: {err}"))?;
+        let time_control = match &self.time {
+            TimeChoice::Untimed => None,
+            TimeChoice::Timed(control) => Some(control.clone()),
+        };
+        Ok(ChessSettings::new(
+            game,
+            (white, black),
+            rand::random(),
+            time_control,
+        ))
+    }
+
+    /// Cancel only shows when there is a game to go back to
+    pub fn view(&self, can_cancel: bool) -> Element<'_, SetupMessage> {
+        let start = [
+            (StartPosition::Standard, "Standard"),
+            (StartPosition::Fen, "FEN"),
+            (StartPosition::Chess960, "Chess960"),
+        ]
+        .into_iter()
+        .fold(row().spacing(20), |start, (value, label)| {
+            start.push(radio(label, value, Some(self.start), SetupMessage::Start))
+        });
+        let mut position = column()
+            .spacing(10)
+            .push(text("Start position"))
+            .push(start);
+        match self.start {
+            StartPosition::Standard => {}
+            StartPosition::Fen => {
+                position = position
+                    .push(text_input("a position in FEN", &self.fen, SetupMessage::Fen).padding(8))
+            }
+            StartPosition::Chess960 => {
+                position = position.push(
+                    text_input(
+                        "the number of the position; random when left empty",
+                        &self.chess960,
+                        SetupMessage::Chess960,
+                    )
+                    .padding(8),
+                )
+            }
+        }
+
+        let times: Vec<TimeChoice> = [TimeChoice::Untimed]
+            .into_iter()
+            .chain(time_controls().into_iter().map(TimeChoice::Timed))
+            .collect();
+        let time = row()
+            .spacing(20)
+            .align_items(Alignment::Center)
+            .push(text("Time control").width(Length::Units(150)))
+            .push(pick_list(
+                times,
+                Some(self.time.clone()),
+                SetupMessage::Time,
+            ));
+
+        let mut buttons = row()
+            .spacing(20)
+            .push(button(text("Play")).on_press(SetupMessage::Play));
+        if can_cancel {
+            buttons = buttons.push(button(text("Cancel")).on_press(SetupMessage::Cancel));
+        }
+
+        let mut screen = column()
+            .spacing(30)
+            .padding(40)
+            .width(Length::Units(700))
+            .push(text("New game").size(40))
+            .push(self.player_view(PieceColor::White))
+            .push(self.player_view(PieceColor::Black))
+            .push(position)
+            .push(time);
+        if let Some(err) = &self.error {
+            screen = screen.push(text(err).color(Color::from_rgb8(204, 48, 76)));
+        }
+        screen.push(buttons).into()
+    }
+
+    fn player_view(&self, color: PieceColor) -> Element<'_, SetupMessage> {
+        let (label, kind, path, book) = match color {
+            PieceColor::White => ("White", self.white, &self.white_path, &self.white_book),
+            PieceColor::Black => ("Black", self.black, &self.black_path, &self.black_book),
+        };
+        let mut player = column().spacing(10).push(
+            row()
+                .spacing(20)
+                .align_items(Alignment::Center)
+                .push(text(label).width(Length::Units(150)))
+                .push(pick_list(PlayerKind::all(), Some(kind), move |kind| {
+                    SetupMessage::Player(color, kind)
+                })),
+        );
+        match kind {
+            PlayerKind::External => {
+                player = player.push(
+                    text_input("the path of the engine", path, move |path| {
+                        SetupMessage::EnginePath(color, path)
+                    })
+                    .padding(8),
+                );
+            }
+            PlayerKind::Level(_) | PlayerKind::Full => {
+                player = player.push(
+                    text_input("an opening book; none when left empty", book, move |path| {
+                        SetupMessage::BookPath(color, path)
+                    })
+                    .padding(8),
+                );
+            }
+            PlayerKind::Human | PlayerKind::Random => {}
+        }
+        player.into()
+    }
+}
+
+fn player(kind: PlayerKind, path: &str, book: &str) -> Result<Player, String> {
+    let book = Some(book.trim())
+        .filter(|book| !book.is_empty())
+        .map(BookSpec::new);
+    let spec = match kind {
+        PlayerKind::Human => return Ok(Player::Human),
+        PlayerKind::Random => EngineSpec::Random,
+        PlayerKind::Level(difficulty) => EngineSpec::Level(difficulty, book),
+        PlayerKind::Full => EngineSpec::Full(book),
+        PlayerKind::External if path.trim().is_empty() => {
+            return Err("the path of the engine is missing".to_string())
+        }
+        PlayerKind::External => EngineSpec::Uci(path.trim().to_string()),
+    };
+    spec.build()
+        .map(Player::computer)
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+
+use iced::Color;
+
+/// the color schemes of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Green,
+    Brown,
+    Blue,
+}
+
+/// the colors of a theme
+pub struct Palette {
+    pub light: Color,
+    pub dark: Color,
+    /// laid over the square of the selected piece
+    pub selected: Color,
+    /// laid over the two squares of the last move
+    pub last_move: Color,
+    /// the dots and rings on the squares the selected piece can go to
+    pub legal_move: Color,
+    pub check: Color,
+    pub checkmate: Color,
+    /// behind the pieces of the promotion picker
+    pub promotion: Color,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Green, Theme::Brown, Theme::Blue];
+
+    pub fn palette(self) -> Palette {
+        let (light, dark) = match self {
+            Theme::Green => (Color::WHITE, Color::from_rgb8(74, 101, 71)),
+            Theme::Brown => (
+                Color::from_rgb8(240, 217, 181),
+                Color::from_rgb8(181, 136, 99),
+            ),
+            Theme::Blue => (
+                Color::from_rgb8(222, 227, 230),
+                Color::from_rgb8(140, 162, 173),
+            ),
+        };
+        Palette {
+            light,
+            dark,
+            selected: Color::from_rgba8(20, 85, 200, 0.35),
+            last_move: Color::from_rgba8(155, 199, 0, 0.45),
+            legal_move: Color::from_rgba8(20, 85, 30, 0.5),
+            check: Color::from_rgb8(204, 48, 76),
+            checkmate: Color::from_rgb8(230, 48, 76),
+            promotion: Color::from_rgb8(240, 240, 240),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Theme::Green => "Green",
+            Theme::Brown => "Brown",
+            Theme::Blue => "Blue",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::pieces::{PieceColor, PieceKind};
+use crate::board::Board;
+use crate::computer::engine::SearchLimits;
+use crate::computer::search::{self, SearchConfig, SearchResult, MATE_SCORE};
+use crate::computer::syzygy::Tablebase;
+use crate::computer::transposition::TranspositionTable;
+use crate::computer::Move;
+use crate::Turn;
+
+/// how a position is analysed
+#[derive(Clone)]
+pub struct AnalysisOptions {
+    /// how many of the best moves get a line of their own
+    pub multi_pv: usize,
+    pub threads: usize,
+    pub hash_size_mb: usize,
+    pub tablebase: Option<Arc<Tablebase>>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            multi_pv: 1,
+            threads: 1,
+            hash_size_mb: TranspositionTable::DEFAULT_SIZE_MB,
+            tablebase: None,
+        }
+    }
+}
+
+/// one of the best moves and what the engine expects to follow it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalysisLine {
+    /// in centipawns from the point of view of the side to move
+    pub score: i32,
+    /// starts with the move the line is about; each move with what a pawn reaching the last
+    /// rank becomes
+    pub pv: Vec<(Move, PieceKind)>,
+}
+
+impl AnalysisLine {
+    /// the moves until mate; negative when the side to move gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        let plies_to_mate = MATE_SCORE - self.score.abs();
+        (plies_to_mate < 1000).then(|| {
+            let moves = (plies_to_mate + 1) / 2;
+            if self.score > 0 {
+                moves
+            } else {
+                -moves
+            }
+        })
+    }
+
+    /// the score the way players write it, from white's point of view; eg. "+0.35",
+    /// "-1.20" or "#-3" when black mates in three
+    pub fn display_score(&self, turn: Turn) -> String {
+        let sign = match turn {
+            PieceColor::White => 1,
+            PieceColor::Black => -1,
+        };
+        match self.mate_in() {
+            Some(moves) => format!("#{}", sign * moves),
+            None => format!("{:+.2}", (sign * self.score) as f64 / 100.0),
+        }
+    }
+
+    /// the line in standard algebraic notation, separated by spaces
+    pub fn to_san(&self, board: &Board, turn: Turn) -> String {
+        let (mut board, mut turn) = (board.clone(), turn);
+        let mut moves = vec![];
+        for &((fr, to), promotion) in &self.pv {
+            moves.push(board.to_san(turn, fr, to, promotion));
+            board.move_piece_and_promote_unchecked(&mut turn, fr, to, promotion);
+        }
+        moves.join(" ")
+    }
+}
+
+/// the analysis after a completed depth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalysisUpdate {
+    pub depth: u8,
+    pub nodes: u64,
+    pub time: Duration,
+    /// best first; fewer than asked for when there aren't that many legal moves
+    pub lines: Vec<AnalysisLine>,
+}
+
+impl From<&SearchResult> for AnalysisUpdate {
+    fn from(result: &SearchResult) -> Self {
+        Self {
+            depth: result.depth,
+            nodes: result.nodes,
+            time: result.time,
+            lines: result
+                .lines
+                .iter()
+                .map(|(score, pv)| AnalysisLine {
+                    score: *score,
+                    // the search only promotes to a queen
+                    pv: pv.iter().map(|mv| (*mv, PieceKind::Queen)).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// stops an analysis from anywhere; eg. from another thread than the one that started it
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// a search of a position in the background that goes deeper until it's stopped
+///
+/// it only ends by itself once a forced mate is found or the maximum depth is reached.
+/// dropping it stops the search without waiting for it
+pub struct Analysis {
+    stop: StopHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Analysis {
+    /// starts analysing `board` with `turn` to move; `on_update` is called from the search's
+    /// thread every time a depth is done
+    pub fn start(
+        board: Board,
+        turn: Turn,
+        options: AnalysisOptions,
+        mut on_update: impl FnMut(AnalysisUpdate) + Send + 'static,
+    ) -> Self {
+        let stop = StopHandle::default();
+        let flag = stop.0.clone();
+        let thread = thread::spawn(move || {
+            let tt = TranspositionTable::new(options.hash_size_mb);
+            let config = SearchConfig {
+                threads: options.threads,
+                multi_pv: options.multi_pv,
+                tablebase: options.tablebase.as_deref(),
+                ..SearchConfig::default()
+            };
+            search::search(
+                &board,
+                turn,
+                &SearchLimits::default(),
+                &config,
+                &tt,
+                &flag,
+                &mut |result| on_update(AnalysisUpdate::from(result)),
+            );
+        });
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// the same as `start` with the updates sent through a channel; it's closed once the
+    /// analysis is over
+    pub fn with_channel(
+        board: Board,
+        turn: Turn,
+        options: AnalysisOptions,
+    ) -> (Self, Receiver<AnalysisUpdate>) {
+        let (sender, receiver) = mpsc::channel();
+        let analysis = Self::start(board, turn, options, move |update| {
+            // nobody listening any more is no reason to stop
+            let _ = sender.send(update);
+        });
+        (analysis, receiver)
+    }
+
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// the search ended by itself or was stopped
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// stops the search and waits until its last update was delivered
+    pub fn stop(mut self) {
+        self.stop.stop();
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("the analysis thread panicked");
+        }
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::{Position, PositionNotation};
+
+    #[test]
+    fn lines_keep_their_underpromotions() {
+        let (board, turn) = Board::parse_fen("8/P6k/8/8/8/8/6K1/8 w - - 0 1").unwrap();
+        let mv = |fr, to| {
+            (
+                Position::from_notation(fr).unwrap(),
+                Position::from_notation(to).unwrap(),
+            )
+        };
+        let line = AnalysisLine {
+            score: 0,
+            pv: vec![
+                (mv("a7", "a8"), PieceKind::Knight),
+                (mv("h7", "g6"), PieceKind::Queen),
+                (mv("a8", "b6"), PieceKind::Queen),
+            ],
+        };
+        assert_eq!(line.to_san(&board, turn), "a8=N Kg6 Nb6");
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::board::pieces::PieceKind;
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::computer::{extract_legal_moves, Move};
+use crate::game::{Game, GameResult};
+use crate::Turn;
+
+/// the size of an entry of a Polyglot book file
+const ENTRY_SIZE: usize = 16;
+
+/// an entry of a Polyglot book; every field is stored big-endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    /// the Polyglot key of the position, which is what `Board::zobrist_hash` computes
+    pub key: u64,
+    /// to file in bits 0-2, to rank 3-5, from file 6-8, from rank 9-11 and the promotion
+    /// (1 knight to 4 queen) in 12-14; castling is written as the king taking its rook
+    pub mv: u16,
+    /// how often the move should be played compared to the other moves of the position
+    pub weight: u16,
+    /// unused here but kept so books can be written back unchanged
+    pub learn: u32,
+}
+
+/// a move of the book for the position at hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub fr: Position,
+    pub to: Position,
+    pub promotion: Option<PieceKind>,
+    pub weight: u16,
+}
+
+impl BookMove {
+    pub fn as_move(&self) -> Move {
+        (self.fr, self.to)
+    }
+}
+
+/// how a move is chosen when the book has several for a position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BookSelection {
+    /// at random, in proportion to the weights; the usual way to vary the openings
+    #[default]
+    Weighted,
+    /// always the move with the highest weight
+    Best,
+}
+
+/// an opening book in the Polyglot format
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    /// sorted by key so the moves of a position are found with a binary search
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// reads the entries of a book file; a truncated entry at the end is ignored
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(entry[12..16].try_into().unwrap()),
+            })
+            .collect();
+        // books are sorted already; the sort is stable so the order of the moves is kept
+        entries.sort_by_key(|entry| entry.key);
+        Self { entries }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mv.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// the legal moves the book has for `turn`, in the book's order
+    pub fn moves(&self, board: &Board, turn: Turn) -> Vec<BookMove> {
+        let key = board.zobrist_hash(turn);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal_moves = extract_legal_moves(board, turn);
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .map(|entry| decode_move(board, entry.mv, entry.weight))
+            // a key collision or a broken book could suggest anything
+            .filter(|mv| legal_moves.contains(&mv.as_move()))
+            .collect()
+    }
+
+    /// a book move for `turn`; None when the position isn't in the book
+    pub fn pick(
+        &self,
+        board: &Board,
+        turn: Turn,
+        selection: BookSelection,
+        rng: &mut impl Rng,
+    ) -> Option<BookMove> {
+        let moves = self.moves(board, turn);
+        match selection {
+            BookSelection::Best => moves
+                .iter()
+                .filter(|mv| mv.weight > 0)
+                // the first of the best moves, as max_by_key would return the last
+                .min_by_key(|mv| u16::MAX - mv.weight)
+                .copied(),
+            BookSelection::Weighted => {
+                let total: u32 = moves.iter().map(|mv| mv.weight as u32).sum();
+                // moves with no weight are in the book only to be avoided
+                if total == 0 {
+                    return None;
+                }
+                let mut choice = rng.gen_range(0..total);
+                moves.into_iter().find(|mv| {
+                    if choice < mv.weight as u32 {
+                        return true;
+                    }
+                    choice -= mv.weight as u32;
+                    false
+                })
+            }
+        }
+    }
+}
+
+/// the Polyglot encoding of a move of `board`
+pub fn encode_move(board: &Board, fr: Position, to: Position, promotion: Option<PieceKind>) -> u16 {
+    // castling is the king taking its own rook; a Chess960 move already is
+    let to_file = if board.is_castling(fr, to) && !board.is_chess960() {
+        if to.j() > fr.j() {
+            7
+        } else {
+            0
+        }
+    } else {
+        to.j()
+    };
+    let promotion = match promotion {
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(PieceKind::Queen) => 4,
+        _ => 0,
+    };
+    (to_file as u16)
+        | (to.i() as u16) << 3
+        | (fr.j() as u16) << 6
+        | (fr.i() as u16) << 9
+        | promotion << 12
+}
+
+fn decode_move(board: &Board, mv: u16, weight: u16) -> BookMove {
+    let square = |bits: u16| Position::new(((bits >> 3) & 7) as i8, (bits & 7) as i8);
+    let fr = square(mv >> 6);
+    let mut to = square(mv);
+    let is_king =
+        matches!(board.look_up_cell(fr), Some(Some(piece)) if piece.kind == PieceKind::King);
+    if is_king && !board.is_chess960() && fr.i() == to.i() && (to.j() - fr.j()).abs() > 1 {
+        // the king takes its rook; it lands two squares away from where it started
+        to = Position::new(to.i(), fr.j() + 2 * (to.j() - fr.j()).signum());
+    }
+    let promotion = match (mv >> 12) & 7 {
+        1 => Some(PieceKind::Knight),
+        2 => Some(PieceKind::Bishop),
+        3 => Some(PieceKind::Rook),
+        4 => Some(PieceKind::Queen),
+        _ => None,
+    };
+    BookMove {
+        fr,
+        to,
+        promotion,
+        weight,
+    }
+}
+
+/// collects the opening moves of finished games into a Polyglot book
+///
+/// a move scores 2 for every game the side that played it won and 1 for every draw, as
+/// Polyglot's own make-book does; moves that only lost are left out
+pub struct BookBuilder {
+    /// moves after this many plies of a game aren't counted
+    max_plies: usize,
+    /// (key, move) to (games, score)
+    counts: HashMap<(u64, u16), (u32, u32)>,
+}
+
+impl BookBuilder {
+    pub fn new(max_plies: usize) -> Self {
+        Self {
+            max_plies,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// counts the first moves of `game`, which ended with `result`
+    pub fn add_game(&mut self, game: &Game, result: GameResult) {
+        let (board, turn) = game.start();
+        let (mut board, mut turn) = (board.clone(), turn);
+        for played in game.moves().iter().take(self.max_plies) {
+            let (fr, to) = played.as_move();
+            let key = board.zobrist_hash(turn);
+            let promotion = board.is_promotion(fr, to).then_some(played.promotion);
+            let mv = encode_move(&board, fr, to, promotion);
+            let score = match result.winner() {
+                None => 1,
+                Some(winner) if winner == turn => 2,
+                Some(_) => 0,
+            };
+            let count = self.counts.entry((key, mv)).or_default();
+            count.0 += 1;
+            count.1 += score;
+            // the game checked its moves already
+            board
+                .move_piece_and_promote(&mut turn, fr, to, played.promotion)
+                .expect("the moves of a game are legal");
+        }
+    }
+
+    /// the book of the moves played in at least `min_games` games
+    pub fn build(&self, min_games: u32) -> OpeningBook {
+        let max_score = self
+            .counts
+            .values()
+            .map(|(_, score)| *score)
+            .max()
+            .unwrap_or(0);
+        // weights have 16 bits; bigger scores are scaled down with their proportions kept
+        let scale = |score: u32| {
+            if max_score <= u16::MAX as u32 {
+                score as u16
+            } else {
+                ((score as u64 * u16::MAX as u64 / max_score as u64) as u16).max(1)
+            }
+        };
+        let mut entries: Vec<BookEntry> = self
+            .counts
+            .iter()
+            .filter(|(_, (games, score))| *games >= min_games && *score > 0)
+            .map(|(&(key, mv), &(_, score))| BookEntry {
+                key,
+                mv,
+                weight: scale(score),
+                learn: 0,
+            })
+            .collect();
+        // the best moves of a position come first, as in books made by Polyglot
+        entries.sort_by_key(|entry| (entry.key, u16::MAX - entry.weight, entry.mv));
+        OpeningBook { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::PositionNotation;
+
+    fn pos(notation: &str) -> Position {
+        Position::from_notation(notation).unwrap()
+    }
+
+    /// encodes the move and decodes it back on the same board
+    fn round_trip(fen: &str, fr: &str, to: &str, promotion: Option<PieceKind>) -> u16 {
+        let (board, _) = Board::parse_fen(fen).unwrap();
+        let mv = encode_move(&board, pos(fr), pos(to), promotion);
+        assert_eq!(
+            decode_move(&board, mv, 1),
+            BookMove {
+                fr: pos(fr),
+                to: pos(to),
+                promotion,
+                weight: 1,
+            }
+        );
+        mv
+    }
+
+    #[test]
+    fn castling_is_written_as_the_king_taking_its_rook() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(round_trip(fen, "e1", "g1", None), 0x0107);
+        assert_eq!(round_trip(fen, "e1", "c1", None), 0x0100);
+        assert_eq!(round_trip(fen, "e8", "g8", None), 0x0f3f);
+        assert_eq!(round_trip(fen, "e8", "c8", None), 0x0f38);
+        // a king going one file is no castling
+        assert_eq!(round_trip(fen, "e1", "f1", None), 0x0105);
+
+        // in Chess960 the king already moves onto its rook
+        let fen = "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1";
+        assert_eq!(round_trip(fen, "e1", "g1", None), 0x0106);
+        assert_eq!(round_trip(fen, "e1", "b1", None), 0x0101);
+    }
+
+    #[test]
+    fn promotions_keep_their_piece() {
+        let fen = "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        for (kind, bits) in [
+            (PieceKind::Knight, 1),
+            (PieceKind::Bishop, 2),
+            (PieceKind::Rook, 3),
+            (PieceKind::Queen, 4),
+        ] {
+            assert_eq!(round_trip(fen, "a7", "a8", Some(kind)) >> 12, bits);
+            assert_eq!(round_trip(fen, "a7", "b8", Some(kind)) >> 12, bits);
+        }
+        assert_eq!(round_trip(fen, "e1", "e2", None) >> 12, 0);
+    }
+}
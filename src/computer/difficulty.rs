@@ -0,0 +1,97 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// the named strength levels of the computer player; from the weakest to the strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+    Beginner,
+    Novice,
+    #[default]
+    Intermediate,
+    Advanced,
+    Strong,
+}
+
+/// the knobs a difficulty level turns on the search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelSettings {
+    /// maximum depth of the search in plies
+    pub depth: u8,
+    /// how long the computer may think about a single move, counted in searched positions
+    /// rather than wall time so the same seed always leads to the same game
+    pub nodes: u64,
+    /// random centipawns (+/-) added to the score of every root move
+    pub noise: i32,
+    /// probability of deliberately playing one of the runner-up moves
+    pub blunder_chance: f64,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Novice,
+        Difficulty::Intermediate,
+        Difficulty::Advanced,
+        Difficulty::Strong,
+    ];
+
+    pub fn settings(self) -> LevelSettings {
+        match self {
+            Difficulty::Beginner => LevelSettings {
+                depth: 1,
+                nodes: 2_000,
+                noise: 150,
+                blunder_chance: 0.25,
+            },
+            Difficulty::Novice => LevelSettings {
+                depth: 2,
+                nodes: 10_000,
+                noise: 80,
+                blunder_chance: 0.15,
+            },
+            Difficulty::Intermediate => LevelSettings {
+                depth: 2,
+                nodes: 30_000,
+                noise: 30,
+                blunder_chance: 0.05,
+            },
+            Difficulty::Advanced => LevelSettings {
+                depth: 3,
+                nodes: 80_000,
+                noise: 10,
+                blunder_chance: 0.0,
+            },
+            Difficulty::Strong => LevelSettings {
+                depth: 5,
+                nodes: 250_000,
+                noise: 0,
+                blunder_chance: 0.0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Beginner => "Beginner",
+            Difficulty::Novice => "Novice",
+            Difficulty::Intermediate => "Intermediate",
+            Difficulty::Advanced => "Advanced",
+            Difficulty::Strong => "Strong",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    /// the level's name in any case; eg. "novice"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.to_string().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
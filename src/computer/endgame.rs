@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use crate::board::{
+    pieces::{moves, PieceColor, PieceKind},
+    position::Position,
+    Board,
+};
+use crate::computer::evaluation::{piece_value, PAWN_VALUE};
+
+/// the score of an ending that is won with correct play; above any normal evaluation but
+/// well below the mates
+pub const KNOWN_WIN: i32 = 10_000;
+/// recognized endings have at most this many pieces, kings included
+const MAX_PIECES: usize = 6;
+const KING_STEPS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// what the recognizers know about a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recognized {
+    /// nobody can win whatever is played
+    Draw,
+    /// in centipawns from the point of view of the side asked about; steers the winning
+    /// side towards the mate
+    Score(i32),
+}
+
+/// the pieces of one side
+struct Side {
+    king: Position,
+    /// every piece but the king
+    pieces: Vec<(PieceKind, Position)>,
+}
+
+impl Side {
+    fn count(&self, kind: PieceKind) -> usize {
+        self.pieces.iter().filter(|(k, _)| *k == kind).count()
+    }
+
+    fn has_only(&self, kinds: &[PieceKind]) -> bool {
+        let mut sorted: Vec<PieceKind> = self.pieces.iter().map(|(kind, _)| *kind).collect();
+        sorted.sort();
+        let mut expected = kinds.to_vec();
+        expected.sort();
+        sorted == expected
+    }
+
+    fn material(&self) -> i32 {
+        self.pieces.iter().map(|(kind, _)| piece_value(*kind)).sum()
+    }
+
+    fn squares_of(&self, kind: PieceKind) -> impl Iterator<Item = Position> + '_ {
+        self.pieces
+            .iter()
+            .filter(move |(k, _)| *k == kind)
+            .map(|(_, pos)| *pos)
+    }
+}
+
+/// looks for an ending with a known outcome or a known way to win; None for anything else
+pub fn recognize(board: &Board, color: PieceColor) -> Option<Recognized> {
+    if board.pieces().count() > MAX_PIECES {
+        return None;
+    }
+    let mut white = Side {
+        king: Position::new(0, 0),
+        pieces: vec![],
+    };
+    let mut black = Side {
+        king: Position::new(0, 0),
+        pieces: vec![],
+    };
+    for (pos, piece) in board.pieces() {
+        let side = match piece.color {
+            PieceColor::White => &mut white,
+            PieceColor::Black => &mut black,
+        };
+        match piece.kind {
+            PieceKind::King => side.king = pos,
+            kind => side.pieces.push((kind, pos)),
+        }
+    }
+
+    if is_insufficient(&white, &black) {
+        return Some(Recognized::Draw);
+    }
+    // the endings below are against a lone king
+    let (strong, weak, strong_color) = match (white.pieces.is_empty(), black.pieces.is_empty()) {
+        (false, true) => (&white, &black, PieceColor::White),
+        (true, false) => (&black, &white, PieceColor::Black),
+        _ => return None,
+    };
+    let weak_to_move = color != strong_color;
+
+    let recognized = if strong.has_only(&[PieceKind::Bishop, PieceKind::Knight]) {
+        Recognized::Score(bishop_and_knight(board, strong, weak))
+    } else if strong.has_only(&[PieceKind::Pawn]) {
+        king_and_pawn(strong, weak, strong_color, weak_to_move)?
+    } else if is_wrong_rook_pawn(strong, weak, strong_color) {
+        Recognized::Draw
+    } else if can_force_mate(strong) {
+        Recognized::Score(mating(board, strong, weak))
+    } else {
+        return None;
+    };
+
+    Some(match recognized {
+        Recognized::Score(score) if color != strong_color => Recognized::Score(-score),
+        recognized => recognized,
+    })
+}
+
+/// neither side can ever mate; eg. a king and a knight against a king
+fn is_insufficient(white: &Side, black: &Side) -> bool {
+    let minors = |side: &Side| {
+        side.pieces
+            .iter()
+            .all(|(kind, _)| matches!(kind, PieceKind::Knight | PieceKind::Bishop))
+    };
+    let total = white.pieces.len() + black.pieces.len();
+    if !minors(white) || !minors(black) {
+        return false;
+    }
+    match total {
+        0 | 1 => true,
+        // two knights can't force the mate either
+        2 if white.count(PieceKind::Knight) == 2 || black.count(PieceKind::Knight) == 2 => true,
+        // only bishops, all on squares of the same color
+        _ => {
+            let bishops: Vec<Position> = white
+                .squares_of(PieceKind::Bishop)
+                .chain(black.squares_of(PieceKind::Bishop))
+                .collect();
+            bishops.len() == total
+                && bishops
+                    .iter()
+                    .all(|pos| is_dark(*pos) == is_dark(bishops[0]))
+        }
+    }
+}
+
+/// a queen, a rook, two bishops of different colors or a bishop and a knight mate a lone king
+fn can_force_mate(strong: &Side) -> bool {
+    let bishops: Vec<Position> = strong.squares_of(PieceKind::Bishop).collect();
+    strong.count(PieceKind::Queen) > 0
+        || strong.count(PieceKind::Rook) > 0
+        || bishops
+            .iter()
+            .any(|pos| is_dark(*pos) != is_dark(bishops[0]))
+        || (!bishops.is_empty() && strong.count(PieceKind::Knight) > 0)
+}
+
+/// drives the lone king to the edge and brings the other king closer
+fn mating(board: &Board, strong: &Side, weak: &Side) -> i32 {
+    KNOWN_WIN
+        + strong.material()
+        + push_to_edge(weak.king)
+        + push_close(strong.king, weak.king)
+        + confine(board, strong, weak)
+}
+
+/// the mate only works in a corner of the bishop's color, so the lone king goes there
+/// and the knight joins in
+fn bishop_and_knight(board: &Board, strong: &Side, weak: &Side) -> i32 {
+    let bishop = strong.squares_of(PieceKind::Bishop).next().unwrap();
+    let knight = strong.squares_of(PieceKind::Knight).next().unwrap();
+    let corners = if is_dark(bishop) {
+        [Position::new(0, 0), Position::new(7, 7)]
+    } else {
+        [Position::new(0, 7), Position::new(7, 0)]
+    };
+    // counted along the edges, which is the way the king gets walked to the corner
+    let to_corner = corners
+        .iter()
+        .map(|corner| {
+            ((corner.i() - weak.king.i()).abs() + (corner.j() - weak.king.j()).abs()) as i32
+        })
+        .min()
+        .unwrap();
+    KNOWN_WIN
+        + strong.material()
+        + 3 * (14 - to_corner).pow(2)
+        + push_close(strong.king, weak.king)
+        + push_close(knight, weak.king) / 2
+        + confine(board, strong, weak)
+}
+
+/// a king and a pawn against a king: a win if the lone king can't catch the pawn, a draw
+/// if the pawn is on a rook file and the lone king got to the corner
+fn king_and_pawn(
+    strong: &Side,
+    weak: &Side,
+    strong_color: PieceColor,
+    weak_to_move: bool,
+) -> Option<Recognized> {
+    let pawn = strong.squares_of(PieceKind::Pawn).next()?;
+    let queening = queening_square(pawn, strong_color);
+    if is_rook_file(pawn) && distance(weak.king, queening) <= 1 {
+        return Some(Recognized::Draw);
+    }
+
+    // the rule of the square; a pawn on its starting rank can move two squares at once
+    let rank = pawn.adaptive(strong_color).i();
+    let pawn_moves = (7 - rank).min(5) as i32;
+    let own_king_in_the_way =
+        strong.king.j() == pawn.j() && strong.king.adaptive(strong_color).i() > rank;
+    if !own_king_in_the_way && distance(weak.king, queening) > pawn_moves + weak_to_move as i32 {
+        return Some(Recognized::Score(KNOWN_WIN + PAWN_VALUE + 20 * rank as i32));
+    }
+    None
+}
+
+/// a bishop and pawns on a rook file whose queening square the bishop doesn't cover;
+/// the lone king in the corner can't be driven out
+fn is_wrong_rook_pawn(strong: &Side, weak: &Side, strong_color: PieceColor) -> bool {
+    let mut bishops = strong.squares_of(PieceKind::Bishop);
+    let (Some(bishop), None) = (bishops.next(), bishops.next()) else {
+        return false;
+    };
+    let mut pawns = strong.squares_of(PieceKind::Pawn);
+    let Some(first_pawn) = pawns.next() else {
+        return false;
+    };
+    if strong.pieces.len() != 1 + strong.count(PieceKind::Pawn)
+        || !is_rook_file(first_pawn)
+        || pawns.any(|pawn| pawn.j() != first_pawn.j())
+    {
+        return false;
+    }
+    let queening = queening_square(first_pawn, strong_color);
+    is_dark(bishop) != is_dark(queening) && distance(weak.king, queening) <= 1
+}
+
+/// the square where a pawn of `color` promotes
+fn queening_square(pawn: Position, color: PieceColor) -> Position {
+    Position::new(Position::new(7, 0).adaptive(color).i(), pawn.j())
+}
+
+fn is_dark(pos: Position) -> bool {
+    (pos.i() + pos.j()) % 2 == 0
+}
+
+fn is_rook_file(pos: Position) -> bool {
+    pos.j() == 0 || pos.j() == 7
+}
+
+/// the number of king moves between two squares
+fn distance(a: Position, b: Position) -> i32 {
+    (a.i() - b.i()).abs().max((a.j() - b.j()).abs()) as i32
+}
+
+/// higher near the edges and higher still in the corners
+fn push_to_edge(pos: Position) -> i32 {
+    let file = pos.j().min(7 - pos.j()) as i32;
+    let rank = pos.i().min(7 - pos.i()) as i32;
+    50 * (3 - file.min(rank)) + 10 * (6 - file - rank)
+}
+
+/// higher the smaller the part of the board the lone king can walk around in without
+/// crossing an attacked square
+fn confine(board: &Board, strong: &Side, weak: &Side) -> i32 {
+    let attacked: HashSet<Position> = strong
+        .pieces
+        .iter()
+        .map(|(_, pos)| *pos)
+        .chain([strong.king])
+        .flat_map(|pos| moves::get_legal_moves_unchecked(pos, board))
+        .collect();
+    let mut reachable = HashSet::from([weak.king]);
+    let mut stack = vec![weak.king];
+    while let Some(pos) = stack.pop() {
+        for (di, dj) in KING_STEPS {
+            let next = Position::new(pos.i() + di, pos.j() + dj);
+            let on_board = (0..8).contains(&next.i()) && (0..8).contains(&next.j());
+            if on_board && !attacked.contains(&next) && reachable.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    4 * (64 - reachable.len() as i32)
+}
+
+/// higher the closer the kings are
+fn push_close(a: Position, b: Position) -> i32 {
+    20 * (7 - distance(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognize_fen(fen: &str) -> Option<Recognized> {
+        let (board, turn) = Board::parse_fen(fen).unwrap();
+        recognize(&board, turn)
+    }
+
+    fn score(fen: &str) -> i32 {
+        match recognize_fen(fen) {
+            Some(Recognized::Score(score)) => score,
+            recognized => panic!("{fen}: {recognized:?}"),
+        }
+    }
+
+    #[test]
+    fn queen_and_rook_mates_drive_the_king_to_the_edge() {
+        for piece in ['Q', 'R'] {
+            let centre = score(&format!("8/8/8/3k4/8/8/8/{piece}3K3 w - - 0 1"));
+            let edge = score(&format!("3k4/8/8/8/8/8/8/{piece}3K3 w - - 0 1"));
+            let corner = score(&format!("k7/8/8/8/8/8/8/{piece}3K3 w - - 0 1"));
+            assert!(centre > KNOWN_WIN);
+            assert!(edge > centre && corner > edge, "{piece}");
+            // the other way around for the lone king
+            assert_eq!(
+                score(&format!("8/8/8/3k4/8/8/8/{piece}3K3 b - - 0 1")),
+                -centre
+            );
+        }
+        assert!(score("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1") > score("8/8/8/3k4/8/8/8/R3K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn bishop_and_knight_mate_in_the_corner_of_the_bishop() {
+        // the bishop on c1 is on the dark squares, so the mate is on a1 or h8
+        let right_corner = score("7k/8/8/8/8/8/8/2B1KN2 w - - 0 1");
+        let wrong_corner = score("k7/8/8/8/8/8/8/2B1KN2 w - - 0 1");
+        assert!(wrong_corner > KNOWN_WIN);
+        assert!(right_corner > wrong_corner);
+    }
+
+    #[test]
+    fn the_rule_of_the_square() {
+        // the king is five moves from e8 and the pawn four
+        assert!(score("8/8/8/8/4P3/k7/8/6K1 w - - 0 1") > KNOWN_WIN);
+        // a move closer it catches the pawn
+        assert_eq!(recognize_fen("8/8/8/8/4P3/k7/8/6K1 b - - 0 1"), None);
+        assert_eq!(recognize_fen("8/8/8/8/k3P3/8/8/6K1 w - - 0 1"), None);
+        // from its starting rank the pawn moves two squares at once, so it needs five moves
+        assert!(score("8/8/8/8/8/8/k3P3/6K1 w - - 0 1") > KNOWN_WIN);
+        assert_eq!(recognize_fen("8/8/8/8/8/k7/4P3/6K1 w - - 0 1"), None);
+        // the king in front of the rook pawn holds the draw
+        assert_eq!(
+            recognize_fen("k7/8/8/8/8/8/P7/6K1 w - - 0 1"),
+            Some(Recognized::Draw)
+        );
+    }
+
+    #[test]
+    fn the_wrong_rook_pawn_is_a_draw() {
+        // a8 is a light square and the bishop on c1 a dark-squared one
+        assert_eq!(
+            recognize_fen("k7/8/8/P7/8/8/8/2B1K3 w - - 0 1"),
+            Some(Recognized::Draw)
+        );
+        assert_eq!(
+            recognize_fen("k7/8/8/P7/P7/8/8/2B1K3 w - - 0 1"),
+            Some(Recognized::Draw)
+        );
+        // the right bishop wins, and so does the wrong one when the king is far away
+        assert_ne!(
+            recognize_fen("k7/8/8/P7/8/8/8/3BK3 w - - 0 1"),
+            Some(Recognized::Draw)
+        );
+        assert_ne!(
+            recognize_fen("8/8/8/P7/8/8/4k3/2B1K3 w - - 0 1"),
+            Some(Recognized::Draw)
+        );
+    }
+
+    #[test]
+    fn too_little_material_is_a_draw() {
+        for fen in [
+            "8/8/8/3k4/8/8/8/4K3 w - - 0 1",
+            "8/8/8/3k4/8/8/8/4KN2 w - - 0 1",
+            "8/8/8/3k4/8/8/8/3NKN2 w - - 0 1",
+            // both bishops on dark squares
+            "8/8/8/3k4/8/B7/8/2B1K3 b - - 0 1",
+        ] {
+            assert_eq!(recognize_fen(fen), Some(Recognized::Draw), "{fen}");
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::board::pieces::PieceKind;
+use crate::computer::{time_manager::Clock, Move};
+use crate::game::Game;
+
+/// anything that can choose a move for the side to move; the built-in computer players
+/// implement it and so can any experimental bot
+pub trait Engine: Send {
+    fn name(&self) -> String;
+
+    /// reseeds whatever randomness the engine uses so games can be reproduced;
+    /// deterministic engines can ignore it
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// forgets whatever the engine learned about the previous game
+    fn new_game(&mut self) {}
+
+    /// picks a move for the side to move of `game`, or returns None if there are no legal
+    /// moves; the moves played so far show the engine which positions would repeat
+    ///
+    /// implementations should return as soon as possible once `stop` is set,
+    /// with the best move found so far
+    fn pick_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+        stop: &AtomicBool,
+    ) -> Option<EngineMove>;
+}
+
+/// the node limit of a move when nothing else bounds the search, about a second of thinking;
+/// unlike a time limit it makes the same moves on any machine, so games can be replayed
+pub const DEFAULT_NODES: u64 = 20_000;
+
+/// the bounds of a search; None means the engine decides on its own
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    /// the number of positions to search
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// when playing under a clock the engine splits the remaining time by itself
+    pub clock: Option<Clock>,
+    /// the clock of the other side; only passed on to external engines
+    pub opponent_clock: Option<Clock>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineMove {
+    pub best_move: Move,
+    /// what a pawn reaching the last rank becomes; searches only promote to a queen but a
+    /// book or another engine may underpromote
+    pub promotion: PieceKind,
+    /// engines that don't search have nothing to report
+    pub info: Option<SearchInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u8,
+    /// in centipawns from the point of view of the side to move
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+    /// the line the engine expects to be played; starts with the best move
+    pub pv: Vec<Move>,
+}
+
+impl EngineMove {
+    pub fn new(best_move: Move) -> Self {
+        Self {
+            best_move,
+            promotion: PieceKind::Queen,
+            info: None,
+        }
+    }
+}
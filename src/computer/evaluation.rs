@@ -0,0 +1,159 @@
+use crate::board::{
+    pieces::{PieceColor, PieceKind},
+    position::Position,
+    Board,
+};
+use crate::computer::endgame::{self, Recognized};
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+
+// piece-square tables; written from white's point of view with the 8th rank on top
+// so the table is readable. index them through square_index
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_MIDDLE_GAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_END_GAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+/// the material value of a piece in centipawns; the king is priceless so it counts as 0
+pub fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => PAWN_VALUE,
+        PieceKind::Knight => KNIGHT_VALUE,
+        PieceKind::Bishop => BISHOP_VALUE,
+        PieceKind::Rook => ROOK_VALUE,
+        PieceKind::Queen => QUEEN_VALUE,
+        PieceKind::King => 0,
+    }
+}
+
+/// static evaluation of the board in centipawns from the point of view of `color`
+pub fn evaluate(board: &Board, color: PieceColor) -> i32 {
+    // the endings the recognizers know better than the tables below
+    match endgame::recognize(board, color) {
+        Some(Recognized::Draw) => return 0,
+        Some(Recognized::Score(score)) => return score,
+        None => {}
+    }
+
+    // once the queens are gone, or there is little material left, the king should come out
+    let non_pawn_material: i32 = board
+        .pieces()
+        .filter(|(_, piece)| piece.kind != PieceKind::Pawn)
+        .map(|(_, piece)| piece_value(piece.kind))
+        .sum();
+    let is_end_game = non_pawn_material <= 2 * ROOK_VALUE + 2 * BISHOP_VALUE;
+
+    board
+        .pieces()
+        .map(|(pos, piece)| {
+            let index = square_index(pos, piece.color);
+            let square_bonus = match piece.kind {
+                PieceKind::Pawn => PAWN_TABLE[index],
+                PieceKind::Knight => KNIGHT_TABLE[index],
+                PieceKind::Bishop => BISHOP_TABLE[index],
+                PieceKind::Rook => ROOK_TABLE[index],
+                PieceKind::Queen => QUEEN_TABLE[index],
+                PieceKind::King if is_end_game => KING_END_GAME_TABLE[index],
+                PieceKind::King => KING_MIDDLE_GAME_TABLE[index],
+            };
+            let score = piece_value(piece.kind) + square_bonus;
+            if piece.color == color {
+                score
+            } else {
+                -score
+            }
+        })
+        .sum()
+}
+
+/// maps a position to an index of the piece-square tables above
+fn square_index(pos: Position, color: PieceColor) -> usize {
+    let rank = match color {
+        PieceColor::White => 7 - pos.i(),
+        PieceColor::Black => pos.i(),
+    };
+    (rank * 8 + pos.j()) as usize
+}
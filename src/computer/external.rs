@@ -0,0 +1,421 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::pieces::{PieceColor, PieceKind};
+use crate::computer::engine::{Engine, EngineMove, SearchInfo, SearchLimits};
+use crate::computer::search::MATE_SCORE;
+use crate::computer::{extract_legal_moves, Move};
+use crate::game::Game;
+use crate::uci::{move_to_uci, parse_uci_move};
+use crate::Turn;
+
+/// how long the engine gets to answer "uci" and "isready"
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// how long the engine thinks when the limits leave it up to the engine
+const DEFAULT_MOVETIME: Duration = Duration::from_secs(1);
+/// extra time on top of the limits before the engine is told to stop
+const GRACE: Duration = Duration::from_secs(1);
+/// how long the engine gets to send its move once told to stop
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum ExternalEngineError {
+    /// the process couldn't be started
+    Spawn(io::Error),
+    /// the engine didn't answer in time
+    Timeout,
+    /// the engine closed its output
+    Exited,
+}
+
+impl fmt::Display for ExternalEngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalEngineError::Spawn(err) => write!(f, "couldn't start the engine: {err}"),
+            ExternalEngineError::Timeout => write!(f, "the engine didn't answer in time"),
+            ExternalEngineError::Exited => write!(f, "the engine exited"),
+        }
+    }
+}
+
+/// a UCI engine running in its own process; eg. to play against a reference engine
+///
+/// the engine is given the starting position of the game and the moves since, so it can
+/// detect repetitions. an engine that crashes, hangs or sends an illegal move gets no move
+/// picked, the same as a side without legal moves
+pub struct ExternalEngine {
+    name: String,
+    process: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// whether the engine was told to write castling the Chess960 way
+    chess960: bool,
+}
+
+impl ExternalEngine {
+    /// starts the engine at `path` and performs the UCI handshake
+    pub fn new(path: &str, args: &[&str]) -> Result<Self, ExternalEngineError> {
+        let mut process = Command::new(path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(ExternalEngineError::Spawn)?;
+        let stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+
+        // reading happens on its own thread so every wait for the engine can time out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            name: path.to_string(),
+            process,
+            stdin,
+            lines,
+            chess960: false,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    /// setoption name <name> value <value>; engines ignore options they don't know
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), ExternalEngineError> {
+        self.send(&format!("setoption name {name} value {value}"))?;
+        self.wait_ready()
+    }
+
+    fn wait_ready(&mut self) -> Result<(), ExternalEngineError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), ExternalEngineError> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| ExternalEngineError::Exited)
+    }
+
+    fn read_line(&self, deadline: Instant) -> Result<String, ExternalEngineError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => ExternalEngineError::Timeout,
+            RecvTimeoutError::Disconnected => ExternalEngineError::Exited,
+        })
+    }
+
+    /// position fen <start> moves <moves>; a game from the starting position starts with a
+    /// FEN as well
+    fn position_command(game: &Game) -> String {
+        let mut position = format!("position fen {}", game.start_fen());
+        for (ply, played) in game.moves().iter().enumerate() {
+            if ply == 0 {
+                position += " moves";
+            }
+            let (board, _) = game.position(ply);
+            position += " ";
+            position += &move_to_uci(board, played.as_move(), played.promotion);
+        }
+        position
+    }
+
+    fn go_command(limits: &SearchLimits, turn: Turn) -> String {
+        let mut go = String::from("go");
+        if let Some(depth) = limits.depth {
+            go += &format!(" depth {depth}");
+        }
+        if let Some(nodes) = limits.nodes {
+            go += &format!(" nodes {nodes}");
+        }
+        if let Some(movetime) = limits.movetime {
+            go += &format!(" movetime {}", movetime.as_millis());
+        }
+        if let Some(clock) = limits.clock {
+            // when the other clock isn't known both sides get the same time
+            let opponent = limits.opponent_clock.unwrap_or(clock);
+            let (white, black) = match turn {
+                PieceColor::White => (clock, opponent),
+                PieceColor::Black => (opponent, clock),
+            };
+            go += &format!(
+                " wtime {} btime {} winc {} binc {}",
+                white.remaining.as_millis(),
+                black.remaining.as_millis(),
+                white.increment.as_millis(),
+                black.increment.as_millis(),
+            );
+            if let Some(moves_to_go) = clock.moves_to_go {
+                go += &format!(" movestogo {moves_to_go}");
+            }
+        }
+        if go == "go" {
+            go += &format!(" movetime {}", DEFAULT_MOVETIME.as_millis());
+        }
+        go
+    }
+
+    /// the longest the engine may take before it's told to stop
+    fn time_budget(limits: &SearchLimits) -> Option<Duration> {
+        match (limits.movetime, limits.clock) {
+            (Some(movetime), _) => Some(movetime + GRACE),
+            (None, Some(clock)) => Some(clock.remaining + GRACE),
+            // depth and node limits can take any time
+            (None, None) if limits.depth.is_some() || limits.nodes.is_some() => None,
+            (None, None) => Some(DEFAULT_MOVETIME + GRACE),
+        }
+    }
+}
+
+impl Engine for ExternalEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) {
+        // an engine that doesn't answer any more fails its next move instead
+        let _ = self.send("ucinewgame").and_then(|_| self.wait_ready());
+    }
+
+    fn pick_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+        stop: &AtomicBool,
+    ) -> Option<EngineMove> {
+        let (board, turn) = (game.board(), game.turn());
+        let legal_moves = extract_legal_moves(board, turn);
+        if legal_moves.is_empty() {
+            return None;
+        }
+        // leftovers of an earlier search that was given up on
+        while self.lines.try_recv().is_ok() {}
+
+        if board.is_chess960() != self.chess960 {
+            self.chess960 = board.is_chess960();
+            let value = if self.chess960 { "true" } else { "false" };
+            self.set_option("UCI_Chess960", value).ok()?;
+        }
+
+        self.send(&Self::position_command(game)).ok()?;
+        self.send(&Self::go_command(limits, turn)).ok()?;
+
+        let started = Instant::now();
+        let budget = Self::time_budget(limits);
+        let mut stop_deadline: Option<Instant> = None;
+        let mut info: Option<SearchInfo> = None;
+
+        loop {
+            let must_stop = stop.load(Ordering::Relaxed)
+                || budget.is_some_and(|budget| started.elapsed() > budget);
+            if must_stop && stop_deadline.is_none() {
+                self.send("stop").ok()?;
+                stop_deadline = Some(Instant::now() + STOP_TIMEOUT);
+            }
+            if stop_deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                // the engine hangs; it won't be trusted with another move
+                let _ = self.process.kill();
+                return None;
+            }
+
+            let line = match self.lines.recv_timeout(Duration::from_millis(10)) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return None,
+            };
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    if let Some(parsed) = parse_info(&line) {
+                        info = Some(parsed);
+                    }
+                }
+                Some("bestmove") => {
+                    let (fr, to, promotion) = tokens.next().and_then(parse_uci_move)?;
+                    return legal_moves.contains(&(fr, to)).then_some(EngineMove {
+                        best_move: (fr, to),
+                        promotion: promotion.unwrap_or(PieceKind::Queen),
+                        info,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // give the engine a moment to quit on its own before it's killed
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// reads the search info of an "info" line; None for lines without a score, eg. "info string"
+fn parse_info(line: &str) -> Option<SearchInfo> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let mut info = SearchInfo {
+        depth: 0,
+        score: 0,
+        nodes: 0,
+        time: Duration::ZERO,
+        pv: vec![],
+    };
+    let mut has_score = false;
+    let value = |i: usize| {
+        tokens
+            .get(i + 1)
+            .and_then(|value| value.parse::<i64>().ok())
+    };
+
+    let mut i = 1;
+    while i < tokens.len() {
+        match tokens[i] {
+            "string" => break,
+            "depth" => info.depth = value(i)?.clamp(0, u8::MAX as i64) as u8,
+            "nodes" => info.nodes = value(i)?.max(0) as u64,
+            "time" => info.time = Duration::from_millis(value(i)?.max(0) as u64),
+            "score" => {
+                let score = tokens
+                    .get(i + 2)
+                    .and_then(|value| value.parse::<i32>().ok())?;
+                info.score = match *tokens.get(i + 1)? {
+                    "cp" => score,
+                    "mate" if score > 0 => MATE_SCORE - (2 * score - 1),
+                    "mate" => -MATE_SCORE + 2 * -score,
+                    _ => return None,
+                };
+                has_score = true;
+                i += 1;
+            }
+            "pv" => {
+                info.pv = tokens[i + 1..]
+                    .iter()
+                    .map_while(|notation| parse_uci_move(notation))
+                    .map(|(fr, to, _)| -> Move { (fr, to) })
+                    .collect();
+                break;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    has_score.then_some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::computer::time_manager::Clock;
+
+    const FAKE_ENGINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fake_engine.sh");
+
+    /// what the fake engine was sent, then what it picked when it's told to play `mv`
+    fn play(game: &Game, limits: &SearchLimits, mv: &str) -> (Vec<String>, Option<EngineMove>) {
+        let log = std::env::temp_dir().join(format!("fake_engine_{}_{mv}.log", std::process::id()));
+        let mut engine =
+            ExternalEngine::new("sh", &[FAKE_ENGINE, log.to_str().unwrap(), mv]).unwrap();
+        assert_eq!(engine.name(), "Fake");
+        let engine_move = engine.pick_move(game, limits, &AtomicBool::new(false));
+        drop(engine);
+        let commands = fs::read_to_string(&log).unwrap();
+        let _ = fs::remove_file(&log);
+        (commands.lines().map(str::to_string).collect(), engine_move)
+    }
+
+    fn clock(millis: u64, increment: u64) -> Clock {
+        Clock {
+            remaining: Duration::from_millis(millis),
+            increment: Duration::from_millis(increment),
+            moves_to_go: None,
+        }
+    }
+
+    #[test]
+    fn sends_the_moves_of_the_game_and_both_clocks() {
+        let mut game = Game::default();
+        for mv in ["e2e4", "e7e5", "g1f3"] {
+            let (fr, to, _) = parse_uci_move(mv).unwrap();
+            game.play(fr, to, PieceKind::Queen).unwrap();
+        }
+        let limits = SearchLimits {
+            clock: Some(clock(30_000, 1_000)),
+            opponent_clock: Some(clock(60_000, 2_000)),
+            ..SearchLimits::default()
+        };
+        let (commands, engine_move) = play(&game, &limits, "b8c6");
+        assert!(commands.contains(
+            &"position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves e2e4 e7e5 g1f3"
+                .to_string()
+        ));
+        assert!(commands.contains(&"go wtime 60000 btime 30000 winc 2000 binc 1000".to_string()));
+
+        let engine_move = engine_move.unwrap();
+        assert_eq!(
+            engine_move.best_move,
+            parse_uci_move("b8c6").map(|(fr, to, _)| (fr, to)).unwrap()
+        );
+        assert_eq!(engine_move.info.unwrap().score, 12);
+    }
+
+    #[test]
+    fn sends_the_starting_fen_and_the_promotions() {
+        let mut game = Game::from_fen("8/P6k/8/8/8/8/6Kp/8 w - - 3 40").unwrap();
+        for mv in ["a7a8n", "h2h1r"] {
+            let (fr, to, promotion) = parse_uci_move(mv).unwrap();
+            game.play(fr, to, promotion.unwrap()).unwrap();
+        }
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let (commands, engine_move) = play(&game, &limits, "g2h1");
+        assert!(commands
+            .contains(&"position fen 8/P6k/8/8/8/8/6Kp/8 w - - 3 40 moves a7a8n h2h1r".to_string()));
+        assert!(commands.contains(&"go depth 3".to_string()));
+        assert!(engine_move.is_some());
+    }
+
+    #[test]
+    fn an_illegal_move_is_no_move() {
+        let (_, engine_move) = play(&Game::default(), &SearchLimits::default(), "e2e5");
+        assert!(engine_move.is_none());
+    }
+}
@@ -1,4 +1,7 @@
-use rand::Rng;
+use std::sync::atomic::AtomicBool;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use crate::board::{
     pieces::{
@@ -8,11 +11,37 @@ use crate::board::{
     position::Position,
     Board,
 };
+use crate::game::Game;
+use engine::{Engine, EngineMove, SearchLimits};
+
+pub mod analysis;
+pub mod book;
+pub mod difficulty;
+pub mod endgame;
+pub mod engine;
+pub mod evaluation;
+pub mod external;
+pub mod search;
+pub mod selfplay;
+pub mod syzygy;
+pub mod time_manager;
+pub mod transposition;
+
+pub type Move = (Position, Position);
+
+/// picks a random move; preferring captures then moves that don't hang the piece
+pub struct ComputerEngine {
+    rng: StdRng,
+}
 
-type Move = (Position, Position);
-pub struct ComputerEngine;
 impl ComputerEngine {
-    pub fn pick_move(&self, board: &Board, color: PieceColor) -> Move {
+    pub fn new() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn pick_move(&mut self, board: &Board, color: PieceColor) -> Move {
         let legal_moves: Vec<Move> = extract_legal_moves(board, color);
         let capturing_moves: Vec<Move> = get_capturing_moves(board, color, &legal_moves);
 
@@ -20,12 +49,41 @@ impl ComputerEngine {
             let safe_moves = get_safe_moves(board, color, &legal_moves);
 
             if safe_moves.is_empty() {
-                return pick_random_move(legal_moves);
+                return pick_random_move(&mut self.rng, legal_moves);
             }
-            return pick_random_move(safe_moves);
+            return pick_random_move(&mut self.rng, safe_moves);
         }
 
-        pick_random_move(capturing_moves)
+        pick_random_move(&mut self.rng, capturing_moves)
+    }
+}
+
+impl Default for ComputerEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine for ComputerEngine {
+    fn name(&self) -> String {
+        "Random".to_string()
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn pick_move(
+        &mut self,
+        game: &Game,
+        _limits: &SearchLimits,
+        _stop: &AtomicBool,
+    ) -> Option<EngineMove> {
+        let (board, turn) = (game.board(), game.turn());
+        if extract_legal_moves(board, turn).is_empty() {
+            return None;
+        }
+        Some(EngineMove::new(ComputerEngine::pick_move(self, board, turn)))
     }
 }
 
@@ -43,7 +101,7 @@ fn get_safe_moves(board: &Board, color: PieceColor, legal_moves: &Vec<Move>) ->
     safe_moves
 }
 
-fn extract_legal_moves(board: &Board, color: PieceColor) -> Vec<Move> {
+pub(crate) fn extract_legal_moves(board: &Board, color: PieceColor) -> Vec<Move> {
     let positions = board.get_all_pieces_pos_by_color(color);
     let all_legal_moves: Vec<Move> = positions
         .iter()
@@ -72,7 +130,7 @@ fn get_capturing_moves(board: &Board, color: PieceColor, legal_moves: &Vec<Move>
     res
 }
 
-fn pick_random_move(moves: Vec<Move>) -> Move {
-    let fr_rand = rand::thread_rng().gen_range(0..moves.len());
+fn pick_random_move(rng: &mut StdRng, moves: Vec<Move>) -> Move {
+    let fr_rand = rng.gen_range(0..moves.len());
     moves[fr_rand]
 }
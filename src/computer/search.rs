@@ -0,0 +1,953 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::board::{
+    pieces::{moves, PieceColor, PieceKind},
+    Board,
+};
+use crate::computer::{
+    book::{BookSelection, OpeningBook},
+    difficulty::Difficulty,
+    endgame::{self, Recognized},
+    engine::{Engine, EngineMove, SearchInfo, SearchLimits},
+    evaluation::{self, piece_value},
+    extract_legal_moves,
+    syzygy::{Tablebase, Wdl},
+    time_manager::TimeManager,
+    transposition::{Bound, TableEntry, TranspositionTable},
+    Move,
+};
+use crate::game::Game;
+
+/// score of being checkmated on the root; mates found deeper are closer to zero
+pub const MATE_SCORE: i32 = 100_000;
+const INFINITY: i32 = MATE_SCORE + 1;
+/// any score beyond this is a forced mate
+const MATE_BOUND: i32 = MATE_SCORE - 1000;
+/// a position the tablebases say is won; below the mates but above any evaluation
+const TB_WIN_SCORE: i32 = MATE_BOUND - 1000;
+/// captures are resolved at most this many plies past the nominal depth
+const MAX_QUIESCENCE_DEPTH: u8 = 4;
+/// iterative deepening goes on until this depth when the limits don't say otherwise
+pub const MAX_DEPTH: u8 = 64;
+/// a score this much worse than the previous iteration's means the best move is in trouble
+const FAIL_LOW_MARGIN: i32 = 30;
+/// the book is consulted for this many of the engine's moves of a game unless told otherwise
+pub const DEFAULT_BOOK_DEPTH: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Move,
+    /// in centipawns from the point of view of the side to move
+    pub score: i32,
+    /// the last fully searched depth
+    pub depth: u8,
+    pub nodes: u64,
+    pub time: Duration,
+    /// the line the engine expects to be played; starts with the best move
+    pub pv: Vec<Move>,
+    /// every root move with its score from the last completed iteration; best first
+    pub root_moves: Vec<(Move, i32)>,
+    /// the score and line of each of the best `multi_pv` root moves; best first
+    pub lines: Vec<(i32, Vec<Move>)>,
+}
+
+impl From<&SearchResult> for SearchInfo {
+    fn from(result: &SearchResult) -> Self {
+        SearchInfo {
+            depth: result.depth,
+            score: result.score,
+            nodes: result.nodes,
+            time: result.time,
+            pv: result.pv.clone(),
+        }
+    }
+}
+
+/// a computer player that searches the game tree; its strength depends on the difficulty
+pub struct SearchEngine {
+    /// None plays at full strength; only the caller's limits bound the search
+    difficulty: Option<Difficulty>,
+    rng: StdRng,
+    threads: usize,
+    tt: TranspositionTable,
+    book: Option<Arc<OpeningBook>>,
+    book_selection: BookSelection,
+    book_depth: usize,
+    tablebase: Option<Arc<Tablebase>>,
+    /// the number of plies and the position of the game searched last
+    last_game: Option<(usize, u64)>,
+}
+
+impl SearchEngine {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            difficulty: Some(difficulty),
+            rng: StdRng::from_entropy(),
+            threads: 1,
+            tt: TranspositionTable::default(),
+            book: None,
+            book_selection: BookSelection::default(),
+            book_depth: DEFAULT_BOOK_DEPTH,
+            tablebase: None,
+            last_game: None,
+        }
+    }
+
+    /// an engine without a difficulty; it searches until the limits it is given are reached
+    pub fn full_strength() -> Self {
+        Self {
+            difficulty: None,
+            ..Self::new(Difficulty::default())
+        }
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.difficulty
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Option<Difficulty>) {
+        self.difficulty = difficulty;
+    }
+
+    /// more than one thread makes the engine stronger but its games no longer reproducible
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// replaces the transposition table with an empty one of `size_mb` megabytes
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.tt = TranspositionTable::new(size_mb);
+    }
+
+    /// an opening book to play from before searching; the book is shared since it can be big
+    pub fn set_book(&mut self, book: Option<Arc<OpeningBook>>) {
+        self.book = book;
+    }
+
+    pub fn set_book_selection(&mut self, selection: BookSelection) {
+        self.book_selection = selection;
+    }
+
+    /// the book is only consulted for the first `depth` moves the engine plays in a game
+    pub fn set_book_depth(&mut self, depth: usize) {
+        self.book_depth = depth;
+    }
+
+    /// endgame tablebases to probe; the search stops at positions they know the outcome of
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    /// forgets the positions searched so far; eg. when a new game starts
+    pub fn clear_hash(&self) {
+        self.tt.clear();
+    }
+
+    /// same as Engine::pick_move but `on_info` hears about every completed iteration
+    pub fn think(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+        stop: &AtomicBool,
+        on_info: &mut dyn FnMut(&SearchInfo),
+    ) -> Option<EngineMove> {
+        let (board, turn) = (game.board(), game.turn());
+        // the table scores going back to a position of the game as a draw; once moves are
+        // taken back those positions may no longer be part of the game
+        let plies = game.moves().len();
+        let went_back = self.last_game.is_some_and(|(last_plies, last_key)| {
+            last_plies > plies || {
+                let (board, turn) = game.position(last_plies);
+                board.zobrist_hash(turn) != last_key
+            }
+        });
+        if went_back {
+            self.clear_hash();
+        }
+        self.last_game = Some((plies, board.zobrist_hash(turn)));
+
+        // the moves of the side to move so far, counting this one
+        let moves_played = game.moves().len() / 2 + 1;
+        if let Some(book) = self
+            .book
+            .as_ref()
+            .filter(|_| moves_played <= self.book_depth)
+        {
+            if let Some(book_move) = book.pick(board, turn, self.book_selection, &mut self.rng) {
+                return Some(EngineMove {
+                    promotion: book_move.promotion.unwrap_or(PieceKind::Queen),
+                    ..EngineMove::new(book_move.as_move())
+                });
+            }
+        }
+
+        let settings = self.difficulty.map(Difficulty::settings);
+        // the level is an upper bound; the caller may only ask for less
+        let limits = match settings {
+            Some(settings) => SearchLimits {
+                depth: Some(
+                    limits
+                        .depth
+                        .map_or(settings.depth, |d| d.min(settings.depth)),
+                ),
+                nodes: Some(
+                    limits
+                        .nodes
+                        .map_or(settings.nodes, |n| n.min(settings.nodes)),
+                ),
+                ..*limits
+            },
+            None => *limits,
+        };
+        let (noise, blunder_chance) = settings.map_or((0, 0.0), |settings| {
+            (settings.noise, settings.blunder_chance)
+        });
+        // going back to a position of the game is scored as a draw
+        let history: HashSet<u64> = game.reversible_hashes().iter().copied().collect();
+        let config = SearchConfig {
+            threads: self.threads,
+            // noise and blunders need the exact score of every root move not just the best one
+            exact_root_scores: noise > 0 || blunder_chance > 0.0,
+            multi_pv: 1,
+            tablebase: self.tablebase.as_deref(),
+            halfmove_clock: game.halfmove_clock(),
+            history: Some(&history),
+        };
+        let result = search(
+            board,
+            turn,
+            &limits,
+            &config,
+            &self.tt,
+            stop,
+            &mut |result| on_info(&SearchInfo::from(result)),
+        )?;
+        let info = SearchInfo::from(&result);
+
+        let rng = &mut self.rng;
+        let mut candidates: Vec<(Move, i32)> = result
+            .root_moves
+            .into_iter()
+            .map(|(mv, score)| (mv, score + rng.gen_range(-noise..=noise)))
+            .collect();
+        candidates.sort_by_key(|(_, score)| Reverse(*score));
+
+        let best_move = if candidates.len() > 1 && rng.gen_bool(blunder_chance) {
+            // a human-looking mistake; one of the runner-up moves instead of a random one
+            let runner_ups = candidates.len().min(4);
+            candidates[rng.gen_range(1..runner_ups)].0
+        } else {
+            candidates[0].0
+        };
+        Some(EngineMove {
+            best_move,
+            promotion: PieceKind::Queen,
+            info: Some(info),
+        })
+    }
+}
+
+impl Engine for SearchEngine {
+    fn name(&self) -> String {
+        match self.difficulty {
+            Some(difficulty) => format!("Computer ({difficulty})"),
+            None => "Computer".to_string(),
+        }
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn new_game(&mut self) {
+        self.clear_hash();
+        self.last_game = None;
+    }
+
+    fn pick_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+        stop: &AtomicBool,
+    ) -> Option<EngineMove> {
+        self.think(game, limits, stop, &mut |_| {})
+    }
+}
+
+/// how the search spreads its work; the limits say how far it goes
+#[derive(Clone, Copy)]
+pub struct SearchConfig<'a> {
+    /// 1 keeps the search deterministic; more threads search the same tree in parallel
+    /// and share their findings through the transposition table (lazy SMP)
+    pub threads: usize,
+    /// when set every root move is searched with a full window,
+    /// otherwise only the score of the best move is exact
+    pub exact_root_scores: bool,
+    /// how many of the best root moves get an exact score and a line of their own
+    pub multi_pv: usize,
+    /// at the root only the moves that keep the tablebase outcome are searched
+    pub tablebase: Option<&'a Tablebase>,
+    /// plies since the last capture or pawn move; the tablebase needs them to tell wins
+    /// from wins that come too late for the fifty-move rule
+    pub halfmove_clock: u32,
+    /// positions that were played already; the search scores them as draws so it doesn't
+    /// go round in circles
+    pub history: Option<&'a HashSet<u64>>,
+}
+
+impl Default for SearchConfig<'_> {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            exact_root_scores: false,
+            multi_pv: 1,
+            tablebase: None,
+            halfmove_clock: 0,
+            history: None,
+        }
+    }
+}
+
+/// iterative deepening alpha-beta search; returns None if `color` has no legal moves
+///
+/// setting `stop` aborts the search; the last completed iteration is returned.
+/// `on_iteration` is called with the result of every completed iteration
+pub fn search(
+    board: &Board,
+    color: PieceColor,
+    limits: &SearchLimits,
+    config: &SearchConfig,
+    tt: &TranspositionTable,
+    stop: &AtomicBool,
+    on_iteration: &mut dyn FnMut(&SearchResult),
+) -> Option<SearchResult> {
+    let mut root_moves = extract_legal_moves(board, color);
+    if root_moves.is_empty() {
+        return None;
+    }
+    if let Some(tb_moves) = config
+        .tablebase
+        .and_then(|tablebase| tablebase.root_moves(board, color, config.halfmove_clock))
+    {
+        root_moves.retain(|mv| tb_moves.contains(mv));
+    }
+    let root_key = board.zobrist_hash(color);
+    order_moves(
+        board,
+        &mut root_moves,
+        tt.probe(root_key).and_then(|e| e.best_move),
+    );
+    let max_depth = limits.depth.unwrap_or(MAX_DEPTH).max(1);
+
+    // set by the main thread once it is done so the helpers stop too
+    let finished = AtomicBool::new(false);
+    let helper_nodes = AtomicU64::new(0);
+
+    let result = thread::scope(|scope| {
+        for id in 1..config.threads.max(1) {
+            let mut root_moves = root_moves.clone();
+            let (finished, helper_nodes) = (&finished, &helper_nodes);
+            scope.spawn(move || {
+                let mut searcher = Searcher::new(tt, [stop, finished]);
+                searcher.tablebase = config.tablebase;
+                searcher.history = config.history;
+                // half of the helpers stay a ply ahead of the main thread so the threads
+                // don't all search the same nodes at the same time
+                let first_depth = 1 + (id % 2) as u8;
+                for depth in first_depth..=max_depth {
+                    match searcher.search_root(board, color, &root_moves, depth, config) {
+                        Some(scored) => root_moves = scored.into_iter().map(|(mv, _)| mv).collect(),
+                        None => break,
+                    }
+                }
+                helper_nodes.fetch_add(searcher.nodes, Ordering::Relaxed);
+            });
+        }
+
+        let mut searcher = Searcher::new(tt, [stop, stop]);
+        searcher.tablebase = config.tablebase;
+        searcher.history = config.history;
+        let result = iterative_deepening(
+            searcher,
+            board,
+            color,
+            root_moves,
+            limits,
+            config,
+            on_iteration,
+        );
+        finished.store(true, Ordering::Relaxed);
+        result
+    });
+
+    Some(SearchResult {
+        nodes: result.nodes + helper_nodes.load(Ordering::Relaxed),
+        ..result
+    })
+}
+
+/// the main thread's search; the only one that minds the limits and reports a result
+fn iterative_deepening(
+    mut searcher: Searcher,
+    board: &Board,
+    color: PieceColor,
+    mut root_moves: Vec<Move>,
+    limits: &SearchLimits,
+    config: &SearchConfig,
+    on_iteration: &mut dyn FnMut(&SearchResult),
+) -> SearchResult {
+    let started = Instant::now();
+    let mut time_manager = limits.clock.map(TimeManager::new);
+    // running out of time on the clock loses the game; that deadline holds from the start
+    let hard_deadline = time_manager.as_ref().map(TimeManager::hard_deadline);
+    let deadline = [
+        limits.movetime.map(|movetime| Instant::now() + movetime),
+        hard_deadline,
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+    // otherwise the first iteration runs without a deadline so there is always a move to play
+    searcher.deadline = hard_deadline;
+    let mut result: Option<SearchResult> = None;
+
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).max(1) {
+        let Some(scored) = searcher.search_root(board, color, &root_moves, depth, config) else {
+            break;
+        };
+        root_moves = scored.iter().map(|(mv, _)| *mv).collect();
+        let (best_move, score) = scored[0];
+        let lines: Vec<(i32, Vec<Move>)> = scored
+            .iter()
+            .take(config.multi_pv.max(1))
+            .map(|(mv, score)| {
+                let pv = principal_variation(board, color, *mv, searcher.tt, depth as usize);
+                (*score, pv)
+            })
+            .collect();
+        let iteration = SearchResult {
+            best_move,
+            score,
+            depth,
+            nodes: searcher.nodes,
+            time: started.elapsed(),
+            pv: lines[0].1.clone(),
+            root_moves: scored,
+            lines,
+        };
+        on_iteration(&iteration);
+        let previous = result.replace(iteration);
+        searcher.deadline = deadline;
+        searcher.max_nodes = limits.nodes;
+
+        // no need to look any further once a forced mate is found
+        if score.abs() >= MATE_SCORE - depth as i32 {
+            break;
+        }
+        if let Some(time_manager) = &mut time_manager {
+            if let Some(previous) = previous {
+                time_manager.on_iteration(
+                    previous.best_move != best_move,
+                    score < previous.score - FAIL_LOW_MARGIN,
+                );
+            }
+            if !time_manager.can_start_iteration() {
+                break;
+            }
+        }
+    }
+
+    // stopped before the first iteration was done; any legal move is better than none
+    result.unwrap_or(SearchResult {
+        best_move: root_moves[0],
+        score: 0,
+        depth: 0,
+        nodes: searcher.nodes,
+        time: started.elapsed(),
+        pv: vec![root_moves[0]],
+        root_moves: vec![(root_moves[0], 0)],
+        lines: vec![(0, vec![root_moves[0]])],
+    })
+}
+
+/// follows the best moves stored in the transposition table, starting with `best_move`
+fn principal_variation(
+    board: &Board,
+    color: PieceColor,
+    best_move: Move,
+    tt: &TranspositionTable,
+    max_len: usize,
+) -> Vec<Move> {
+    let mut pv = vec![best_move];
+    let mut board = board.clone();
+    let mut turn = color;
+    board.move_piece_unchecked(&mut turn, best_move.0, best_move.1);
+
+    while pv.len() < max_len {
+        let Some(mv) = tt
+            .probe(board.zobrist_hash(turn))
+            .and_then(|entry| entry.best_move)
+        else {
+            break;
+        };
+        // a hash collision could hand out the move of another position
+        if !extract_legal_moves(&board, turn).contains(&mv) {
+            break;
+        }
+        board.move_piece_unchecked(&mut turn, mv.0, mv.1);
+        pv.push(mv);
+    }
+    pv
+}
+
+struct Searcher<'a> {
+    tt: &'a TranspositionTable,
+    tablebase: Option<&'a Tablebase>,
+    history: Option<&'a HashSet<u64>>,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    /// the caller's stop flag and the one the main thread raises for its helpers
+    stops: [&'a AtomicBool; 2],
+    nodes: u64,
+    aborted: bool,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(tt: &'a TranspositionTable, stops: [&'a AtomicBool; 2]) -> Self {
+        Self {
+            tt,
+            tablebase: None,
+            history: None,
+            deadline: None,
+            max_nodes: None,
+            stops,
+            nodes: 0,
+            aborted: false,
+        }
+    }
+
+    /// searches every root move to `depth`; returns the moves with their scores, best first,
+    /// or None if the search was aborted before the iteration was complete
+    fn search_root(
+        &mut self,
+        board: &Board,
+        color: PieceColor,
+        root_moves: &[Move],
+        depth: u8,
+        config: &SearchConfig,
+    ) -> Option<Vec<(Move, i32)>> {
+        let mut alpha = -INFINITY;
+        let mut scored = Vec::with_capacity(root_moves.len());
+
+        for mv in root_moves {
+            let mut board = board.clone();
+            let mut turn = color;
+            board.move_piece_unchecked(&mut turn, mv.0, mv.1);
+
+            let score = -self.negamax(&board, turn, depth - 1, -INFINITY, -alpha, 1);
+            if self.aborted {
+                return None;
+            }
+            scored.push((*mv, score));
+            // the moves that can't make it into the best multi_pv only have to be shown worse
+            let multi_pv = config.multi_pv.max(1);
+            if !config.exact_root_scores && scored.len() >= multi_pv {
+                let mut scores: Vec<i32> = scored.iter().map(|(_, score)| *score).collect();
+                scores.sort_unstable_by_key(|score| Reverse(*score));
+                alpha = scores[multi_pv - 1];
+            }
+        }
+
+        // sort_by_key is stable; equal moves keep the order of the previous iteration
+        scored.sort_by_key(|(_, score)| Reverse(*score));
+        let (best_move, score) = scored[0];
+        self.tt.store(
+            board.zobrist_hash(color),
+            TableEntry {
+                best_move: Some(best_move),
+                depth,
+                score,
+                bound: Bound::Exact,
+            },
+        );
+        Some(scored)
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        color: PieceColor,
+        depth: u8,
+        mut alpha: i32,
+        beta: i32,
+        ply: i32,
+    ) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiescence(board, color, alpha, beta, MAX_QUIESCENCE_DEPTH);
+        }
+        self.nodes += 1;
+
+        let key = board.zobrist_hash(color);
+        if self.history.is_some_and(|history| history.contains(&key)) {
+            return 0;
+        }
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.probe(key) {
+            tt_move = entry.best_move;
+            let score = score_from_tt(entry.score, ply);
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(score) = self.probe_tablebase(board, color, ply) {
+            self.tt.store(
+                key,
+                TableEntry {
+                    best_move: tt_move,
+                    depth: MAX_DEPTH,
+                    score: score_to_tt(score, ply),
+                    bound: Bound::Exact,
+                },
+            );
+            return score;
+        }
+        let mut legal_moves = extract_legal_moves(board, color);
+        if legal_moves.is_empty() {
+            return if board.is_king_safe(color) {
+                0 // stalemate
+            } else {
+                -MATE_SCORE + ply
+            };
+        }
+        // nothing to search for in a drawn ending; checked after the mates since some of
+        // them, eg. two knights, still mate a king that walks into it
+        if endgame::recognize(board, color) == Some(Recognized::Draw) {
+            return 0;
+        }
+        order_moves(board, &mut legal_moves, tt_move);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for mv in legal_moves {
+            let mut board = board.clone();
+            let mut turn = color;
+            board.move_piece_unchecked(&mut turn, mv.0, mv.1);
+
+            let score = -self.negamax(&board, turn, depth - 1, -beta, -alpha, ply + 1);
+            if self.aborted {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(
+            key,
+            TableEntry {
+                best_move,
+                depth,
+                score: score_to_tt(best_score, ply),
+                bound,
+            },
+        );
+        best_score
+    }
+
+    /// only looks at captures so the static evaluation is not taken in the middle of an exchange
+    fn quiescence(
+        &mut self,
+        board: &Board,
+        color: PieceColor,
+        mut alpha: i32,
+        beta: i32,
+        depth: u8,
+    ) -> i32 {
+        self.nodes += 1;
+        let stand_pat = evaluation::evaluate(board, color);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+        if depth == 0 || self.should_stop() {
+            return alpha;
+        }
+
+        let mut captures = capturing_moves(board, color);
+        order_moves(board, &mut captures, None);
+
+        for mv in captures {
+            let mut board = board.clone();
+            let mut turn = color;
+            board.move_piece_unchecked(&mut turn, mv.0, mv.1);
+
+            let score = -self.quiescence(&board, turn, -beta, -alpha, depth - 1);
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// the score the tablebases give the position, if they have it; wins and losses the
+    /// fifty-move rule saves count as draws
+    fn probe_tablebase(&self, board: &Board, color: PieceColor, ply: i32) -> Option<i32> {
+        Some(match self.tablebase?.probe_wdl(board, color)? {
+            Wdl::Win => TB_WIN_SCORE - ply,
+            Wdl::Loss => -TB_WIN_SCORE + ply,
+            Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0,
+        })
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stops.iter().any(|stop| stop.load(Ordering::Relaxed)) {
+            self.aborted = true;
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                self.aborted = true;
+            }
+        }
+        if let Some(max_nodes) = self.max_nodes {
+            if self.nodes >= max_nodes {
+                self.aborted = true;
+            }
+        }
+        self.aborted
+    }
+}
+
+// mate scores are relative to the root; the table stores them relative to the position
+// itself so they stay right when the position is reached through another path
+
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply
+    } else if score <= -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply
+    } else if score <= -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+/// legal moves of `color` that capture a piece; en-passant is left out
+fn capturing_moves(board: &Board, color: PieceColor) -> Vec<Move> {
+    let is_capture =
+        |to| matches!(board.look_up_cell(to), Some(Some(piece)) if piece.color != color);
+    let mut captures = vec![];
+    for fr in board.get_all_pieces_pos_by_color(color) {
+        // the unchecked moves are way cheaper; only verify the pieces that can capture something
+        if !moves::get_legal_moves_unchecked(fr, board)
+            .into_iter()
+            .any(is_capture)
+        {
+            continue;
+        }
+        captures.extend(
+            moves::get_legal_moves(fr, board, &color)
+                .into_iter()
+                .filter(|to| is_capture(*to))
+                .map(|to| (fr, to)),
+        );
+    }
+    captures
+}
+
+/// the move from the transposition table first, then most valuable victim / least valuable
+/// attacker; quiet moves keep their order at the end
+fn order_moves(board: &Board, moves: &mut [Move], tt_move: Option<Move>) {
+    moves.sort_by_cached_key(|&(fr, to)| {
+        if Some((fr, to)) == tt_move {
+            return Reverse(i32::MAX);
+        }
+        let attacker = board.look_up_cell(fr).unwrap().as_ref().unwrap();
+        match board.look_up_cell(to).unwrap() {
+            Some(victim) if victim.color != attacker.color => Reverse(10 * piece_value(victim.kind) - piece_value(attacker.kind) + 1),
+            _ => Reverse(0),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::{Position, PositionNotation};
+    use crate::computer::book::encode_move;
+
+    #[test]
+    fn single_thread_search_is_deterministic() {
+        let (board, turn) =
+            Board::parse_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let run = || {
+            let tt = TranspositionTable::new(16);
+            let stop = AtomicBool::new(false);
+            search(
+                &board,
+                turn,
+                &limits,
+                &SearchConfig::default(),
+                &tt,
+                &stop,
+                &mut |_| {},
+            )
+            .unwrap()
+        };
+        let (first, second) = (run(), run());
+        assert_eq!(first.depth, 3);
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.pv, second.pv);
+    }
+
+    #[test]
+    fn book_moves_keep_their_promotion() {
+        let (board, turn) = Board::parse_fen("8/1P6/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        let (fr, to) = (
+            Position::from_notation("b7").unwrap(),
+            Position::from_notation("b8").unwrap(),
+        );
+        let mut bytes = board.zobrist_hash(turn).to_be_bytes().to_vec();
+        bytes.extend(encode_move(&board, fr, to, Some(PieceKind::Knight)).to_be_bytes());
+        bytes.extend(1u16.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        let mut engine = SearchEngine::full_strength();
+        engine.set_book(Some(Arc::new(OpeningBook::from_bytes(&bytes))));
+
+        let stop = AtomicBool::new(false);
+        let engine_move = engine
+            .pick_move(
+                &Game::new(board.clone(), turn),
+                &SearchLimits::default(),
+                &stop,
+            )
+            .unwrap();
+        assert_eq!(engine_move.best_move, (fr, to));
+        assert_eq!(engine_move.promotion, PieceKind::Knight);
+    }
+
+    #[test]
+    fn going_back_to_a_position_of_the_game_is_a_draw() {
+        let (board, turn) = Board::parse_fen("k7/8/8/8/8/8/8/2K1Q3 b - - 0 1").unwrap();
+        let mut game = Game::new(board, turn);
+        let square = |notation| Position::from_notation(notation).unwrap();
+        for (fr, to) in [("a8", "a7"), ("e1", "e2"), ("a7", "a8"), ("e2", "e1")] {
+            game.play(square(fr), square(to), PieceKind::Queen).unwrap();
+        }
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let stop = AtomicBool::new(false);
+        let mut engine = SearchEngine::full_strength();
+        // the lone king is lost anywhere else; Ka7 brings back the position after its move
+        let engine_move = engine.pick_move(&game, &limits, &stop).unwrap();
+        assert_eq!(engine_move.best_move, (square("a8"), square("a7")));
+        assert_eq!(engine_move.info.unwrap().score, 0);
+
+        // once the moves are taken back there is nothing to repeat
+        for _ in 0..4 {
+            game.undo();
+        }
+        let engine_move = engine.pick_move(&game, &limits, &stop).unwrap();
+        assert!(engine_move.info.unwrap().score < 0);
+    }
+
+    #[test]
+    fn taking_moves_back_forgets_the_scores_of_the_longer_game() {
+        let (board, turn) = Board::parse_fen("8/8/8/4k3/8/8/3PK3/8 b - - 0 1").unwrap();
+        let mut game = Game::new(board, turn);
+        let square = |notation| Position::from_notation(notation).unwrap();
+        for (fr, to) in [
+            ("e5", "d5"),
+            ("e2", "e3"),
+            ("d5", "e5"),
+            ("e3", "e2"),
+            ("e5", "d5"),
+            ("e2", "e3"),
+        ] {
+            game.play(square(fr), square(to), PieceKind::Queen).unwrap();
+        }
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let stop = AtomicBool::new(false);
+        let mut engine = SearchEngine::full_strength();
+        engine.pick_move(&game, &limits, &stop).unwrap();
+
+        for _ in 0..5 {
+            game.undo();
+        }
+        let engine_move = engine.pick_move(&game, &limits, &stop).unwrap();
+        let fresh = SearchEngine::full_strength()
+            .pick_move(&game, &limits, &stop)
+            .unwrap();
+        assert_eq!(engine_move.best_move, fresh.best_move);
+        assert_eq!(engine_move.info.unwrap().score, fresh.info.unwrap().score);
+    }
+
+    #[test]
+    fn two_knights_still_mate() {
+        let (board, turn) = Board::parse_fen("k7/3N4/1K2N3/8/8/8/8/8 w - - 0 1").unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let mut engine = SearchEngine::full_strength();
+        let engine_move = engine
+            .pick_move(&Game::new(board, turn), &limits, &AtomicBool::new(false))
+            .unwrap();
+        let square = |notation| Position::from_notation(notation).unwrap();
+        assert_eq!(engine_move.best_move, (square("e6"), square("c7")));
+        assert_eq!(engine_move.info.unwrap().score, MATE_SCORE - 1);
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::AtomicBool;
+
+use crate::board::pieces::PieceColor;
+use crate::board::Board;
+use crate::computer::engine::{Engine, SearchLimits};
+use crate::game::{Game, GameResult, Termination};
+use crate::Turn;
+
+/// the seed handed to the engine playing `color`; the GUI and the headless runner both
+/// derive the engines' seeds from the game's seed through here so their games match
+pub fn seed_for(seed: u64, color: PieceColor) -> u64 {
+    match color {
+        PieceColor::White => seed,
+        PieceColor::Black => seed.wrapping_add(1),
+    }
+}
+
+/// plays a game between two engines without a window
+///
+/// the game goes on until it's over by the rules; after `max_plies` it's adjudicated a draw,
+/// and an engine that doesn't come up with a legal move loses by forfeit
+pub fn play_game(
+    board: Board,
+    turn: Turn,
+    white: &mut dyn Engine,
+    black: &mut dyn Engine,
+    limits: &SearchLimits,
+    seed: u64,
+    max_plies: usize,
+) -> Game {
+    white.new_game();
+    white.set_seed(seed_for(seed, PieceColor::White));
+    black.new_game();
+    black.set_seed(seed_for(seed, PieceColor::Black));
+
+    let stop = AtomicBool::new(false);
+    let mut game = Game::new(board, turn);
+
+    while game.outcome().is_none() {
+        if game.moves().len() >= max_plies {
+            game.adjudicate(GameResult::Draw, Termination::MoveLimit);
+            break;
+        }
+        let turn = game.turn();
+        let engine_move = match turn {
+            PieceColor::White => white.pick_move(&game, limits, &stop),
+            PieceColor::Black => black.pick_move(&game, limits, &stop),
+        };
+        let played = engine_move.map(|engine_move| {
+            let (fr, to) = engine_move.best_move;
+            game.play(fr, to, engine_move.promotion)
+        });
+        if !matches!(played, Some(Ok(()))) {
+            let winner = match turn {
+                PieceColor::White => PieceColor::Black,
+                PieceColor::Black => PieceColor::White,
+            };
+            game.adjudicate(GameResult::win_for(winner), Termination::Forfeit);
+        }
+    }
+    game
+}
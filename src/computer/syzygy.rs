@@ -0,0 +1,1485 @@
+//! probing of Syzygy endgame tablebases
+//!
+//! the tables tell whether a position with few pieces is won, drawn or lost (WDL) and how
+//! many plies it takes to the next capture or pawn move of the winning line (DTZ). the
+//! decoding follows the reference implementation that comes with the tables
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::board::pieces::{PieceColor, PieceKind};
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::computer::{extract_legal_moves, Move};
+use crate::Turn;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+/// the most pieces a table can have
+const MAX_PIECES: usize = 7;
+/// ranks a certain win at the root above everything else
+const MAX_DTZ: i32 = 1 << 18;
+
+/// the outcome of a position with perfect play; the cursed win and the blessed loss are a
+/// win and a loss that the fifty-move rule turns into a draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as i32)).unwrap()
+    }
+}
+
+/// the tables found in one or more directories; they are read the first time they're needed
+pub struct Tablebase {
+    /// by name; eg. "KRvK"
+    tables: HashMap<String, TableFiles>,
+    max_pieces: usize,
+}
+
+#[derive(Default)]
+struct TableFiles {
+    wdl: Option<PathBuf>,
+    dtz: Option<PathBuf>,
+    wdl_table: OnceLock<Option<Table>>,
+    dtz_table: OnceLock<Option<Table>>,
+}
+
+impl Tablebase {
+    /// looks for tables in `paths`, a list of directories separated like the PATH variable
+    pub fn open(paths: &str) -> io::Result<Self> {
+        let mut tables: HashMap<String, TableFiles> = HashMap::new();
+        for dir in std::env::split_paths(paths) {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let (Some(name), Some(extension)) = (
+                    path.file_stem().and_then(OsStr::to_str),
+                    path.extension().and_then(OsStr::to_str),
+                ) else {
+                    continue;
+                };
+                if parse_name(name).is_none() {
+                    continue;
+                }
+                let files = tables.entry(name.to_string()).or_default();
+                match extension {
+                    "rtbw" => files.wdl = Some(path.clone()),
+                    "rtbz" => files.dtz = Some(path.clone()),
+                    _ => {}
+                }
+            }
+        }
+        tables.retain(|_, files| files.wdl.is_some() || files.dtz.is_some());
+        let max_pieces = tables.keys().map(|name| name.len() - 1).max().unwrap_or(0);
+        Ok(Self { tables, max_pieces })
+    }
+
+    /// the number of tables found, counting a WDL and a DTZ file of the same ending once
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// positions with more pieces than this can't be probed
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// whether the position has few enough pieces and no castling rights
+    pub fn can_probe(&self, board: &Board) -> bool {
+        let rights = board.castling_rights();
+        board.pieces().count() <= self.max_pieces
+            && !(rights.white_king_side
+                || rights.white_queen_side
+                || rights.black_king_side
+                || rights.black_queen_side)
+    }
+
+    /// the outcome for `turn` with perfect play, not counting the moves already played
+    /// towards the fifty-move rule; None when the tables needed aren't there
+    pub fn probe_wdl(&self, board: &Board, turn: Turn) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        self.search(board, turn, false).map(|(wdl, _)| wdl)
+    }
+
+    /// the plies to the next capture or pawn move with perfect play; positive when `turn`
+    /// wins, negative when it loses, 0 for a draw. a win or a loss that comes after more than
+    /// 100 plies is a cursed win or a blessed loss
+    pub fn probe_dtz(&self, board: &Board, turn: Turn) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        self.dtz(board, turn)
+    }
+
+    /// the moves that keep the best outcome the fifty-move rule allows, given the plies
+    /// played since the last capture or pawn move
+    ///
+    /// when the position is won only the moves that get closest to the next capture or
+    /// pawn move are kept, so following them always converts the win in time
+    pub fn root_moves(&self, board: &Board, turn: Turn, halfmove_clock: u32) -> Option<Vec<Move>> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        let clock = halfmove_clock as i32;
+        let mut ranked = vec![];
+        for (fr, to) in extract_legal_moves(board, turn) {
+            let mv = TbMove::new(board, turn, fr, to, PieceKind::Queen);
+            let (after, next) = mv.play(board, turn);
+            let mut dtz = if mv.zeroing() {
+                dtz_before_zeroing(-self.search(&after, next, false)?.0)
+            } else {
+                let dtz = -self.dtz(&after, next)?;
+                dtz + dtz.signum()
+            };
+            // a mate is as close to zeroing as it gets
+            if dtz == 2 && is_mate(&after, next) {
+                dtz = 1;
+            }
+            let rank = match dtz {
+                // a win that comes in time; they are all the same to the rule
+                dtz if dtz > 0 && dtz + clock <= 99 => MAX_DTZ,
+                dtz if dtz > 0 => MAX_DTZ - (dtz + clock),
+                // a loss the rule can't save
+                dtz if dtz < 0 && -dtz * 2 + clock < 100 => -MAX_DTZ,
+                dtz if dtz < 0 => -MAX_DTZ + (-dtz + clock),
+                _ => 0,
+            };
+            ranked.push(((fr, to), rank, dtz));
+        }
+        let best_rank = ranked.iter().map(|(_, rank, _)| *rank).max()?;
+        let mut best: Vec<(Move, i32)> = ranked
+            .into_iter()
+            .filter(|(_, rank, _)| *rank == best_rank)
+            .map(|(mv, _, dtz)| (mv, dtz))
+            .collect();
+        if best_rank == MAX_DTZ {
+            let quickest = best.iter().map(|(_, dtz)| *dtz).min()?;
+            best.retain(|(_, dtz)| *dtz == quickest);
+        }
+        Some(best.into_iter().map(|(mv, _)| mv).collect())
+    }
+
+    /// the outcome, and whether the best move is a capture or a pawn move, which the tables
+    /// can't be trusted with; captures are searched since the tables leave them out
+    fn search(&self, board: &Board, turn: Turn, check_zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = tb_moves(board, turn);
+        let mut searched = 0;
+        let mut best = Wdl::Loss;
+        for mv in &moves {
+            if !mv.capture && (!check_zeroing || !mv.pawn) {
+                continue;
+            }
+            searched += 1;
+            let (after, next) = mv.play(board, turn);
+            let value = -self.search(&after, next, false)?.0;
+            if value > best {
+                best = value;
+                if value >= Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // with every move searched the table isn't needed; it might be wrong anyway,
+        // eg. the tables don't know about en passant
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            self.probe_table_wdl(board, turn)?
+        };
+        if best >= value {
+            Some((best, best > Wdl::Draw || no_more_moves))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, board: &Board, turn: Turn) -> Option<i32> {
+        let (wdl, zeroing_best_move) = self.search(board, turn, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best_move {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        if let Some(dtz) = self.probe_table_dtz(board, turn, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Some((dtz + 100 * cursed as i32) * wdl.signum());
+        }
+
+        // the table only has the other side to move; a ply deeper it has the answer
+        let mut min_dtz = i32::MAX;
+        for mv in tb_moves(board, turn) {
+            let (after, next) = mv.play(board, turn);
+            let mut dtz = if mv.zeroing() {
+                -dtz_before_zeroing(self.search(&after, next, false)?.0)
+            } else {
+                -self.dtz(&after, next)?
+            };
+            if dtz == 1 && is_mate(&after, next) {
+                min_dtz = 1;
+            }
+            if !mv.zeroing() {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+        // no legal moves; checkmated
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    fn probe_table_wdl(&self, board: &Board, turn: Turn) -> Option<Wdl> {
+        let material = Material::of(board);
+        if material.piece_count() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let (table, black_stronger) = self.table(&material, TableKind::Wdl)?;
+        let value = table.probe(board, turn, black_stronger, Wdl::Draw)?;
+        Wdl::from_value(value? - 2)
+    }
+
+    /// None inside when the table has the other side to move
+    fn probe_table_dtz(&self, board: &Board, turn: Turn, wdl: Wdl) -> Option<Option<i32>> {
+        let material = Material::of(board);
+        let (table, black_stronger) = self.table(&material, TableKind::Dtz)?;
+        table.probe(board, turn, black_stronger, wdl)
+    }
+
+    /// the table of the material, and whether it has the colors the other way around
+    fn table(&self, material: &Material, kind: TableKind) -> Option<(&Table, bool)> {
+        let white_first = material.name(PieceColor::White);
+        let black_first = material.name(PieceColor::Black);
+        let (name, black_stronger) = if self.tables.contains_key(&white_first) {
+            (white_first, false)
+        } else {
+            (black_first, true)
+        };
+        let files = self.tables.get(&name)?;
+        let (path, slot) = match kind {
+            TableKind::Wdl => (files.wdl.as_ref()?, &files.wdl_table),
+            TableKind::Dtz => (files.dtz.as_ref()?, &files.dtz_table),
+        };
+        let table = slot.get_or_init(|| Table::read(path, &name, kind));
+        Some((table.as_ref()?, black_stronger))
+    }
+}
+
+/// a move along with what the probing needs to know about it
+struct TbMove {
+    fr: Position,
+    to: Position,
+    promotion: PieceKind,
+    capture: bool,
+    pawn: bool,
+}
+
+impl TbMove {
+    fn new(board: &Board, turn: Turn, fr: Position, to: Position, promotion: PieceKind) -> Self {
+        let pawn =
+            matches!(board.look_up_cell(fr), Some(Some(piece)) if piece.kind == PieceKind::Pawn);
+        let capture = matches!(board.look_up_cell(to), Some(Some(piece)) if piece.color != turn)
+            // en passant
+            || (pawn && fr.j() != to.j());
+        Self {
+            fr,
+            to,
+            promotion,
+            capture,
+            pawn,
+        }
+    }
+
+    /// a capture or a pawn move; it resets the fifty-move counter
+    fn zeroing(&self) -> bool {
+        self.capture || self.pawn
+    }
+
+    fn play(&self, board: &Board, turn: Turn) -> (Board, Turn) {
+        let (mut board, mut turn) = (board.clone(), turn);
+        board.move_piece_and_promote_unchecked(&mut turn, self.fr, self.to, self.promotion);
+        (board, turn)
+    }
+}
+
+/// every legal move, underpromotions included
+fn tb_moves(board: &Board, turn: Turn) -> Vec<TbMove> {
+    let mut moves = vec![];
+    for (fr, to) in extract_legal_moves(board, turn) {
+        if board.is_promotion(fr, to) {
+            for promotion in [
+                PieceKind::Queen,
+                PieceKind::Rook,
+                PieceKind::Bishop,
+                PieceKind::Knight,
+            ] {
+                moves.push(TbMove::new(board, turn, fr, to, promotion));
+            }
+        } else {
+            moves.push(TbMove::new(board, turn, fr, to, PieceKind::Queen));
+        }
+    }
+    moves
+}
+
+fn is_mate(board: &Board, turn: Turn) -> bool {
+    !board.is_king_safe(turn) && extract_legal_moves(board, turn).is_empty()
+}
+
+/// the DTZ of the move before a capture or a pawn move that reaches `wdl`
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+/// how many pieces of each kind each side has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Material {
+    /// [color][kind]; kinds in the order of PieceKind
+    counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    fn of(board: &Board) -> Self {
+        let mut counts = [[0; 6]; 2];
+        for (_, piece) in board.pieces() {
+            counts[color_index(piece.color)][piece.kind as usize] += 1;
+        }
+        Self { counts }
+    }
+
+    fn piece_count(&self) -> usize {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|count| *count as usize)
+            .sum()
+    }
+
+    /// the name of the table with `first` as the side before the "v"; eg. "KRvK"
+    fn name(&self, first: PieceColor) -> String {
+        let side = |color: PieceColor| {
+            let mut name = String::new();
+            for (kind, letter) in [(5, 'K'), (4, 'Q'), (3, 'R'), (2, 'B'), (1, 'N'), (0, 'P')] {
+                for _ in 0..self.counts[color_index(color)][kind] {
+                    name.push(letter);
+                }
+            }
+            name
+        };
+        let second = match first {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        };
+        format!("{}v{}", side(first), side(second))
+    }
+}
+
+fn color_index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+/// the material of a table's name, with the side before the "v" as white
+fn parse_name(name: &str) -> Option<Material> {
+    let (white, black) = name.split_once('v')?;
+    let mut counts = [[0; 6]; 2];
+    for (color, side) in [white, black].into_iter().enumerate() {
+        if !side.starts_with('K') {
+            return None;
+        }
+        for letter in side.chars() {
+            let kind = match letter {
+                'K' => 5,
+                'Q' => 4,
+                'R' => 3,
+                'B' => 2,
+                'N' => 1,
+                'P' => 0,
+                _ => return None,
+            };
+            counts[color][kind] += 1;
+        }
+    }
+    let material = Material { counts };
+    if counts[0][5] != 1 || counts[1][5] != 1 || material.piece_count() > MAX_PIECES {
+        return None;
+    }
+    Some(material)
+}
+
+/// the code a table uses for a piece: 1 to 6 for a white pawn to a white king, 9 to 14 for
+/// the black ones
+fn piece_code(kind: PieceKind, color: PieceColor) -> u8 {
+    kind as u8 + 1 + 8 * color_index(color) as u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+// the flags of a table's pairs data
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// a table file; WDL tables have a part for each side to move unless both sides have the
+/// same pieces, and tables with pawns have a part for each file of the leading pawn
+struct Table {
+    data: Vec<u8>,
+    kind: TableKind,
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    piece_count: usize,
+    /// pawns of the leading color and of the other one
+    pawn_count: [usize; 2],
+    /// [side][file]
+    parts: Vec<Vec<PairsData>>,
+    /// where the DTZ value maps start
+    dtz_map: usize,
+}
+
+/// a compressed part of a table and how positions are numbered in it
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    /// the pieces in the order they are encoded
+    pieces: [u8; MAX_PIECES],
+    /// the sizes of the groups of pieces that are encoded together; ends with a 0
+    group_len: [usize; MAX_PIECES + 1],
+    /// what the index of each group is multiplied by; the last one is the size of the part
+    group_idx: [u64; MAX_PIECES + 1],
+    sizeof_block: u64,
+    span: u64,
+    num_blocks: u64,
+    block_length_size: u64,
+    sparse_index_size: u64,
+    min_sym_len: u8,
+    /// offsets into the file
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    blocks: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    map_idx: [usize; 4],
+}
+
+impl Table {
+    fn read(path: &Path, name: &str, kind: TableKind) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.get(..4)? != magic {
+            return None;
+        }
+        let material = parse_name(name)?;
+        Table::new(data, kind, material)
+    }
+
+    fn new(data: Vec<u8>, kind: TableKind, material: Material) -> Option<Self> {
+        let [white, black] = material.counts;
+        let (white_pawns, black_pawns) = (white[0] as usize, black[0] as usize);
+        // the side with fewer pawns leads, since that compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let mut table = Self {
+            data,
+            kind,
+            symmetric: white == black,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: material.counts.iter().any(|side| side[..5].contains(&1)),
+            piece_count: material.piece_count(),
+            pawn_count: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            parts: vec![],
+            dtz_map: 0,
+        };
+        table.parse()?;
+        Some(table)
+    }
+
+    fn sides(&self) -> usize {
+        if self.kind == TableKind::Wdl && !self.symmetric {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn files(&self) -> usize {
+        if self.has_pawns {
+            4
+        } else {
+            1
+        }
+    }
+
+    fn parse(&mut self) -> Option<()> {
+        let data = std::mem::take(&mut self.data);
+        let result = self.parse_data(&data);
+        self.data = data;
+        result
+    }
+
+    fn parse_data(&mut self, data: &[u8]) -> Option<()> {
+        let (sides, files) = (self.sides(), self.files());
+        let mut p = 4;
+        let flags = *data.get(p)?;
+        p += 1;
+        if (flags & 2 != 0) != self.has_pawns || (flags & 1 != 0) == self.symmetric {
+            return None;
+        }
+        let pawns_on_both_sides = self.has_pawns && self.pawn_count[1] > 0;
+        self.parts = vec![vec![PairsData::default(); files]; sides];
+
+        for f in 0..files {
+            let byte = *data.get(p)?;
+            let next = if pawns_on_both_sides {
+                *data.get(p + 1)?
+            } else {
+                0xFF
+            };
+            let order = [
+                [(byte & 0xF) as usize, (next & 0xF) as usize],
+                [(byte >> 4) as usize, (next >> 4) as usize],
+            ];
+            p += 1 + pawns_on_both_sides as usize;
+            for k in 0..self.piece_count {
+                let byte = *data.get(p)?;
+                for (side, parts) in self.parts.iter_mut().enumerate() {
+                    parts[f].pieces[k] = if side == 0 { byte & 0xF } else { byte >> 4 };
+                }
+                p += 1;
+            }
+            for (side, order) in order.into_iter().enumerate().take(sides) {
+                self.set_groups(side, f, order);
+            }
+        }
+        p += p & 1;
+
+        for f in 0..files {
+            for side in 0..sides {
+                p = self.parts[side][f].set_sizes(data, p)?;
+            }
+        }
+        if self.kind == TableKind::Dtz {
+            p = self.set_dtz_map(data, p)?;
+        }
+        for f in 0..files {
+            for side in 0..sides {
+                let part = &mut self.parts[side][f];
+                part.sparse_index = p;
+                p += part.sparse_index_size as usize * 6;
+            }
+        }
+        for f in 0..files {
+            for side in 0..sides {
+                let part = &mut self.parts[side][f];
+                part.block_length = p;
+                p += part.block_length_size as usize * 2;
+            }
+        }
+        for f in 0..files {
+            for side in 0..sides {
+                let part = &mut self.parts[side][f];
+                p = (p + 0x3F) & !0x3F;
+                part.blocks = p;
+                p += (part.num_blocks * part.sizeof_block) as usize;
+            }
+        }
+        (p <= data.len()).then_some(())
+    }
+
+    /// splits the pieces into groups and works out the size of each group's index
+    fn set_groups(&mut self, side: usize, f: usize, order: [usize; 2]) {
+        let indices = indices();
+        let (has_pawns, has_unique_pieces) = (self.has_pawns, self.has_unique_pieces);
+        let pawns_on_both_sides = has_pawns && self.pawn_count[1] > 0;
+        let piece_count = self.piece_count;
+        let part = &mut self.parts[side][f];
+
+        // the leading group is the kings and a unique piece, the two kings, or the
+        // leading pawns; identical pieces form the other groups
+        let mut first_len: i32 = if has_pawns {
+            0
+        } else if has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut n = 0;
+        part.group_len[0] = 1;
+        for i in 1..piece_count {
+            first_len -= 1;
+            if first_len > 0 || part.pieces[i] == part.pieces[i - 1] {
+                part.group_len[n] += 1;
+            } else {
+                n += 1;
+                part.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        part.group_len[n] = 0;
+
+        // the order the groups are encoded in is part of the table
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares = 64
+            - part.group_len[0]
+            - if pawns_on_both_sides {
+                part.group_len[1]
+            } else {
+                0
+            };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                part.group_idx[0] = idx;
+                idx *= if has_pawns {
+                    indices.lead_pawns_size[part.group_len[0]][f]
+                } else if has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                part.group_idx[1] = idx;
+                idx *= indices.binomial[part.group_len[1]][48 - part.group_len[0]];
+            } else {
+                part.group_idx[next] = idx;
+                idx *= indices.binomial[part.group_len[next]][free_squares];
+                free_squares -= part.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        part.group_idx[n] = idx;
+    }
+
+    /// DTZ tables may store their values through a map for each outcome
+    fn set_dtz_map(&mut self, data: &[u8], mut p: usize) -> Option<usize> {
+        self.dtz_map = p;
+        for f in 0..self.files() {
+            let part = &mut self.parts[0][f];
+            if part.flags & FLAG_MAPPED == 0 {
+                continue;
+            }
+            if part.flags & FLAG_WIDE != 0 {
+                p += p & 1;
+                for i in 0..4 {
+                    part.map_idx[i] = (p - self.dtz_map) / 2 + 1;
+                    p += 2 * read_u16_le(data, p)? as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    part.map_idx[i] = p - self.dtz_map + 1;
+                    p += *data.get(p)? as usize + 1;
+                }
+            }
+        }
+        Some(p + (p & 1))
+    }
+
+    /// the stored value of the position; None inside when a DTZ table has the other side
+    /// to move
+    fn probe(
+        &self,
+        board: &Board,
+        turn: Turn,
+        black_stronger: bool,
+        wdl: Wdl,
+    ) -> Option<Option<i32>> {
+        let indices = indices();
+        // the tables have the stronger side as white, and only white to move when both
+        // sides have the same pieces; otherwise the colors and the board are flipped
+        let symmetric_black_to_move = self.symmetric && turn == PieceColor::Black;
+        let flip = symmetric_black_to_move || black_stronger;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip as usize) ^ color_index(turn);
+
+        let mut pieces: Vec<(u8, u8)> = board
+            .pieces()
+            .map(|(pos, piece)| {
+                let square = (8 * pos.i() + pos.j()) as u8;
+                (square, piece_code(piece.kind, piece.color))
+            })
+            .collect();
+        pieces.sort_by_key(|(square, _)| *square);
+        let mut squares: Vec<u8> = vec![];
+        let mut codes: Vec<u8> = vec![];
+        let mut lead_pawns_count = 0;
+        let mut file = 0;
+
+        if self.has_pawns {
+            // the leading pawns come first; they are the ones of the color of the first piece
+            let lead_code = self.parts[0][0].pieces[0] ^ flip_color;
+            for (square, code) in &pieces {
+                if *code == lead_code {
+                    squares.push(square ^ flip_squares);
+                    codes.push(*code ^ flip_color);
+                }
+            }
+            lead_pawns_count = squares.len();
+            // the pawn nearest the edge, and the lowest of those, leads
+            let lead = (0..lead_pawns_count).max_by_key(|i| {
+                (
+                    indices.map_pawns[squares[*i] as usize],
+                    std::cmp::Reverse(*i),
+                )
+            })?;
+            squares.swap(0, lead);
+            file = edge_distance(squares[0] % 8);
+        }
+
+        let part_flags = self.part(0, file).flags;
+        if self.kind == TableKind::Dtz
+            && (part_flags & FLAG_STM) as usize != stm
+            && (!self.symmetric || self.has_pawns)
+        {
+            return Some(None);
+        }
+
+        let lead_code = self
+            .has_pawns
+            .then(|| self.parts[0][0].pieces[0] ^ flip_color);
+        for (square, code) in &pieces {
+            if Some(*code) != lead_code {
+                squares.push(square ^ flip_squares);
+                codes.push(code ^ flip_color);
+            }
+        }
+        let size = squares.len();
+        let part = self.part(stm, file);
+
+        // the pieces go in the order the table encodes them
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if part.pieces[i] == codes[j] {
+                    codes.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // the leading piece goes on the a1-d1-d4 triangle, or the a to d files for pawns
+        if squares[0] % 8 > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx: u64;
+        if self.has_pawns {
+            idx = indices.lead_pawn_idx[lead_pawns_count][squares[0] as usize];
+            squares[1..lead_pawns_count].sort_by_key(|square| indices.map_pawns[*square as usize]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                idx += indices.binomial[i][indices.map_pawns[*square as usize] as usize];
+            }
+        } else {
+            if squares[0] / 8 > 3 {
+                for square in squares.iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            // the first leading piece off the a1-h8 diagonal goes below it
+            for i in 0..part.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+                let adjust1 = (s1 > s0) as u64;
+                let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+                let rank = |square: u8| (square / 8) as u64;
+                idx = if off_diagonal(s0) != 0 {
+                    (indices.map_a1d1d4[s0 as usize] * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64
+                        - adjust2
+                } else if off_diagonal(s1) != 0 {
+                    (6 * 63 + rank(s0) * 28 + indices.map_b1h1h7[s1 as usize]) * 62 + s2 as u64
+                        - adjust2
+                } else if off_diagonal(s2) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank(s0) * 7 * 28
+                        + (rank(s1) - adjust1) * 28
+                        + indices.map_b1h1h7[s2 as usize]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank(s0) * 7 * 6
+                        + (rank(s1) - adjust1) * 6
+                        + (rank(s2) - adjust2)
+                };
+            } else {
+                idx = indices.map_kk[indices.map_a1d1d4[squares[0] as usize] as usize]
+                    [squares[1] as usize];
+            }
+        }
+
+        // the other groups, each as a combination of the squares the earlier groups left
+        idx *= part.group_idx[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut start = part.group_len[0];
+        let mut next = 1;
+        while part.group_len[next] != 0 {
+            let len = part.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n: u64 = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|s| square > **s).count();
+                let free = square as usize - adjust - if remaining_pawns { 8 } else { 0 };
+                n += indices.binomial[i + 1][free];
+            }
+            remaining_pawns = false;
+            idx += n * part.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        let value = part.decompress(&self.data, idx)?;
+        Some(Some(match self.kind {
+            TableKind::Wdl => value,
+            TableKind::Dtz => self.map_dtz(part, value, wdl)?,
+        }))
+    }
+
+    fn part(&self, stm: usize, file: usize) -> &PairsData {
+        &self.parts[stm % self.parts.len()][if self.has_pawns { file } else { 0 }]
+    }
+
+    /// turns a stored DTZ value into plies
+    fn map_dtz(&self, part: &PairsData, value: i32, wdl: Wdl) -> Option<i32> {
+        let mut value = value;
+        if part.flags & FLAG_MAPPED != 0 {
+            let map = match wdl {
+                Wdl::Win | Wdl::Draw => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3,
+            };
+            let idx = part.map_idx[map] + value as usize;
+            value = if part.flags & FLAG_WIDE != 0 {
+                read_u16_le(&self.data, self.dtz_map + 2 * idx)? as i32
+            } else {
+                *self.data.get(self.dtz_map + idx)? as i32
+            };
+        }
+        // some tables count moves rather than plies
+        if (wdl == Wdl::Win && part.flags & FLAG_WIN_PLIES == 0)
+            || (wdl == Wdl::Loss && part.flags & FLAG_LOSS_PLIES == 0)
+            || wdl == Wdl::CursedWin
+            || wdl == Wdl::BlessedLoss
+        {
+            value *= 2;
+        }
+        Some(value + 1)
+    }
+}
+
+impl PairsData {
+    /// reads the sizes of the compressed data and the Huffman code of its symbols
+    fn set_sizes(&mut self, data: &[u8], mut p: usize) -> Option<usize> {
+        self.flags = *data.get(p)?;
+        p += 1;
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            // every position has the same value, which is kept here
+            self.min_sym_len = *data.get(p)?;
+            return Some(p + 1);
+        }
+
+        let groups = self.group_len.iter().position(|len| *len == 0)?;
+        let tb_size = self.group_idx[groups];
+        self.sizeof_block = 1 << *data.get(p)?;
+        self.span = 1 << *data.get(p + 1)?;
+        self.sparse_index_size = tb_size.div_ceil(self.span);
+        let padding = *data.get(p + 2)? as u64;
+        self.num_blocks = read_u32_le(data, p + 3)? as u64;
+        // padded so the sparse index can't point past the end
+        self.block_length_size = self.num_blocks + padding;
+        let max_sym_len = *data.get(p + 7)?;
+        self.min_sym_len = *data.get(p + 8)?;
+        p += 9;
+        self.lowest_sym = p;
+
+        // a canonical Huffman code: longer codes have lower values, so base64[i] is the
+        // lowest 64-bit left-aligned code of length min_sym_len + i
+        let lengths = (max_sym_len.checked_sub(self.min_sym_len)? + 1) as usize;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = read_u16_le(data, self.lowest_sym + 2 * i)? as u64;
+            let next_lowest = read_u16_le(data, self.lowest_sym + 2 * (i + 1))? as u64;
+            self.base64[i] = (self.base64[i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base = base
+                .checked_shl(64 - i as u32 - self.min_sym_len as u32)
+                .unwrap_or(0);
+        }
+        p += lengths * 2;
+
+        // the symbols stand for pairs of other symbols, down to the stored values
+        let symbols = read_u16_le(data, p)? as usize;
+        p += 2;
+        self.btree = p;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.symlen[sym] = self.set_symlen(data, sym, &mut visited)?;
+            }
+        }
+        Some(p + symbols * 3 + (symbols & 1))
+    }
+
+    /// the number of values a symbol stands for, minus one
+    fn set_symlen(&mut self, data: &[u8], sym: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[sym] = true;
+        let (left, right) = self.pair(data, sym)?;
+        if right == 0xFFF {
+            return Some(0);
+        }
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                self.symlen[child] = self.set_symlen(data, child, visited)?;
+            }
+        }
+        Some(
+            self.symlen[left]
+                .wrapping_add(self.symlen[right])
+                .wrapping_add(1),
+        )
+    }
+
+    /// the two symbols a symbol stands for; a leaf has the stored value on the left and
+    /// 0xFFF on the right
+    fn pair(&self, data: &[u8], sym: usize) -> Option<(usize, usize)> {
+        let lr = data.get(self.btree + 3 * sym..self.btree + 3 * sym + 3)?;
+        let left = ((lr[1] as usize & 0xF) << 8) | lr[0] as usize;
+        let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+        Some((left, right))
+    }
+
+    /// the value at `idx`
+    fn decompress(&self, data: &[u8], idx: u64) -> Option<i32> {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(self.min_sym_len as i32);
+        }
+
+        // the sparse index points somewhere near the value; the block lengths say the rest
+        let k = (idx / self.span) as usize;
+        let entry = self.sparse_index + 6 * k;
+        let mut block = read_u32_le(data, entry)? as usize;
+        let mut offset = read_u16_le(data, entry + 4)? as i64;
+        offset += (idx % self.span) as i64 - (self.span / 2) as i64;
+        let block_length =
+            |block: usize| read_u16_le(data, self.block_length + 2 * block).map(|len| len as i64);
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        // find the symbol that holds the value
+        let mut p = self.blocks + block * self.sizeof_block as usize;
+        let mut buf64 = read_u64_be(data, p)?;
+        p += 8;
+        let mut buf64_size = 64;
+        let min_sym_len = self.min_sym_len as usize;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf64 < *self.base64.get(len)? {
+                len += 1;
+            }
+            sym = ((buf64 - self.base64[len]) >> (64 - len - min_sym_len)) as usize;
+            sym += read_u16_le(data, self.lowest_sym + 2 * len)? as usize;
+            let sym_len = *self.symlen.get(sym)? as i64;
+            if offset < sym_len + 1 {
+                break;
+            }
+            offset -= sym_len + 1;
+            len += min_sym_len;
+            buf64 <<= len;
+            buf64_size -= len;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (read_u32_be(data, p)? as u64) << (64 - buf64_size);
+                p += 4;
+            }
+        }
+
+        // and then the value within the symbol
+        while self.symlen[sym] != 0 {
+            let (left, right) = self.pair(data, sym)?;
+            let left_len = *self.symlen.get(left)? as i64;
+            if offset < left_len + 1 {
+                sym = left;
+            } else {
+                offset -= left_len + 1;
+                sym = right;
+            }
+        }
+        Some(self.pair(data, sym)?.0 as i32)
+    }
+}
+
+fn read_u16_le(data: &[u8], p: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(p..p + 2)?.try_into().ok()?))
+}
+
+fn read_u32_le(data: &[u8], p: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(p..p + 4)?.try_into().ok()?))
+}
+
+fn read_u32_be(data: &[u8], p: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(p..p + 4)?.try_into().ok()?))
+}
+
+fn read_u64_be(data: &[u8], p: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(p..p + 8)?.try_into().ok()?))
+}
+
+/// how far a square is above the a1-h8 diagonal; negative below it
+fn off_diagonal(square: u8) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+/// 0 for the a and h files up to 3 for the d and e files
+fn edge_distance(file: u8) -> usize {
+    file.min(7 - file) as usize
+}
+
+/// the numbering of piece placements shared by every table
+struct Indices {
+    /// the squares below the a1-h8 diagonal, 0 to 27
+    map_b1h1h7: [u64; 64],
+    /// the a1-d1-d4 triangle, 0 to 9 with the diagonal last
+    map_a1d1d4: [u64; 64],
+    /// the 462 placements of two kings with the first on the a1-d1-d4 triangle
+    map_kk: [[u64; 64]; 10],
+    /// [k][n]
+    binomial: [[u64; 64]; MAX_PIECES],
+    /// a2-h7 to 0..47; the leading pawn is the one with the highest value
+    map_pawns: [u64; 64],
+    /// [leading pawns][square of the leading one]
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    /// [leading pawns][file]
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+fn indices() -> &'static Indices {
+    static INDICES: OnceLock<Indices> = OnceLock::new();
+    INDICES.get_or_init(Indices::new)
+}
+
+impl Indices {
+    fn new() -> Self {
+        let mut indices = Indices {
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        let mut code = 0;
+        for square in 0..64u8 {
+            if off_diagonal(square) < 0 {
+                indices.map_b1h1h7[square as usize] = code;
+                code += 1;
+            }
+        }
+
+        let mut code = 0;
+        let mut diagonal = vec![];
+        for rank in 0..4u8 {
+            for file in 0..4u8 {
+                let square = 8 * rank + file;
+                if off_diagonal(square) < 0 {
+                    indices.map_a1d1d4[square as usize] = code;
+                    code += 1;
+                } else if off_diagonal(square) == 0 {
+                    diagonal.push(square);
+                }
+            }
+        }
+        for square in diagonal {
+            indices.map_a1d1d4[square as usize] = code;
+            code += 1;
+        }
+
+        let mut code = 0;
+        let mut both_on_diagonal = vec![];
+        for idx in 0..10 {
+            for s1 in 0..28u8 {
+                // b1 is the only square of the triangle numbered 0
+                if indices.map_a1d1d4[s1 as usize] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                if s1 % 8 > 3 || s1 / 8 > 3 || off_diagonal(s1) > 0 {
+                    continue;
+                }
+                for s2 in 0..64u8 {
+                    let touching = (s1 / 8).abs_diff(s2 / 8) <= 1 && (s1 % 8).abs_diff(s2 % 8) <= 1;
+                    if touching || (off_diagonal(s1) == 0 && off_diagonal(s2) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        indices.map_kk[idx as usize][s2 as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            indices.map_kk[idx as usize][s2 as usize] = code;
+            code += 1;
+        }
+
+        indices.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                indices.binomial[k][n] = if k > 0 {
+                    indices.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { indices.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut available_squares = 47;
+        for lead_pawns in 1..=5 {
+            for file in 0..4u8 {
+                let mut idx = 0;
+                for rank in 1..7u8 {
+                    let square = (8 * rank + file) as usize;
+                    if lead_pawns == 1 {
+                        indices.map_pawns[square] = available_squares;
+                        available_squares = available_squares.wrapping_sub(1);
+                        indices.map_pawns[square ^ 7] = available_squares;
+                        available_squares = available_squares.wrapping_sub(1);
+                    }
+                    indices.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += indices.binomial[lead_pawns - 1][indices.map_pawns[square] as usize];
+                }
+                indices.lead_pawns_size[lead_pawns][file as usize] = idx;
+            }
+        }
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::PositionNotation;
+
+    /// generated with tests/fixtures/syzygy/generate.py
+    fn tablebase() -> Tablebase {
+        Tablebase::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/syzygy"
+        ))
+        .unwrap()
+    }
+
+    fn probe(tablebase: &Tablebase, fen: &str) -> (Wdl, i32) {
+        let (board, turn) = Board::parse_fen(fen).unwrap();
+        (
+            tablebase.probe_wdl(&board, turn).unwrap(),
+            tablebase.probe_dtz(&board, turn).unwrap(),
+        )
+    }
+
+    fn mv(fr: &str, to: &str) -> Move {
+        (
+            Position::from_notation(fr).unwrap(),
+            Position::from_notation(to).unwrap(),
+        )
+    }
+
+    #[test]
+    fn finds_the_fixture_tables() {
+        let tablebase = tablebase();
+        assert_eq!(tablebase.len(), 5);
+        assert_eq!(tablebase.max_pieces(), 3);
+    }
+
+    #[test]
+    fn probes_known_rook_endings() {
+        let tablebase = tablebase();
+        // mate in one
+        assert_eq!(
+            probe(&tablebase, "k7/8/K7/8/8/8/8/7R w - - 0 1"),
+            (Wdl::Win, 1)
+        );
+        // mated
+        assert_eq!(
+            probe(&tablebase, "k6R/8/K7/8/8/8/8/8 b - - 0 1"),
+            (Wdl::Loss, -1)
+        );
+        // stalemate
+        assert_eq!(
+            probe(&tablebase, "k1K5/7R/8/8/8/8/8/8 b - - 0 1"),
+            (Wdl::Draw, 0)
+        );
+        // the rook is lost
+        assert_eq!(
+            probe(&tablebase, "8/8/8/8/8/8/8/kR2K3 b - - 0 1"),
+            (Wdl::Draw, 0)
+        );
+        let (wdl, dtz) = probe(&tablebase, "8/8/8/3k4/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(wdl, Wdl::Win);
+        assert!(dtz > 1 && dtz <= 100);
+    }
+
+    #[test]
+    fn probes_the_colors_the_other_way_around() {
+        let tablebase = tablebase();
+        assert_eq!(
+            probe(&tablebase, "K7/8/k7/8/8/8/8/7r b - - 0 1"),
+            (Wdl::Win, 1)
+        );
+        assert_eq!(
+            probe(&tablebase, "K6r/8/k7/8/8/8/8/8 w - - 0 1"),
+            (Wdl::Loss, -1)
+        );
+        assert_eq!(
+            probe(&tablebase, "8/8/8/3k4/8/8/8/R3K3 w - - 0 1"),
+            probe(&tablebase, "r3k3/8/8/8/3K4/8/8/8 b - - 0 1"),
+        );
+        assert_eq!(
+            probe(&tablebase, "8/8/8/3K4/8/8/8/q3k3 w - - 0 1"),
+            probe(&tablebase, "Q3K3/8/8/8/3k4/8/8/8 b - - 0 1"),
+        );
+    }
+
+    #[test]
+    fn mirrored_positions_probe_the_same() {
+        let tablebase = tablebase();
+        assert_eq!(
+            probe(&tablebase, "7k/8/7K/8/8/8/8/R7 w - - 0 1"),
+            (Wdl::Win, 1)
+        );
+        assert_eq!(
+            probe(&tablebase, "7R/8/8/8/8/K7/8/k7 w - - 0 1"),
+            (Wdl::Win, 1)
+        );
+        for fen in [
+            "8/8/8/3k4/8/8/8/R3K3 w - - 0 1",
+            "8/2k5/8/8/5Q2/8/1K6/8 b - - 0 1",
+            "8/8/1k6/8/8/8/5KR1/8 b - - 0 1",
+        ] {
+            let (placement, rest) = fen.split_once(' ').unwrap();
+            let expected = probe(&tablebase, fen);
+            let ranks: Vec<String> = placement.split('/').map(String::from).collect();
+            let flip_files: Vec<String> = ranks
+                .iter()
+                .map(|rank| rank.chars().rev().collect())
+                .collect();
+            let mut flip_ranks = ranks.clone();
+            flip_ranks.reverse();
+            let mut flip_both = flip_files.clone();
+            flip_both.reverse();
+            for mirrored in [flip_files, flip_ranks, flip_both] {
+                let mirrored = format!("{} {rest}", mirrored.join("/"));
+                assert_eq!(probe(&tablebase, &mirrored), expected, "{mirrored}");
+            }
+        }
+    }
+
+    #[test]
+    fn probes_pawn_endings() {
+        let tablebase = tablebase();
+        // the opposition decides
+        assert_eq!(
+            probe(&tablebase, "8/4k3/8/4K3/4P3/8/8/8 w - - 0 1").0,
+            Wdl::Draw
+        );
+        assert_eq!(
+            probe(&tablebase, "8/4k3/8/4K3/4P3/8/8/8 b - - 0 1").0,
+            Wdl::Loss
+        );
+        // the rook pawn can't be won with the king in front of it
+        assert_eq!(
+            probe(&tablebase, "k7/8/K7/P7/8/8/8/8 w - - 0 1"),
+            (Wdl::Draw, 0)
+        );
+        // promoting wins at once
+        assert_eq!(
+            probe(&tablebase, "8/4P3/8/8/8/k7/8/4K3 w - - 0 1"),
+            (Wdl::Win, 1)
+        );
+        // the black pawn uses the table the other way around
+        assert_eq!(
+            probe(&tablebase, "8/8/8/4p3/4k3/8/4K3/8 b - - 0 1").0,
+            Wdl::Draw
+        );
+        assert_eq!(
+            probe(&tablebase, "8/8/8/4p3/4k3/8/4K3/8 w - - 0 1").0,
+            Wdl::Loss
+        );
+    }
+
+    #[test]
+    fn root_moves_keep_the_quickest_win() {
+        let tablebase = tablebase();
+        let (board, turn) = Board::parse_fen("k7/8/K7/8/8/8/8/7R w - - 0 1").unwrap();
+        assert_eq!(
+            tablebase.root_moves(&board, turn, 0),
+            Some(vec![mv("h1", "h8")])
+        );
+
+        let (board, turn) = Board::parse_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let dtz = tablebase.probe_dtz(&board, turn).unwrap();
+        let moves = tablebase.root_moves(&board, turn, 0).unwrap();
+        assert!(!moves.is_empty());
+        for (fr, to) in moves {
+            let mut after = board.clone();
+            let mut next = turn;
+            after.move_piece(&mut next, fr, to).unwrap();
+            assert_eq!(tablebase.probe_wdl(&after, next), Some(Wdl::Loss));
+            assert_eq!(tablebase.probe_dtz(&after, next), Some(1 - dtz));
+        }
+    }
+
+    #[test]
+    fn root_moves_keep_the_draw_when_losing_the_rook_is_forced() {
+        let tablebase = tablebase();
+        let (board, turn) = Board::parse_fen("8/8/8/8/8/8/8/kR2K3 b - - 0 1").unwrap();
+        assert_eq!(
+            tablebase.root_moves(&board, turn, 0),
+            Some(vec![mv("a1", "b1")])
+        );
+    }
+
+    /// the fixtures are written by our own generator; this compares them with the official
+    /// tables, which aren't checked in. run it with
+    /// `SYZYGY_PATH=<dir with KRvK, KQvK and KPvK> cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn the_fixtures_match_the_official_tables() {
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH names the official tables");
+        let (fixtures, official) = (tablebase(), Tablebase::open(&path).unwrap());
+        let mut compared = 0;
+        for piece in ['R', 'Q', 'P'] {
+            for (white_king, black_king, square) in (0..64)
+                .flat_map(|a| (0..64).flat_map(move |b| (0..64).map(move |c| (a, b, c))))
+            {
+                if white_king == black_king || square == white_king || square == black_king {
+                    continue;
+                }
+                if piece == 'P' && !(8..56).contains(&square) {
+                    continue;
+                }
+                let mut squares = [None; 64];
+                squares[white_king] = Some('K');
+                squares[black_king] = Some('k');
+                squares[square] = Some(piece);
+                let placement: Vec<String> = squares
+                    .chunks(8)
+                    .rev()
+                    .map(|rank| {
+                        rank.iter()
+                            .map(|piece| piece.map_or("1".to_string(), String::from))
+                            .collect()
+                    })
+                    .collect();
+                for turn in ["w", "b"] {
+                    let fen = format!("{} {turn} - - 0 1", placement.join("/"));
+                    let Ok((board, turn)) = Board::parse_fen(&fen) else {
+                        continue;
+                    };
+                    let waiting = match turn {
+                        PieceColor::White => PieceColor::Black,
+                        PieceColor::Black => PieceColor::White,
+                    };
+                    if !board.is_king_safe(waiting) {
+                        continue;
+                    }
+                    assert_eq!(probe(&fixtures, &fen), probe(&official, &fen), "{fen}");
+                    compared += 1;
+                }
+            }
+        }
+        assert!(compared > 0);
+    }
+}
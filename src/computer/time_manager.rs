@@ -0,0 +1,185 @@
+use std::time::{Duration, Instant};
+
+/// kept aside on every move for the latency between the engine and the clock
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// how many more moves the game is assumed to last when the time control doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// the hard limit is this many times the soft one
+const HARD_LIMIT_FACTOR: u32 = 4;
+/// never spend more than this share of the remaining time on a single move
+const MAX_USAGE: f64 = 0.8;
+
+/// the clock of the side to move
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    pub remaining: Duration,
+    pub increment: Duration,
+    /// moves left until the next time control; None for sudden death
+    pub moves_to_go: Option<u32>,
+}
+
+/// decides how long the engine thinks about a move when playing under a clock
+///
+/// the soft limit is checked between iterations; no new iteration starts once it's
+/// reached. the hard limit aborts the search in the middle of an iteration
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    started: Instant,
+    soft: Duration,
+    hard: Duration,
+    /// stretches the soft limit when the search is unsure about the best move
+    scale: f64,
+}
+
+impl TimeManager {
+    pub fn new(clock: Clock) -> Self {
+        let available = clock.remaining.saturating_sub(MOVE_OVERHEAD);
+        let max_usage = available.mul_f64(MAX_USAGE);
+        let moves_to_go = clock
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, DEFAULT_MOVES_TO_GO);
+
+        // the increment comes back after the move so most of it can be spent right away
+        let soft = (available / moves_to_go + clock.increment.mul_f64(0.75)).min(max_usage);
+        let hard = (soft * HARD_LIMIT_FACTOR).min(max_usage);
+
+        Self {
+            started: Instant::now(),
+            soft,
+            hard,
+            scale: 1.0,
+        }
+    }
+
+    pub fn soft_limit(&self) -> Duration {
+        self.soft
+    }
+
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// the instant the search has to be stopped no matter what
+    pub fn hard_deadline(&self) -> Instant {
+        self.started + self.hard
+    }
+
+    /// called after every completed iteration of the search
+    ///
+    /// a best move that keeps changing, or a score that suddenly drops (a fail-low),
+    /// means the position is harder than it looked so the engine thinks longer
+    pub fn on_iteration(&mut self, best_move_changed: bool, failed_low: bool) {
+        self.scale = 1.0;
+        if best_move_changed {
+            self.scale += 0.5;
+        }
+        if failed_low {
+            self.scale += 1.0;
+        }
+    }
+
+    /// whether there is enough time left to start another iteration
+    pub fn can_start_iteration(&self) -> bool {
+        self.elapsed() < self.soft.mul_f64(self.scale).min(self.hard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(remaining_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> Clock {
+        Clock {
+            remaining: Duration::from_millis(remaining_ms) + MOVE_OVERHEAD,
+            increment: Duration::from_millis(increment_ms),
+            moves_to_go,
+        }
+    }
+
+    /// as if the search had been going for `elapsed` already
+    fn after(mut time: TimeManager, elapsed: Duration) -> TimeManager {
+        time.started -= elapsed;
+        time
+    }
+
+    #[test]
+    fn the_time_is_split_over_the_moves_to_go() {
+        let time = TimeManager::new(clock(60_000, 0, None));
+        assert_eq!(time.soft_limit(), Duration::from_secs(2));
+        assert_eq!(time.hard_limit(), Duration::from_secs(8));
+
+        let time = TimeManager::new(clock(60_000, 0, Some(10)));
+        assert_eq!(time.soft_limit(), Duration::from_secs(6));
+        assert_eq!(time.hard_limit(), Duration::from_secs(24));
+
+        // most of the increment is spent on top
+        let time = TimeManager::new(clock(60_000, 1_000, None));
+        assert_eq!(time.soft_limit(), Duration::from_millis(2_750));
+        assert_eq!(time.hard_limit(), Duration::from_millis(11_000));
+    }
+
+    #[test]
+    fn the_moves_to_go_are_clamped() {
+        let sudden_death = TimeManager::new(clock(60_000, 0, None));
+        let far_away = TimeManager::new(clock(60_000, 0, Some(100)));
+        assert_eq!(far_away.soft_limit(), sudden_death.soft_limit());
+
+        // the last move before the time control; as if it were one more
+        let last = TimeManager::new(clock(60_000, 0, Some(1)));
+        let none = TimeManager::new(clock(60_000, 0, Some(0)));
+        assert_eq!(none.soft_limit(), last.soft_limit());
+        assert_eq!(last.soft_limit(), Duration::from_secs(48));
+    }
+
+    #[test]
+    fn never_spends_all_of_the_time_left() {
+        for (remaining, increment, moves_to_go) in [
+            (1_000, 0, Some(1)),
+            (1_000, 5_000, None),
+            (100, 10_000, Some(1)),
+            (0, 1_000, None),
+        ] {
+            let time = TimeManager::new(clock(remaining, increment, moves_to_go));
+            let max_usage = Duration::from_millis(remaining).mul_f64(MAX_USAGE);
+            assert!(time.soft_limit() <= max_usage);
+            assert!(time.hard_limit() <= max_usage);
+            assert!(time.soft_limit() <= time.hard_limit());
+        }
+        let time = TimeManager::new(Clock {
+            remaining: Duration::from_millis(10),
+            increment: Duration::ZERO,
+            moves_to_go: None,
+        });
+        assert_eq!(time.hard_limit(), Duration::ZERO);
+        assert!(!time.can_start_iteration());
+    }
+
+    #[test]
+    fn unsure_searches_get_more_time() {
+        let time = TimeManager::new(clock(60_000, 0, None));
+        let mut time = after(time, Duration::from_millis(2_500));
+        assert!(!time.can_start_iteration());
+
+        time.on_iteration(true, false);
+        assert!(time.can_start_iteration());
+        // the scale is decided anew after every iteration
+        time.on_iteration(false, false);
+        assert!(!time.can_start_iteration());
+
+        let mut time = after(time, Duration::from_millis(1_000));
+        time.on_iteration(false, true);
+        assert!(time.can_start_iteration());
+        let mut time = after(time, Duration::from_millis(1_000));
+        time.on_iteration(true, true);
+        assert!(time.can_start_iteration());
+        // but never past the hard limit
+        let mut time = after(time, Duration::from_millis(4_000));
+        time.on_iteration(true, true);
+        assert!(!time.can_start_iteration());
+    }
+}
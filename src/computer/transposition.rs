@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::board::position::Position;
+use crate::computer::Move;
+
+/// what the stored score says about the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// the real score is at least the stored one; the search failed high
+    Lower,
+    /// the real score is at most the stored one; the search failed low
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    pub best_move: Option<Move>,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+}
+
+/// a hash table of already searched positions that is shared between the search threads
+///
+/// there are no locks; every slot is two atomics, the key xor-ed with the data and the
+/// data itself. a slot torn by two threads writing at once fails the key check on the next
+/// probe and is treated as a miss, so a race can only lose an entry, never corrupt one
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+struct Slot {
+    checked_key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 16;
+
+    pub fn new(size_mb: usize) -> Self {
+        let len = (size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+        Self {
+            slots: (0..len)
+                .map(|_| Slot {
+                    checked_key: AtomicU64::new(0),
+                    data: AtomicU64::new(0),
+                })
+                .collect(),
+        }
+    }
+
+    /// forgets every stored position; eg. when a new game starts
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.checked_key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+        if data == 0 || slot.checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        Some(unpack(data))
+    }
+
+    /// deeper searches of the same position are kept; any other position is replaced
+    pub fn store(&self, key: u64, entry: TableEntry) {
+        let slot = self.slot(key);
+        if let Some(existing) = self.probe(key) {
+            if existing.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+        let data = pack(entry);
+        slot.checked_key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE_MB)
+    }
+}
+
+// layout of the data word from the top: score (32 bits), depth (8), bound (2), a bit that is
+// always set so a packed entry is never 0 which marks an empty slot, and at the bottom
+// whether there is a move (bit 12) followed by its two squares (6 bits each)
+
+fn pack(entry: TableEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let mv = match entry.best_move {
+        Some((fr, to)) => 1 << 12 | square(fr) << 6 | square(to),
+        None => 0,
+    };
+    (entry.score as u32 as u64) << 32 | (entry.depth as u64) << 24 | bound << 22 | 1 << 21 | mv
+}
+
+fn unpack(data: u64) -> TableEntry {
+    let best_move = if data & 1 << 12 != 0 {
+        Some((position(data >> 6), position(data)))
+    } else {
+        None
+    };
+    let bound = match data >> 22 & 0b11 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    TableEntry {
+        best_move,
+        depth: (data >> 24) as u8,
+        score: (data >> 32) as u32 as i32,
+        bound,
+    }
+}
+
+fn square(pos: Position) -> u64 {
+    (pos.i() * 8 + pos.j()) as u64
+}
+
+fn position(bits: u64) -> Position {
+    let square = (bits & 0b11_1111) as i8;
+    Position::new(square / 8, square % 8)
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::board::pieces::PieceColor;
+use crate::computer::time_manager::Clock;
+use crate::Turn;
+
+/// what a player gets back on every move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bonus {
+    None,
+    /// Fischer; added after every move
+    Increment(Duration),
+    /// the clock only starts running once this much time of the move has passed
+    SimpleDelay(Duration),
+    /// the time spent on a move is given back after it, up to this much
+    Bronstein(Duration),
+}
+
+/// a period of the game; eg. 40 moves in 90 minutes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    /// the moves to play before the next stage; None for the rest of the game
+    pub moves: Option<u32>,
+    /// added to the clock when the stage starts
+    pub time: Duration,
+}
+
+/// how much time each player gets for the game
+///
+/// the last stage repeats as long as it has a number of moves; eg. 40 moves in 2 hours,
+/// then 40 more in another 2 hours and so on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+    pub bonus: Bonus,
+}
+
+impl TimeControl {
+    pub fn new(stages: Vec<Stage>, bonus: Bonus) -> Self {
+        Self { stages, bonus }
+    }
+
+    /// the whole game in `time`
+    pub fn sudden_death(time: Duration) -> Self {
+        Self::new(vec![Stage { moves: None, time }], Bonus::None)
+    }
+
+    /// the whole game in `time` and `increment` more after every move
+    pub fn fischer(time: Duration, increment: Duration) -> Self {
+        Self::new(
+            vec![Stage { moves: None, time }],
+            Bonus::Increment(increment),
+        )
+    }
+
+    fn stage(&self, index: usize) -> Stage {
+        self.stages[index.min(self.stages.len() - 1)]
+    }
+}
+
+/// the way players write it, minutes for the stages and seconds for the bonus; eg. "5+3",
+/// "40/90, 30+30", "5 d3" for a simple delay and "5 b3" for a Bronstein delay
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let minutes = stage.time.as_secs_f64() / 60.0;
+                match stage.moves {
+                    Some(moves) => format!("{moves}/{minutes}"),
+                    None => format!("{minutes}"),
+                }
+            })
+            .collect();
+        write!(f, "{}", stages.join(", "))?;
+        match self.bonus {
+            Bonus::None => Ok(()),
+            Bonus::Increment(increment) => write!(f, "+{}", increment.as_secs()),
+            Bonus::SimpleDelay(delay) => write!(f, " d{}", delay.as_secs()),
+            Bonus::Bronstein(delay) => write!(f, " b{}", delay.as_secs()),
+        }
+    }
+}
+
+/// the clock of one player
+#[derive(Debug, Clone)]
+struct Side {
+    /// the time left, not counting the move being played
+    remaining: Duration,
+    stage: usize,
+    /// the moves played in the current stage
+    moves: u32,
+}
+
+/// the clocks of both players; only the one of the side to move runs
+#[derive(Debug, Clone)]
+pub struct ChessClock {
+    control: TimeControl,
+    white: Side,
+    black: Side,
+    /// None once the clocks are stopped
+    running: Option<Turn>,
+    /// when the running clock was last started
+    since: Instant,
+}
+
+impl ChessClock {
+    /// starts the clock of `turn`
+    pub fn new(control: TimeControl, turn: Turn) -> Self {
+        let side = Side {
+            remaining: control.stage(0).time,
+            stage: 0,
+            moves: 0,
+        };
+        Self {
+            control,
+            white: side.clone(),
+            black: side,
+            running: Some(turn),
+            since: Instant::now(),
+        }
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// the time `color` has left, counted up to now for the running clock
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        let remaining = self.side(color).remaining;
+        if self.running == Some(color) {
+            remaining.saturating_sub(self.used())
+        } else {
+            remaining
+        }
+    }
+
+    /// the side whose time ran out, if one did
+    pub fn flagged(&self) -> Option<PieceColor> {
+        self.running
+            .filter(|color| self.remaining(*color) == Duration::ZERO)
+    }
+
+    /// the side to move finished its move; it gets its bonus, maybe the time of its next
+    /// stage, and the other clock starts
+    pub fn press(&mut self) {
+        let Some(mover) = self.running else {
+            return;
+        };
+        let elapsed = self.since.elapsed();
+        self.switch_to(match mover {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        });
+
+        let control = &self.control;
+        let side = match mover {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        };
+        // a flag that fell stays down
+        if side.remaining == Duration::ZERO {
+            return;
+        }
+        side.remaining += match control.bonus {
+            Bonus::Increment(increment) => increment,
+            Bonus::Bronstein(delay) => elapsed.min(delay),
+            Bonus::None | Bonus::SimpleDelay(_) => Duration::ZERO,
+        };
+        side.moves += 1;
+        if control.stage(side.stage).moves == Some(side.moves) {
+            side.stage += 1;
+            side.moves = 0;
+            side.remaining += control.stage(side.stage).time;
+        }
+    }
+
+    /// stops the running clock and starts the one of `turn` without any bonus; eg. after
+    /// a move was taken back. neither the time nor the moves of the stage are given back
+    pub fn switch_to(&mut self, turn: Turn) {
+        self.stop();
+        self.running = Some(turn);
+        self.since = Instant::now();
+    }
+
+    /// stops both clocks; eg. once the game is over
+    pub fn stop(&mut self) {
+        if let Some(color) = self.running {
+            let remaining = self.remaining(color);
+            self.side_mut(color).remaining = remaining;
+            self.running = None;
+        }
+    }
+
+    /// what a computer player needs to know to split its time
+    pub fn search_clock(&self, color: PieceColor) -> Clock {
+        let side = self.side(color);
+        let stage = self.control.stage(side.stage);
+        Clock {
+            remaining: self.remaining(color),
+            // a delay saves about as much time as an increment of the same size
+            increment: match self.control.bonus {
+                Bonus::None => Duration::ZERO,
+                Bonus::Increment(bonus) | Bonus::SimpleDelay(bonus) | Bonus::Bronstein(bonus) => {
+                    bonus
+                }
+            },
+            moves_to_go: stage.moves.map(|moves| moves - side.moves),
+        }
+    }
+
+    /// the time counted against the running clock so far on this move
+    fn used(&self) -> Duration {
+        let elapsed = self.since.elapsed();
+        match self.control.bonus {
+            Bonus::SimpleDelay(delay) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+
+    fn side(&self, color: PieceColor) -> &Side {
+        match color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        }
+    }
+
+    fn side_mut(&mut self, color: PieceColor) -> &mut Side {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    /// lets `secs` seconds pass on the running clock
+    fn spend(clock: &mut ChessClock, secs: u32) {
+        clock.since -= SECOND * secs;
+    }
+
+    /// the clocks are read a moment after the time was set back
+    fn assert_about(actual: Duration, secs: u32) {
+        let expected = SECOND * secs;
+        assert!(
+            actual <= expected && expected - actual < Duration::from_millis(100),
+            "{actual:?} is not about {expected:?}"
+        );
+    }
+
+    fn minute_with(bonus: Bonus) -> ChessClock {
+        let control = TimeControl::new(
+            vec![Stage {
+                moves: None,
+                time: SECOND * 60,
+            }],
+            bonus,
+        );
+        ChessClock::new(control, PieceColor::White)
+    }
+
+    #[test]
+    fn the_increment_comes_after_the_move() {
+        let mut clock = minute_with(Bonus::Increment(SECOND * 2));
+        spend(&mut clock, 10);
+        assert_about(clock.remaining(PieceColor::White), 50);
+        clock.press();
+        assert_about(clock.remaining(PieceColor::White), 52);
+        assert_about(clock.remaining(PieceColor::Black), 60);
+    }
+
+    #[test]
+    fn bronstein_gives_back_the_time_spent_up_to_the_delay() {
+        let mut clock = minute_with(Bonus::Bronstein(SECOND * 3));
+        spend(&mut clock, 2);
+        clock.press();
+        assert_about(clock.remaining(PieceColor::White), 60);
+
+        clock.press();
+        spend(&mut clock, 10);
+        clock.press();
+        assert_about(clock.remaining(PieceColor::White), 53);
+    }
+
+    #[test]
+    fn a_simple_delay_passes_before_the_clock_runs() {
+        let mut clock = minute_with(Bonus::SimpleDelay(SECOND * 5));
+        spend(&mut clock, 3);
+        assert_about(clock.remaining(PieceColor::White), 60);
+        spend(&mut clock, 5);
+        assert_about(clock.remaining(PieceColor::White), 57);
+        clock.press();
+        assert_about(clock.remaining(PieceColor::White), 57);
+    }
+
+    #[test]
+    fn the_last_stage_repeats() {
+        let control = TimeControl::new(
+            vec![
+                Stage {
+                    moves: Some(2),
+                    time: SECOND * 60,
+                },
+                Stage {
+                    moves: Some(1),
+                    time: SECOND * 30,
+                },
+            ],
+            Bonus::None,
+        );
+        let mut clock = ChessClock::new(control, PieceColor::White);
+        let moves_to_go = |clock: &ChessClock, color| clock.search_clock(color).moves_to_go;
+        assert_eq!(moves_to_go(&clock, PieceColor::White), Some(2));
+
+        clock.press();
+        assert_eq!(moves_to_go(&clock, PieceColor::White), Some(1));
+        assert_eq!(moves_to_go(&clock, PieceColor::Black), Some(2));
+        assert_about(clock.remaining(PieceColor::White), 60);
+
+        clock.press();
+        clock.press();
+        assert_eq!(moves_to_go(&clock, PieceColor::White), Some(1));
+        assert_about(clock.remaining(PieceColor::White), 90);
+
+        clock.press();
+        clock.press();
+        assert_eq!(moves_to_go(&clock, PieceColor::White), Some(1));
+        assert_about(clock.remaining(PieceColor::White), 120);
+    }
+
+    #[test]
+    fn a_flag_stays_down() {
+        let mut clock = ChessClock::new(
+            TimeControl::fischer(SECOND * 5, SECOND * 2),
+            PieceColor::White,
+        );
+        assert_eq!(clock.flagged(), None);
+        spend(&mut clock, 6);
+        assert_eq!(clock.flagged(), Some(PieceColor::White));
+        assert_eq!(clock.remaining(PieceColor::White), Duration::ZERO);
+
+        clock.press();
+        assert_eq!(clock.remaining(PieceColor::White), Duration::ZERO);
+        clock.switch_to(PieceColor::White);
+        assert_eq!(clock.flagged(), Some(PieceColor::White));
+    }
+
+    #[test]
+    fn a_stopped_clock_keeps_its_time() {
+        let mut clock = minute_with(Bonus::None);
+        spend(&mut clock, 10);
+        clock.stop();
+        assert_eq!(clock.flagged(), None);
+        assert_about(clock.remaining(PieceColor::White), 50);
+        assert_eq!(clock.search_clock(PieceColor::White).increment, Duration::ZERO);
+    }
+}
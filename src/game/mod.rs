@@ -0,0 +1,523 @@
+use std::fmt;
+
+use crate::board::fen::{FenError, STARTING_FEN};
+use crate::board::pieces::{PieceColor, PieceKind};
+use crate::board::position::Position;
+use crate::board::{Board, BoardMoveError};
+use crate::computer::Move;
+use crate::Turn;
+
+pub mod clock;
+pub mod pgn;
+
+/// a draw can be claimed after this many plies without a capture or a pawn move
+const FIFTY_MOVES_PLIES: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    /// the side that won; None for a draw
+    pub fn winner(self) -> Option<PieceColor> {
+        match self {
+            GameResult::WhiteWins => Some(PieceColor::White),
+            GameResult::BlackWins => Some(PieceColor::Black),
+            GameResult::Draw => None,
+        }
+    }
+
+    /// a win for `color`
+    pub fn win_for(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => GameResult::WhiteWins,
+            PieceColor::Black => GameResult::BlackWins,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        };
+        write!(f, "{result}")
+    }
+}
+
+/// why the game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    /// adjudicated as a draw after a set number of moves
+    MoveLimit,
+    /// a player failed to move; eg. an external engine that crashed or hung
+    Forfeit,
+    /// a player ran out of time
+    TimeForfeit,
+    Resignation,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let termination = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FiftyMoves => "fifty-move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::MoveLimit => "move limit",
+            Termination::Forfeit => "forfeit",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::Resignation => "resignation",
+        };
+        write!(f, "{termination}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+/// a move that was played in a game, along with what is needed to take it back
+#[derive(Debug, Clone)]
+pub struct PlayedMove {
+    pub fr: Position,
+    pub to: Position,
+    pub promotion: PieceKind,
+    /// the move in standard algebraic notation
+    pub san: String,
+    board_before: Board,
+    halfmove_clock_before: u32,
+}
+
+impl PlayedMove {
+    pub fn as_move(&self) -> Move {
+        (self.fr, self.to)
+    }
+}
+
+/// a game from a starting position; keeps the moves played so far so it can tell
+/// repetitions and the fifty-move rule apart, take moves back and be written as PGN
+#[derive(Debug, Clone)]
+pub struct Game {
+    start: Board,
+    start_turn: Turn,
+    start_halfmove_clock: u32,
+    /// the number of the first move, counted the FEN way; it goes up after black's moves
+    start_fullmove_number: u32,
+    board: Board,
+    turn: Turn,
+    moves: Vec<PlayedMove>,
+    /// the zobrist hashes of every position of the game, starting with the first one
+    hashes: Vec<u64>,
+    /// plies since the last capture or pawn move
+    halfmove_clock: u32,
+    /// set when the game ended for a reason the board doesn't show; eg. a resignation
+    adjudication: Option<Outcome>,
+}
+
+impl Game {
+    pub fn new(board: Board, turn: Turn) -> Self {
+        let hash = board.zobrist_hash(turn);
+        Self {
+            start: board.clone(),
+            start_turn: turn,
+            start_halfmove_clock: 0,
+            start_fullmove_number: 1,
+            board,
+            turn,
+            moves: vec![],
+            hashes: vec![hash],
+            halfmove_clock: 0,
+            adjudication: None,
+        }
+    }
+
+    /// a game from a complete FEN; unlike Board::parse_fen it keeps the move counters
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let (board, turn) = Board::parse_fen(fen)?;
+        let halfmove_clock = match fen.split_whitespace().nth(4) {
+            Some(clock) => clock.parse().map_err(|_| FenError::InvalidHalfmoveClock)?,
+            None => 0,
+        };
+        // some programs write 0 for the first move
+        let fullmove_number = match fen.split_whitespace().nth(5) {
+            Some(number) => number
+                .parse::<u32>()
+                .map_err(|_| FenError::InvalidFullmoveNumber)?
+                .max(1),
+            None => 1,
+        };
+        Ok(Self {
+            start_halfmove_clock: halfmove_clock,
+            start_fullmove_number: fullmove_number,
+            halfmove_clock,
+            ..Self::new(board, turn)
+        })
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn turn(&self) -> Turn {
+        self.turn
+    }
+
+    pub fn start(&self) -> (&Board, Turn) {
+        (&self.start, self.start_turn)
+    }
+
+    /// the FEN of the starting position, with its move counters
+    pub fn start_fen(&self) -> String {
+        with_move_counters(
+            self.start.to_fen(self.start_turn),
+            self.start_halfmove_clock,
+            self.start_fullmove_number,
+        )
+    }
+
+    /// plies since the last capture or pawn move; a draw can be claimed at 100
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn moves(&self) -> &[PlayedMove] {
+        &self.moves
+    }
+
+    /// the zobrist hashes of the positions since the last capture or pawn move, the current
+    /// one last; the positions before can't come up again
+    pub fn reversible_hashes(&self) -> &[u64] {
+        let reversible = (self.halfmove_clock as usize).min(self.hashes.len() - 1);
+        &self.hashes[self.hashes.len() - 1 - reversible..]
+    }
+
+    /// the board and the side to move after the first `ply` moves; the current position once
+    /// `ply` reaches the number of moves played
+    pub fn position(&self, ply: usize) -> (&Board, Turn) {
+        match self.moves.get(ply) {
+            Some(played) => {
+                let turn = if ply.is_multiple_of(2) {
+                    self.start_turn
+                } else {
+                    match self.start_turn {
+                        PieceColor::White => PieceColor::Black,
+                        PieceColor::Black => PieceColor::White,
+                    }
+                };
+                (&played.board_before, turn)
+            }
+            None => (&self.board, self.turn),
+        }
+    }
+
+    /// plays a move of the side to move; no more moves can be played once the game is over
+    pub fn play(
+        &mut self,
+        fr: Position,
+        to: Position,
+        promotion: PieceKind,
+    ) -> Result<(), BoardMoveError> {
+        if self.outcome().is_some() {
+            return Err(BoardMoveError::GameOver);
+        }
+        self.play_on(fr, to, promotion)
+    }
+
+    /// same as play but the moves go on after the end of the game; the moves of a game played
+    /// elsewhere don't always stop where this one would, eg. at a repetition nobody claimed
+    pub fn play_on(
+        &mut self,
+        fr: Position,
+        to: Position,
+        promotion: PieceKind,
+    ) -> Result<(), BoardMoveError> {
+        let board_before = self.board.clone();
+        // a Chess960 king castles onto its own rook; that's no capture
+        let resets_clock = (matches!(self.board.look_up_cell(to), Some(Some(_)))
+            && !self.board.is_castling(fr, to))
+            || matches!(self.board.look_up_cell(fr), Some(Some(piece)) if piece.kind == PieceKind::Pawn);
+        let promotion = if self.board.is_promotion(fr, to) {
+            promotion
+        } else {
+            PieceKind::Queen
+        };
+
+        let mut turn = self.turn;
+        self.board
+            .move_piece_and_promote(&mut turn, fr, to, promotion)?;
+        let san = board_before.to_san(self.turn, fr, to, promotion);
+
+        self.moves.push(PlayedMove {
+            fr,
+            to,
+            promotion,
+            san,
+            board_before,
+            halfmove_clock_before: self.halfmove_clock,
+        });
+        self.turn = turn;
+        self.halfmove_clock = if resets_clock {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        self.hashes.push(self.board.zobrist_hash(self.turn));
+        Ok(())
+    }
+
+    /// takes the last move back and returns it
+    pub fn undo(&mut self) -> Option<PlayedMove> {
+        let played = self.moves.pop()?;
+        self.hashes.pop();
+        self.board = played.board_before.clone();
+        self.turn = match self.turn {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        };
+        self.halfmove_clock = played.halfmove_clock_before;
+        self.adjudication = None;
+        Some(played)
+    }
+
+    /// ends the game for a reason the board doesn't show
+    pub fn adjudicate(&mut self, result: GameResult, termination: Termination) {
+        self.adjudication = Some(Outcome {
+            result,
+            termination,
+        });
+    }
+
+    /// `color` ran out of time; a loss unless the opponent has no way to mate at all
+    pub fn flag(&mut self, color: PieceColor) {
+        let opponent = match color {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        };
+        let result = if self.can_mate(opponent) {
+            GameResult::win_for(opponent)
+        } else {
+            GameResult::Draw
+        };
+        self.adjudicate(result, Termination::TimeForfeit);
+    }
+
+    /// how the game ended; None while it goes on
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.adjudication.is_some() {
+            return self.adjudication;
+        }
+        if !self.board.has_legal_moves(self.turn) {
+            return Some(if self.board.is_king_safe(self.turn) {
+                Outcome {
+                    result: GameResult::Draw,
+                    termination: Termination::Stalemate,
+                }
+            } else {
+                let winner = match self.turn {
+                    PieceColor::White => PieceColor::Black,
+                    PieceColor::Black => PieceColor::White,
+                };
+                Outcome {
+                    result: GameResult::win_for(winner),
+                    termination: Termination::Checkmate,
+                }
+            });
+        }
+        let draw = |termination| {
+            Some(Outcome {
+                result: GameResult::Draw,
+                termination,
+            })
+        };
+        if self.halfmove_clock >= FIFTY_MOVES_PLIES {
+            return draw(Termination::FiftyMoves);
+        }
+        let current = self.hashes.last().unwrap();
+        if self.hashes.iter().filter(|hash| *hash == current).count() >= 3 {
+            return draw(Termination::ThreefoldRepetition);
+        }
+        if self.is_insufficient_material() {
+            return draw(Termination::InsufficientMaterial);
+        }
+        None
+    }
+
+    /// neither side can possibly mate: bare kings, or a single minor piece left
+    fn is_insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        for (_, piece) in self.board.pieces() {
+            match piece.kind {
+                PieceKind::King => {}
+                PieceKind::Knight | PieceKind::Bishop => minors += 1,
+                _ => return false,
+            }
+        }
+        minors <= 1
+    }
+
+    /// `color` has more than a lone king; a single minor piece only mates with the help of
+    /// the other side's pieces
+    fn can_mate(&self, color: PieceColor) -> bool {
+        let (mut minors, mut others) = (0, 0);
+        for (_, piece) in self.board.pieces() {
+            match piece.kind {
+                PieceKind::King => {}
+                _ if piece.color != color => others += 1,
+                PieceKind::Knight | PieceKind::Bishop => minors += 1,
+                _ => return true,
+            }
+        }
+        minors >= 2 || (minors == 1 && others > 0)
+    }
+
+    /// the game in Portable Game Notation; `tags` come after the seven required ones and
+    /// may override them, eg. ("Event", "Engine match")
+    pub fn to_pgn(&self, tags: &[(&str, &str)]) -> String {
+        let result = self
+            .outcome()
+            .map_or("*".to_string(), |outcome| outcome.result.to_string());
+        let start_fen = self.start_fen();
+
+        let mut all_tags: Vec<(String, String)> = [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result.as_str()),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        if start_fen != STARTING_FEN {
+            all_tags.push(("SetUp".to_string(), "1".to_string()));
+            all_tags.push(("FEN".to_string(), start_fen));
+        }
+        if self.board.is_chess960() {
+            all_tags.push(("Variant".to_string(), "Chess960".to_string()));
+        }
+        if let Some(outcome) = self.outcome() {
+            all_tags.push(("Termination".to_string(), outcome.termination.to_string()));
+        }
+        for (name, value) in tags {
+            match all_tags.iter_mut().find(|(existing, _)| existing == name) {
+                Some(tag) => tag.1 = value.to_string(),
+                None => all_tags.push((name.to_string(), value.to_string())),
+            }
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in all_tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn += &format!("[{name} \"{value}\"]\n");
+        }
+        pgn.push('\n');
+
+        let mut tokens = vec![];
+        // counts plies as if white had moved first so black's moves share white's number
+        let first_ply = match self.start_turn {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        };
+        for (i, played) in self.moves.iter().enumerate() {
+            let ply = first_ply + i;
+            let move_number = self.start_fullmove_number as usize + ply / 2;
+            if ply % 2 == 0 {
+                tokens.push(format!("{move_number}."));
+            } else if i == 0 {
+                tokens.push(format!("{move_number}..."));
+            }
+            tokens.push(played.san.clone());
+        }
+        tokens.push(result);
+
+        // the movetext is wrapped at 80 columns
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                pgn += &line;
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &token;
+        }
+        pgn += &line;
+        pgn.push('\n');
+        pgn
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        let (board, turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        Self::new(board, turn)
+    }
+}
+
+/// puts `clock` and `fullmove` in place of the "0 1" that Board::to_fen writes
+fn with_move_counters(fen: String, clock: u32, fullmove: u32) -> String {
+    match fen.rsplitn(3, ' ').collect::<Vec<_>>()[..] {
+        [_, _, rest] => format!("{rest} {clock} {fullmove}"),
+        _ => fen,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::PositionNotation;
+    use crate::game::pgn::parse_pgn;
+
+    fn play(game: &mut Game, fr: &str, to: &str) {
+        let (fr, to) = (
+            Position::from_notation(fr).unwrap(),
+            Position::from_notation(to).unwrap(),
+        );
+        game.play(fr, to, PieceKind::Queen).unwrap();
+    }
+
+    #[test]
+    fn the_moves_are_numbered_from_the_fen() {
+        let fen = "8/P6k/8/8/8/8/6Kp/8 b - - 3 40";
+        let mut game = Game::from_fen(fen).unwrap();
+        assert_eq!(game.start_fen(), fen);
+        play(&mut game, "h7", "g6");
+        play(&mut game, "a7", "a8");
+        play(&mut game, "g6", "f5");
+
+        let pgn = game.to_pgn(&[]);
+        assert!(pgn.contains(&format!("[FEN \"{fen}\"]")), "{pgn}");
+        assert!(pgn.contains("40... Kg6 41. a8=Q Kf5 *"), "{pgn}");
+        let read = parse_pgn(&pgn)[0].to_game().unwrap();
+        assert_eq!(read.start_fen(), fen);
+        assert_eq!(read.to_pgn(&[]), pgn);
+    }
+
+    #[test]
+    fn a_fen_without_move_counters_starts_at_the_first_move() {
+        let game = Game::from_fen("8/8/4k3/8/8/3K4/8/R7 w - -").unwrap();
+        assert_eq!(game.start_fen(), "8/8/4k3/8/8/3K4/8/R7 w - - 0 1");
+        assert!(matches!(
+            Game::from_fen("8/8/4k3/8/8/3K4/8/R7 w - - 0 x"),
+            Err(FenError::InvalidFullmoveNumber)
+        ));
+    }
+}
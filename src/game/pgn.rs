@@ -0,0 +1,168 @@
+use std::fmt;
+
+use crate::board::fen::{FenError, STARTING_FEN};
+use crate::game::{Game, GameResult};
+
+/// a game as read from a PGN file, before its moves are checked
+#[derive(Debug, Clone, Default)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// the main line in standard algebraic notation; comments and variations are dropped
+    pub moves: Vec<String>,
+    /// the result at the end of the movetext; None for "*" or when it's missing
+    pub result: Option<GameResult>,
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+    InvalidFen(FenError),
+    /// the move at this index of the main line is illegal or can't be read
+    IllegalMove(usize, String),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::InvalidFen(err) => write!(f, "{err}"),
+            PgnError::IllegalMove(ply, san) => {
+                write!(f, "illegal move \"{san}\" at ply {}", ply + 1)
+            }
+        }
+    }
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// replays the moves from the starting position, or from the FEN tag if there is one
+    pub fn to_game(&self) -> Result<Game, PgnError> {
+        let fen = self.tag("FEN").unwrap_or(STARTING_FEN);
+        let mut game = Game::from_fen(fen).map_err(PgnError::InvalidFen)?;
+        for (ply, san) in self.moves.iter().enumerate() {
+            let (fr, to, promotion) = game
+                .board()
+                .parse_san(game.turn(), san)
+                .ok_or_else(|| PgnError::IllegalMove(ply, san.clone()))?;
+            game.play_on(fr, to, promotion)
+                .map_err(|_| PgnError::IllegalMove(ply, san.clone()))?;
+        }
+        Ok(game)
+    }
+}
+
+/// reads every game of a PGN file; the moves aren't checked until `PgnGame::to_game`
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut game = PgnGame::default();
+    let mut chars = text.chars().peekable();
+    // variations are skipped, however deep they are nested
+    let mut variation_depth = 0;
+
+    let finish = |game: &mut PgnGame, games: &mut Vec<PgnGame>| {
+        if !game.tags.is_empty() || !game.moves.is_empty() || game.result.is_some() {
+            games.push(std::mem::take(game));
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' if variation_depth == 0 => {
+                // a tag after the movetext starts the next game
+                if !game.moves.is_empty() || game.result.is_some() {
+                    finish(&mut game, &mut games);
+                }
+                let mut tag = String::new();
+                let mut in_quotes = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if in_quotes => {
+                            if let Some(escaped) = chars.next() {
+                                tag.push('\\');
+                                tag.push(escaped);
+                            }
+                            continue;
+                        }
+                        '"' => in_quotes = !in_quotes,
+                        ']' if !in_quotes => break,
+                        _ => {}
+                    }
+                    tag.push(c);
+                }
+                if let Some(parsed) = parse_tag(&tag) {
+                    game.tags.push(parsed);
+                }
+            }
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth = variation_depth.max(1) - 1,
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "[]{}();".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                if variation_depth > 0 {
+                    continue;
+                }
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        game.result = match token.as_str() {
+                            "1-0" => Some(GameResult::WhiteWins),
+                            "0-1" => Some(GameResult::BlackWins),
+                            "1/2-1/2" => Some(GameResult::Draw),
+                            _ => None,
+                        };
+                        finish(&mut game, &mut games);
+                    }
+                    // numeric annotation glyphs
+                    _ if token.starts_with('$') => {}
+                    _ => {
+                        // move numbers, possibly glued to the move; eg. "12." or "12...Nf6"
+                        let san =
+                            token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                        let san = if san.len() < token.len() && token.contains('.') {
+                            san
+                        } else {
+                            token.as_str()
+                        };
+                        if !san.is_empty() {
+                            game.moves.push(san.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    finish(&mut game, &mut games);
+    games
+}
+
+/// Name "value" with the escapes of the value undone
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let (name, value) = tag.trim().split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+    Some((name.to_string(), value))
+}
@@ -2,7 +2,11 @@ use board::pieces::PieceColor;
 
 use crate::board::position::Position;
 
+pub mod arena;
 pub mod board;
 pub mod computer;
+pub mod game;
+pub mod uci;
+pub mod xboard;
 
 pub type Turn = PieceColor;
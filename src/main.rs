@@ -1,37 +1,173 @@
+use std::fs;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use chess::arena::EngineSpec;
 use chess::board::Board;
+use chess::computer::difficulty::Difficulty;
+use chess::computer::engine::Engine;
+use chess::game::clock::TimeControl;
+use chess::game::pgn::parse_pgn;
+use chess::game::Game;
 use chess::Turn;
 use chess_ui::ChessUI;
 use iced::{pure::Application, window::Settings};
 
+mod chess_ui;
+
+const USAGE: &str = "\
+usage: chess [options]
+
+without options the game starts with the setup screen
+
+options:
+  --white <player>        human (the default), computer, or an engine: random, full,
+                          uci:<path> or a level; full and the levels take a book after a
+                          comma: full,book=<file>[,bookdepth=<n>][,bookselection=best]
+  --black <player>        the same for black; computer by default
+  --engine-level <level>  the level of the players given as computer: beginner, novice,
+                          intermediate (the default), advanced or strong
+  --fen <fen>             the position to start from
+  --pgn <file>            goes on with the first game of the file
+  --flip                  black at the bottom of the board
+  --seed <n>              seeds the computer players (default random)";
+
 fn main() {
-    let settings = ChessSettings::new(
-        Board::default(),
-        Turn::White,
-        (Player::Computer, Player::Computer),
-    );
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let settings = if args.is_empty() {
+        None
+    } else {
+        Some(parse_args(&args).unwrap_or_else(|err| exit_with(&err)))
+    };
 
     let game = ChessGame::new(settings);
     game.run();
 }
 
-#[allow(dead_code)]
-enum Player {
+/// who plays a side, as given on the command line
+enum PlayerArg {
     Human,
+    /// the built-in engine at the level of --engine-level
     Computer,
+    Engine(EngineSpec),
+}
+
+impl PlayerArg {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "human" => Ok(PlayerArg::Human),
+            "computer" => Ok(PlayerArg::Computer),
+            spec => spec
+                .parse()
+                .map(PlayerArg::Engine)
+                .map_err(|err| format!("{err}; a player can be human or computer too")),
+        }
+    }
+
+    fn build(self, level: Difficulty) -> Result<Player, String> {
+        let spec = match self {
+            PlayerArg::Human => return Ok(Player::Human),
+            PlayerArg::Computer => EngineSpec::Level(level, None),
+            PlayerArg::Engine(spec) => spec,
+        };
+        spec.build()
+            .map(Player::computer)
+            .map_err(|err| err.to_string())
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<ChessSettings, String> {
+    let (mut white, mut black) = (PlayerArg::Human, PlayerArg::Computer);
+    let mut level = Difficulty::default();
+    let mut game = None;
+    let mut flipped = false;
+    let mut seed = rand::random();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+            "--flip" => {
+                flipped = true;
+                continue;
+            }
+            "--white" | "--black" | "--engine-level" | "--fen" | "--pgn" | "--seed" => {}
+            // before taking a value, which a misspelt flag doesn't have
+            _ => return Err(format!("unknown option {arg}")),
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--white" => white = PlayerArg::parse(value)?,
+            "--black" => black = PlayerArg::parse(value)?,
+            "--engine-level" => {
+                level = value
+                    .parse()
+                    .map_err(|_| format!("{arg} needs a level, not \"{value}\""))?
+            }
+            "--fen" => {
+                game = Some(Game::from_fen(value).map_err(|err| err.to_string())?);
+            }
+            "--pgn" => {
+                let text = fs::read_to_string(value).map_err(|err| format!("{value}: {err}"))?;
+                let pgn_game = parse_pgn(&text)
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| format!("{value}: no game in the file"))?;
+                game = Some(
+                    pgn_game
+                        .to_game()
+                        .map_err(|err| format!("{value}: {err}"))?,
+                );
+            }
+            "--seed" => {
+                seed = value
+                    .parse()
+                    .map_err(|_| format!("{arg} needs a number, not \"{value}\""))?
+            }
+            _ => unreachable!("the options were checked above"),
+        }
+    }
+
+    let game = game.unwrap_or_else(|| Game::new(Board::default(), Turn::White));
+    let players = (white.build(level)?, black.build(level)?);
+    let mut settings = ChessSettings::new(game, players, seed, None);
+    settings.flipped = flipped;
+    Ok(settings)
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    process::exit(1);
+}
+
+enum Player {
+    Human,
+    /// shared with the thread the engine thinks on
+    Computer(Arc<Mutex<Box<dyn Engine>>>),
+}
+
+impl Player {
+    fn computer(engine: Box<dyn Engine>) -> Self {
+        Player::Computer(Arc::new(Mutex::new(engine)))
+    }
 }
 
 pub struct ChessGame {
-    settings: ChessSettings,
+    /// None opens the setup screen first
+    settings: Option<ChessSettings>,
 }
 
 impl ChessGame {
-    pub fn new(settings: ChessSettings) -> Self {
+    pub fn new(settings: Option<ChessSettings>) -> Self {
         Self { settings }
     }
     pub fn run(self) {
         ChessUI::run(iced::Settings {
             window: Settings {
-                size: (800, 800),
+                size: (1200, 800),
                 resizable: false,
                 ..Default::default()
             },
@@ -49,255 +185,30 @@ impl ChessGame {
 }
 
 pub struct ChessSettings {
-    board: Board,
-    turn: Turn,
+    /// the game to go on with; often one without any moves yet
+    game: Game,
     players: (Player, Player),
+    /// seeds the computer players; the same seed and board replay the same game
+    seed: u64,
+    /// None for a game without clocks
+    time_control: Option<TimeControl>,
+    /// black at the bottom of the board
+    flipped: bool,
 }
 
 impl ChessSettings {
-    fn new(board: Board, turn: Turn, players: (Player, Player)) -> Self {
+    fn new(
+        game: Game,
+        players: (Player, Player),
+        seed: u64,
+        time_control: Option<TimeControl>,
+    ) -> Self {
         Self {
-            board,
-            turn,
+            game,
             players,
+            seed,
+            time_control,
+            flipped: false,
         }
     }
 }
-
-mod chess_ui {
-
-    use chess::board::pieces::{moves, PieceColor, PieceKind};
-    use chess::board::position::Position;
-    use chess::board::{Board, Cell};
-    use chess::computer::ComputerEngine;
-    use chess::Turn;
-    use iced::button::StyleSheet;
-    use iced::pure::widget::{button, Button, Column, Row};
-    use iced::pure::{Application, Element};
-    use iced::{Background, Color, Length, Svg};
-
-    use crate::{ChessSettings, Player};
-
-    pub struct ChessUI {
-        board: Board,
-        turn: Turn,
-        players: (Player, Player),
-        current_selected: Option<Position>,
-        legal_moves: Vec<Position>,
-        king_state: KingState,
-    }
-
-    impl ChessUI {
-        fn new(board: Board, turn: Turn, players: (Player, Player)) -> Self {
-            Self {
-                board,
-                turn,
-                players,
-                current_selected: None,
-                legal_moves: Vec::new(),
-                // TODO: should depend on the board setup
-                king_state: KingState::Safe,
-            }
-        }
-        fn current_player(&self) -> &Player {
-            match self.turn {
-                PieceColor::White => &self.players.0,
-                PieceColor::Black => &self.players.1,
-            }
-        }
-    }
-    struct SquareStyleSheet {
-        color: Color,
-    }
-
-    enum KingState {
-        Safe,
-        Check(Position),
-        Checkmate(Position),
-    }
-
-    impl StyleSheet for SquareStyleSheet {
-        fn active(&self) -> button::Style {
-            button::Style {
-                background: Some(Background::Color(self.color)),
-                border_width: 0.78,
-                border_color: Color::BLACK,
-                ..Default::default()
-            }
-        }
-    }
-
-    #[derive(Debug, Clone)]
-    pub enum CellClickedEvent {
-        Clicked(Position),
-    }
-    impl Application for ChessUI {
-        type Executor = iced::executor::Default;
-        type Message = CellClickedEvent;
-        type Flags = ChessSettings;
-
-        fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
-            (
-                ChessUI::new(flags.board, flags.turn, flags.players),
-                iced::Command::none(),
-            )
-        }
-
-        fn title(&self) -> String {
-            "Chess".to_string()
-        }
-
-        fn update(&mut self, message: Self::Message) -> iced::Command<Self::Message> {
-            if let KingState::Checkmate(_) = self.king_state {
-                return iced::Command::none();
-            }
-            match message {
-                CellClickedEvent::Clicked(pos) => match self.current_player() {
-                    Player::Human => {
-                        if self.current_selected.is_none() {
-                            let piece = self.board.look_up_cell(pos).unwrap();
-
-                            if piece.is_some() {
-                                self.current_selected = Some(pos);
-                                self.legal_moves =
-                                    moves::get_legal_moves(pos, &self.board, &self.turn);
-                            } else {
-                                self.legal_moves.clear();
-                                self.current_selected = None;
-                            }
-
-                            return iced::Command::none();
-                        }
-                        match self.board.move_piece(
-                            &mut self.turn,
-                            self.current_selected.unwrap(),
-                            pos,
-                        ) {
-                            Ok(_) => {
-                                self.current_selected = None;
-                                self.legal_moves.clear();
-                            }
-                            Err(err) => match err {
-                                chess::board::BoardMoveError::NotYourTurn
-                                | chess::board::BoardMoveError::Illegal => {
-                                    let piece = self.board.look_up_cell(pos).unwrap();
-
-                                    if piece.is_some() {
-                                        self.current_selected = Some(pos);
-                                        self.legal_moves = moves::get_legal_moves(
-                                            pos,
-                                            &mut self.board,
-                                            &self.turn,
-                                        );
-                                    } else {
-                                        self.legal_moves.clear();
-                                        self.current_selected = None;
-                                    }
-                                }
-                                _ => {}
-                            },
-                        }
-                    }
-                    Player::Computer => {
-                        let engine = ComputerEngine;
-
-                        let (fr, to) = engine.pick_move(&self.board, self.turn);
-
-                        self.board.move_piece(&mut self.turn, fr, to).unwrap();
-                    }
-                },
-            }
-
-            let king_pos = self.board.look_up_king_pos(self.turn);
-            if !self.board.is_king_safe(self.turn) {
-                self.king_state = KingState::Check(king_pos);
-            } else {
-                self.king_state = KingState::Safe;
-            }
-
-            if self.board.is_checkmate_for(match self.turn {
-                PieceColor::White => PieceColor::Black,
-                PieceColor::Black => PieceColor::White,
-            }) {
-                self.king_state = KingState::Checkmate(king_pos);
-                println!("CHECKMATED!");
-            }
-
-            iced::Command::none()
-        }
-
-        fn view(&self) -> Element<'_, Self::Message> {
-            let mut column = Column::new();
-
-            for i in (0..8).rev() {
-                let mut row = Row::new();
-                for j in 0..8 {
-                    let mut color = if (i + j) % 2 == 0 {
-                        Color::from_rgba8(74, 101, 71, 20.0)
-                    } else {
-                        Color::WHITE
-                    };
-
-                    let piece_str = get_piece_str(self.board.look_up_cell((i, j).into()).unwrap());
-
-                    let path = format!("{}/src/icons/{piece_str}.svg", env!("CARGO_MANIFEST_DIR"));
-
-                    let svg = Svg::from_path(path);
-
-                    if self.legal_moves.contains(&(i, j).into()) {
-                        color = Color::from_rgba8(226, 203, 128, 35.0);
-                    }
-
-                    if let KingState::Check(pos) = self.king_state {
-                        if pos == Position::new(i, j) {
-                            color = Color::from_rgb8(204, 48, 76);
-                        }
-                    }
-
-                    if let KingState::Checkmate(pos) = self.king_state {
-                        if pos == Position::new(i, j) {
-                            color = Color::from_rgb8(230, 48, 76);
-                        }
-                    }
-                    if let Some(pos) = self.current_selected {
-                        if pos == Position::new(i, j) {
-                            color = Color::from_rgba8(195, 228, 235, 10.0);
-                        }
-                    }
-
-                    let square = Button::new(svg)
-                        .on_press(CellClickedEvent::Clicked((i, j).into()))
-                        .padding(11)
-                        .width(Length::Units(100))
-                        .height(Length::Units(100))
-                        .style(SquareStyleSheet { color });
-                    row = row.push(square);
-                }
-                column = column.push(row);
-            }
-
-            column.into()
-        }
-    }
-
-    fn get_piece_str(cell: &Cell) -> String {
-        let mut piece_str = "".to_string();
-
-        if let Some(piece) = cell {
-            if piece.color == PieceColor::White {
-                piece_str.push_str("white_");
-            } else {
-                piece_str.push_str("black_");
-            }
-            piece_str.push_str(match piece.kind {
-                PieceKind::King => "king",
-                PieceKind::Queen => "queen",
-                PieceKind::Knight => "knight",
-                PieceKind::Bishop => "bishop",
-                PieceKind::Rook => "rook",
-                PieceKind::Pawn => "pawn",
-            });
-        }
-        piece_str
-    }
-}
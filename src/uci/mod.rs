@@ -0,0 +1,571 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::fen::STARTING_FEN;
+use crate::board::pieces::{PieceColor, PieceKind};
+use crate::board::position::{Position, PositionNotation};
+use crate::board::{Board, BoardMoveError};
+use crate::computer::book::OpeningBook;
+use crate::computer::difficulty::Difficulty;
+use crate::computer::engine::{Engine, SearchInfo, SearchLimits};
+use crate::computer::search::{SearchEngine, DEFAULT_BOOK_DEPTH, MATE_SCORE};
+use crate::computer::syzygy::Tablebase;
+use crate::computer::time_manager::Clock;
+use crate::computer::transposition::TranspositionTable;
+use crate::computer::Move;
+use crate::game::Game;
+use crate::Turn;
+
+const ENGINE_NAME: &str = "chess-game-in-rust";
+const ENGINE_AUTHOR: &str = "saleem1333";
+const MAX_HASH_MB: usize = 4096;
+const MAX_THREADS: usize = 64;
+/// the Skill option goes through the difficulty levels; one past the last is full strength
+const MAX_SKILL: usize = Difficulty::ALL.len();
+const MAX_BOOK_DEPTH: usize = 100;
+
+/// speaks the Universal Chess Interface; reads commands from `input` until "quit" or the end
+/// of the input and writes the engine's answers to `output`
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) {
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !uci.handle(line.trim()) {
+            break;
+        }
+    }
+    uci.stop_search();
+}
+
+struct Uci<W> {
+    engine: Arc<Mutex<SearchEngine>>,
+    output: Arc<Mutex<W>>,
+    /// the position to search and the moves that led to it
+    game: Game,
+    search: Option<(JoinHandle<()>, Arc<AtomicBool>)>,
+    /// the book is only handed to the engine while OwnBook is on
+    book: Option<Arc<OpeningBook>>,
+    own_book: bool,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    fn new(output: W) -> Self {
+        let (board, turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        Self {
+            engine: Arc::new(Mutex::new(SearchEngine::full_strength())),
+            output: Arc::new(Mutex::new(output)),
+            game: Game::new(board, turn),
+            search: None,
+            book: None,
+            own_book: false,
+        }
+    }
+
+    /// returns false once the engine should quit
+    fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                send(&self.output, &format!("id name {ENGINE_NAME}"));
+                send(&self.output, &format!("id author {ENGINE_AUTHOR}"));
+                send(
+                    &self.output,
+                    &format!(
+                        "option name Hash type spin default {} min 1 max {MAX_HASH_MB}",
+                        TranspositionTable::DEFAULT_SIZE_MB
+                    ),
+                );
+                send(
+                    &self.output,
+                    &format!("option name Threads type spin default 1 min 1 max {MAX_THREADS}"),
+                );
+                send(
+                    &self.output,
+                    &format!(
+                        "option name Skill type spin default {MAX_SKILL} min 0 max {MAX_SKILL}"
+                    ),
+                );
+                send(&self.output, "option name OwnBook type check default false");
+                send(
+                    &self.output,
+                    "option name BookFile type string default <empty>",
+                );
+                send(
+                    &self.output,
+                    &format!(
+                        "option name BookDepth type spin default {DEFAULT_BOOK_DEPTH} min 0 max {MAX_BOOK_DEPTH}"
+                    ),
+                );
+                send(
+                    &self.output,
+                    "option name SyzygyPath type string default <empty>",
+                );
+                // castling is written the Chess960 way in every position that isn't set up
+                // as in standard chess, so the option changes nothing
+                send(&self.output, "option name UCI_Chess960 type check default false");
+                send(&self.output, "uciok");
+            }
+            Some("isready") => send(&self.output, "readyok"),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.engine.lock().unwrap().new_game();
+            }
+            Some("setoption") => {
+                self.stop_search();
+                self.set_option(line);
+            }
+            Some("position") => {
+                self.stop_search();
+                self.set_position(tokens.collect::<Vec<_>>().join(" ").as_str());
+            }
+            Some("go") => {
+                self.stop_search();
+                self.go(tokens.collect());
+            }
+            Some("stop") => self.stop_search(),
+            Some("quit") => return false,
+            // unknown commands are ignored, as the protocol asks
+            _ => {}
+        }
+        true
+    }
+
+    fn set_option(&mut self, line: &str) {
+        // setoption name <id> [value <x>]; the name may contain spaces
+        let Some(rest) = line.split_once(" name ").map(|(_, rest)| rest) else {
+            return;
+        };
+        let (name, value) = match rest.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (rest.trim(), ""),
+        };
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "ownbook" => self.own_book = value.eq_ignore_ascii_case("true"),
+            "bookfile" if value.is_empty() || value == "<empty>" => self.book = None,
+            "syzygypath" => {
+                let tablebase = if value.is_empty() || value == "<empty>" {
+                    None
+                } else {
+                    match Tablebase::open(value) {
+                        Ok(tablebase) => {
+                            send(
+                                &self.output,
+                                &format!(
+                                    "info string found {} tablebases of up to {} pieces",
+                                    tablebase.len(),
+                                    tablebase.max_pieces()
+                                ),
+                            );
+                            Some(Arc::new(tablebase))
+                        }
+                        Err(err) => {
+                            send(
+                                &self.output,
+                                &format!("info string can't read {value}: {err}"),
+                            );
+                            None
+                        }
+                    }
+                };
+                self.engine.lock().unwrap().set_tablebase(tablebase);
+                return;
+            }
+            "bookfile" => match OpeningBook::open(value) {
+                Ok(book) => self.book = Some(Arc::new(book)),
+                Err(err) => {
+                    self.book = None;
+                    send(
+                        &self.output,
+                        &format!("info string can't read {value}: {err}"),
+                    );
+                }
+            },
+            _ => {
+                let Ok(value) = value.parse::<usize>() else {
+                    return;
+                };
+                let mut engine = self.engine.lock().unwrap();
+                match name.as_str() {
+                    "hash" => engine.set_hash_size(value.clamp(1, MAX_HASH_MB)),
+                    "threads" => engine.set_threads(value.clamp(1, MAX_THREADS)),
+                    "skill" => engine.set_difficulty(Difficulty::ALL.get(value).copied()),
+                    "bookdepth" => engine.set_book_depth(value.min(MAX_BOOK_DEPTH)),
+                    _ => {}
+                }
+                return;
+            }
+        }
+        let book = self.book.clone().filter(|_| self.own_book);
+        self.engine.lock().unwrap().set_book(book);
+    }
+
+    /// position [startpos | fen <fen>] [moves <move>...]
+    fn set_position(&mut self, args: &str) {
+        let (setup, moves) = match args.split_once("moves") {
+            Some((setup, moves)) => (setup.trim(), moves),
+            None => (args.trim(), ""),
+        };
+        let fen = match setup.strip_prefix("fen") {
+            Some(fen) => fen.trim(),
+            None => STARTING_FEN,
+        };
+        let Ok(mut game) = Game::from_fen(fen) else {
+            return;
+        };
+        for mv in moves.split_whitespace() {
+            // the rest of the moves make no sense after an illegal one
+            let Some((fr, to, promotion)) = parse_uci_move(mv) else {
+                break;
+            };
+            if game
+                .play_on(fr, to, promotion.unwrap_or(PieceKind::Queen))
+                .is_err()
+            {
+                break;
+            }
+        }
+        self.game = game;
+    }
+
+    fn go(&mut self, args: Vec<&str>) {
+        let mut limits = SearchLimits::default();
+        let mut infinite = false;
+        let (mut time, mut increment, mut moves_to_go) = (None, Duration::ZERO, None);
+        let (mut opponent_time, mut opponent_increment) = (None, Duration::ZERO);
+        let value = |i: usize| args.get(i + 1).and_then(|value| value.parse::<u64>().ok());
+
+        for (i, arg) in args.iter().enumerate() {
+            let millis = || value(i).map(Duration::from_millis);
+            match (*arg, self.game.turn()) {
+                ("depth", _) => {
+                    limits.depth = value(i).map(|depth| depth.min(u8::MAX as u64) as u8)
+                }
+                ("nodes", _) => limits.nodes = value(i),
+                ("movetime", _) => limits.movetime = millis(),
+                ("wtime", PieceColor::White) | ("btime", PieceColor::Black) => time = millis(),
+                ("winc", PieceColor::White) | ("binc", PieceColor::Black) => {
+                    increment = millis().unwrap_or_default()
+                }
+                ("btime", PieceColor::White) | ("wtime", PieceColor::Black) => {
+                    opponent_time = millis()
+                }
+                ("binc", PieceColor::White) | ("winc", PieceColor::Black) => {
+                    opponent_increment = millis().unwrap_or_default()
+                }
+                ("movestogo", _) => moves_to_go = value(i).map(|moves| moves as u32),
+                ("infinite", _) => infinite = true,
+                _ => {}
+            }
+        }
+        limits.clock = time.map(|remaining| Clock {
+            remaining,
+            increment,
+            moves_to_go,
+        });
+        limits.opponent_clock = opponent_time.map(|remaining| Clock {
+            remaining,
+            increment: opponent_increment,
+            moves_to_go,
+        });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (engine, output) = (self.engine.clone(), self.output.clone());
+        let game = self.game.clone();
+        let thread_stop = stop.clone();
+
+        let handle = thread::spawn(move || {
+            let (board, turn) = (game.board(), game.turn());
+            let mut engine = engine.lock().unwrap();
+            let engine_move = engine.think(&game, &limits, &thread_stop, &mut |info| {
+                send(&output, &format_info(board, turn, info))
+            });
+            // in infinite mode the best move may only be sent after "stop"
+            while infinite && !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            let best_move = match engine_move {
+                Some(engine_move) => {
+                    move_to_uci(board, engine_move.best_move, engine_move.promotion)
+                }
+                None => "0000".to_string(),
+            };
+            send(&output, &format!("bestmove {best_move}"));
+        });
+        self.search = Some((handle, stop));
+    }
+
+    /// stops the running search, if any, and waits for its best move to be sent
+    fn stop_search(&mut self) {
+        if let Some((handle, stop)) = self.search.take() {
+            stop.store(true, Ordering::Relaxed);
+            let _ = handle.join();
+        }
+    }
+}
+
+fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+    let _ = writeln!(output, "{line}");
+    let _ = output.flush();
+}
+
+fn format_info(board: &Board, turn: Turn, info: &SearchInfo) -> String {
+    let millis = info.time.as_millis().max(1) as u64;
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        format_score(info.score),
+        info.nodes,
+        info.nodes * 1000 / millis,
+        info.time.as_millis(),
+        format_moves(board, turn, &info.pv),
+    )
+}
+
+/// "cp <centipawns>" or "mate <moves>"; negative when the engine is getting mated
+pub fn format_score(score: i32) -> String {
+    let plies_to_mate = MATE_SCORE - score.abs();
+    if plies_to_mate < 1000 {
+        let moves = (plies_to_mate + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {score}")
+    }
+}
+
+/// a move in the long algebraic notation of the protocol; eg. "e2e4" or "e7e8q"
+pub fn move_to_uci(board: &Board, (fr, to): Move, promotion: PieceKind) -> String {
+    let mut notation = fr.to_notation() + &to.to_notation();
+    if board.is_promotion(fr, to) {
+        notation.push(match promotion {
+            PieceKind::Rook => 'r',
+            PieceKind::Bishop => 'b',
+            PieceKind::Knight => 'n',
+            _ => 'q',
+        });
+    }
+    notation
+}
+
+/// a sequence of moves from the board with `turn` to move, separated by spaces; pawns
+/// promote to queens
+pub fn format_moves(board: &Board, turn: Turn, moves: &[Move]) -> String {
+    let mut board = board.clone();
+    let mut turn = turn;
+    let mut notations = Vec::with_capacity(moves.len());
+    for &(fr, to) in moves {
+        notations.push(move_to_uci(&board, (fr, to), PieceKind::Queen));
+        if board.move_piece(&mut turn, fr, to).is_err() {
+            break;
+        }
+    }
+    notations.join(" ")
+}
+
+/// parses "e2e4" or "e7e8n" into the two squares and the promotion piece, if any
+pub fn parse_uci_move(notation: &str) -> Option<(Position, Position, Option<PieceKind>)> {
+    if !notation.is_ascii() || !(4..=5).contains(&notation.len()) {
+        return None;
+    }
+    let fr = Position::from_notation(&notation[0..2])?;
+    let to = Position::from_notation(&notation[2..4])?;
+    let promotion = match notation.as_bytes().get(4) {
+        None => None,
+        Some(b'q') => Some(PieceKind::Queen),
+        Some(b'r') => Some(PieceKind::Rook),
+        Some(b'b') => Some(PieceKind::Bishop),
+        Some(b'n') => Some(PieceKind::Knight),
+        Some(_) => return None,
+    };
+    Some((fr, to, promotion))
+}
+
+/// plays a move given in the notation of the protocol
+pub fn play_uci_move(
+    board: &mut Board,
+    turn: &mut Turn,
+    notation: &str,
+) -> Result<(), BoardMoveError> {
+    let (fr, to, promotion) = parse_uci_move(notation).ok_or(BoardMoveError::Illegal)?;
+    board.move_piece_and_promote(turn, fr, to, promotion.unwrap_or(PieceKind::Queen))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::{self, BufReader, Read};
+    use std::time::Instant;
+
+    use super::*;
+
+    /// what the engine wrote, shared with the test
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Output {
+        fn lines(&self) -> Vec<String> {
+            let output = self.0.lock().unwrap();
+            String::from_utf8_lossy(&output)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// hands out the commands one line at a time; like a GUI it waits for the best move of a
+    /// search before going on, unless the next command stops it
+    struct Script {
+        lines: VecDeque<String>,
+        output: Output,
+        searches: usize,
+        last: String,
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let stopping = self.lines.front().is_some_and(|line| line == "stop");
+            if self.last.starts_with("go") && !stopping {
+                let started = Instant::now();
+                while bestmoves(&self.output.lines()) < self.searches {
+                    assert!(started.elapsed() < Duration::from_secs(60), "no bestmove");
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+            let Some(line) = self.lines.pop_front() else {
+                return Ok(0);
+            };
+            if line.starts_with("go") {
+                self.searches += 1;
+            }
+            let bytes = format!("{line}\n").into_bytes();
+            assert!(bytes.len() <= buf.len());
+            buf[..bytes.len()].copy_from_slice(&bytes);
+            self.last = line;
+            Ok(bytes.len())
+        }
+    }
+
+    fn bestmoves(lines: &[String]) -> usize {
+        lines
+            .iter()
+            .filter(|line| line.starts_with("bestmove"))
+            .count()
+    }
+
+    fn transcript(commands: &[&str]) -> Vec<String> {
+        let output = Output::default();
+        let script = Script {
+            lines: commands.iter().map(|command| command.to_string()).collect(),
+            output: output.clone(),
+            searches: 0,
+            last: String::new(),
+        };
+        run(BufReader::new(script), output.clone());
+        output.lines()
+    }
+
+    fn best_move(lines: &[String]) -> &str {
+        let line = lines
+            .iter()
+            .rev()
+            .find(|line| line.starts_with("bestmove"))
+            .unwrap();
+        line.split_whitespace().nth(1).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let lines = transcript(&["uci", "isready", "quit", "isready"]);
+        assert_eq!(lines[0], format!("id name {ENGINE_NAME}"));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("option name Threads type spin")));
+        let uciok = lines.iter().position(|line| line == "uciok").unwrap();
+        assert_eq!(lines[uciok + 1..], ["readyok"]);
+    }
+
+    #[test]
+    fn go_depth_searches_the_position() {
+        let lines = transcript(&[
+            "position startpos moves e2e4 e7e5 g1f3",
+            "go depth 2",
+            "isready",
+        ]);
+        for depth in 1..=2 {
+            let prefix = format!("info depth {depth} score cp ");
+            let info = lines.iter().find(|line| line.starts_with(&prefix)).unwrap();
+            assert!(info.contains(" nodes ") && info.contains(" nps ") && info.contains(" pv "));
+        }
+        assert_eq!(bestmoves(&lines), 1);
+        assert_eq!(lines.last().unwrap(), "readyok");
+
+        let (mut board, mut turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        for mv in ["e2e4", "e7e5", "g1f3", best_move(&lines)] {
+            play_uci_move(&mut board, &mut turn, mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn position_fen_finds_the_mate() {
+        let lines = transcript(&[
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "go depth 3",
+        ]);
+        assert_eq!(best_move(&lines), "a1a8");
+        assert!(lines.iter().any(|line| line.contains(" score mate 1 ")));
+    }
+
+    #[test]
+    fn stop_ends_an_infinite_search() {
+        let lines = transcript(&["ucinewgame", "position startpos", "go infinite", "stop"]);
+        assert_eq!(bestmoves(&lines), 1);
+        let (mut board, mut turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        play_uci_move(&mut board, &mut turn, best_move(&lines)).unwrap();
+    }
+
+    #[test]
+    fn position_keeps_the_halfmove_clock() {
+        let mut uci = Uci::new(Vec::new());
+        uci.set_position("fen 8/8/4k3/8/8/3K4/8/R7 w - - 37 60 moves a1a2 e6e5");
+        assert_eq!(uci.game.halfmove_clock(), 39);
+        assert_eq!(uci.game.start_fen(), "8/8/4k3/8/8/3K4/8/R7 w - - 37 60");
+    }
+
+    #[test]
+    fn position_goes_on_after_a_repetition() {
+        let mut uci = Uci::new(Vec::new());
+        let knights = "g1f3 g8f6 f3g1 f6g8 ".repeat(2);
+        uci.set_position(&format!("startpos moves {knights}e2e4"));
+        assert_eq!(uci.game.moves().len(), 9);
+
+        // the game itself stops there
+        let mut game = Game::default();
+        for mv in knights.split_whitespace() {
+            let (fr, to, promotion) = parse_uci_move(mv).unwrap();
+            game.play(fr, to, promotion.unwrap_or(PieceKind::Queen))
+                .unwrap();
+        }
+        let (fr, to, _) = parse_uci_move("e2e4").unwrap();
+        assert!(matches!(
+            game.play(fr, to, PieceKind::Queen),
+            Err(BoardMoveError::GameOver)
+        ));
+    }
+}
@@ -0,0 +1,463 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::board::fen::STARTING_FEN;
+use crate::board::pieces::{PieceColor, PieceKind};
+use crate::board::Board;
+use crate::computer::engine::{Engine, SearchInfo, SearchLimits};
+use crate::computer::search::{SearchEngine, MATE_SCORE};
+use crate::computer::syzygy::Tablebase;
+use crate::computer::time_manager::Clock;
+use crate::game::{Game, Termination};
+use crate::uci::{format_moves, move_to_uci, parse_uci_move};
+use crate::Turn;
+
+const ENGINE_NAME: &str = "chess-game-in-rust";
+
+/// speaks the Chess Engine Communication Protocol (xboard / WinBoard); reads commands from
+/// `input` until "quit" or the end of the input and writes the engine's answers to `output`
+///
+/// the engine thinks on the thread that reads the commands, so commands sent while it
+/// thinks are handled once its move is out
+pub fn run<R: BufRead, W: Write>(input: R, output: W) {
+    let mut xboard = Xboard::new(output);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !xboard.handle(line.trim()) {
+            break;
+        }
+    }
+}
+
+/// the time control set by "level"
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    /// moves per time control; 0 for the whole game
+    moves_per_session: u32,
+    increment: Duration,
+}
+
+struct Xboard<W> {
+    engine: SearchEngine,
+    output: W,
+    /// the moves played since the position was set; "undo" and "remove" take them back
+    game: Game,
+    /// the side the engine plays; None in force mode
+    engine_color: Option<PieceColor>,
+    post: bool,
+    depth: Option<u8>,
+    move_time: Option<Duration>,
+    level: Option<Level>,
+    /// the engine's clock as last told by "time"
+    time_left: Option<Duration>,
+    /// the opponent's clock as last told by "otim"
+    opponent_time_left: Option<Duration>,
+}
+
+impl<W: Write> Xboard<W> {
+    fn new(output: W) -> Self {
+        let (board, turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        Self {
+            engine: SearchEngine::full_strength(),
+            output,
+            game: Game::new(board, turn),
+            engine_color: Some(PieceColor::Black),
+            post: false,
+            depth: None,
+            move_time: None,
+            level: None,
+            time_left: None,
+            opponent_time_left: None,
+        }
+    }
+
+    /// returns false once the engine should quit
+    fn handle(&mut self, line: &str) -> bool {
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" => {}
+            "protover" => self.send(&format!(
+                "feature myname=\"{ENGINE_NAME}\" setboard=1 usermove=1 ping=1 playother=1 \
+                 sigint=0 sigterm=0 colors=0 analyze=0 reuse=1 egt=\"syzygy\" done=1"
+            )),
+            "new" => {
+                self.game = Game::default();
+                self.engine.new_game();
+                self.engine_color = Some(PieceColor::Black);
+                self.depth = None;
+            }
+            "setboard" => match Game::from_fen(args) {
+                Ok(game) => self.game = game,
+                Err(err) => self.send(&format!("tellusererror Illegal position: {err}")),
+            },
+            "force" | "result" => self.engine_color = None,
+            "go" => {
+                self.engine_color = Some(self.game.turn());
+                self.play_engine_move();
+            }
+            "playother" => {
+                self.engine_color = Some(match self.game.turn() {
+                    PieceColor::White => PieceColor::Black,
+                    PieceColor::Black => PieceColor::White,
+                })
+            }
+            "usermove" => self.user_move(args),
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "level" => self.set_level(args),
+            "st" => self.move_time = args.parse().ok().map(Duration::from_secs),
+            "sd" => self.depth = args.parse().ok(),
+            "time" => self.time_left = parse_centiseconds(args),
+            "otim" => self.opponent_time_left = parse_centiseconds(args),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "ping" => self.send(&format!("pong {args}")),
+            "egtpath" => self.set_egt_path(args),
+            "quit" => return false,
+            // without usermove=1 a move would come on its own; handle those anyway
+            _ if parse_uci_move(command).is_some() => self.user_move(command),
+            _ => self.send(&format!("Error (unknown command): {command}")),
+        }
+        true
+    }
+
+    /// egtpath <type> <path>; only syzygy tables are known
+    fn set_egt_path(&mut self, args: &str) {
+        let Some(("syzygy", path)) = args.split_once(' ') else {
+            return;
+        };
+        match Tablebase::open(path.trim()) {
+            Ok(tablebase) => self.engine.set_tablebase(Some(Arc::new(tablebase))),
+            Err(err) => self.send(&format!("tellusererror Can't read the tablebases: {err}")),
+        }
+    }
+
+    fn user_move(&mut self, notation: &str) {
+        let played = parse_uci_move(notation).map(|(fr, to, promotion)| {
+            self.game
+                .play_on(fr, to, promotion.unwrap_or(PieceKind::Queen))
+        });
+        if !matches!(played, Some(Ok(()))) {
+            self.send(&format!("Illegal move: {notation}"));
+            return;
+        }
+        if !self.send_result() && self.engine_color == Some(self.game.turn()) {
+            self.play_engine_move();
+        }
+    }
+
+    fn take_back(&mut self, plies: usize) {
+        for _ in 0..plies {
+            if self.game.undo().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// level <moves per session> <base time> <increment>; the base is minutes or minutes:seconds
+    fn set_level(&mut self, args: &str) {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        let [moves, base, increment] = args[..] else {
+            return;
+        };
+        let base = match base.split_once(':') {
+            Some((minutes, seconds)) => minutes
+                .parse::<u64>()
+                .ok()
+                .zip(seconds.parse::<u64>().ok())
+                .map(|(minutes, seconds)| minutes * 60 + seconds),
+            None => base.parse::<u64>().ok().map(|minutes| minutes * 60),
+        };
+        let (Ok(moves_per_session), Some(base), Ok(increment)) =
+            (moves.parse(), base, increment.parse::<f64>())
+        else {
+            return;
+        };
+        self.level = Some(Level {
+            moves_per_session,
+            increment: Duration::from_secs_f64(increment.max(0.0)),
+        });
+        self.time_left = Some(Duration::from_secs(base));
+        self.opponent_time_left = Some(Duration::from_secs(base));
+        self.move_time = None;
+    }
+
+    fn limits(&self) -> SearchLimits {
+        let clock = |time_left: Option<Duration>| match (self.level, time_left) {
+            (Some(level), Some(remaining)) => {
+                let moves_to_go = (level.moves_per_session > 0).then(|| {
+                    let moves_played = (self.game.moves().len() / 2) as u32;
+                    level.moves_per_session - moves_played % level.moves_per_session
+                });
+                Some(Clock {
+                    remaining,
+                    increment: level.increment,
+                    moves_to_go,
+                })
+            }
+            _ => None,
+        };
+        SearchLimits {
+            depth: self.depth,
+            nodes: None,
+            movetime: self.move_time,
+            clock: clock(self.time_left),
+            opponent_clock: clock(self.opponent_time_left),
+        }
+    }
+
+    fn play_engine_move(&mut self) {
+        // eg. "go" in a position that is already drawn
+        if self.send_result() {
+            return;
+        }
+        let limits = self.limits();
+        let stop = AtomicBool::new(false);
+        let (game, post) = (&self.game, self.post);
+        let (board, turn) = (game.board(), game.turn());
+        let output = &mut self.output;
+        let engine_move = self.engine.think(game, &limits, &stop, &mut |info| {
+            if post {
+                let _ = writeln!(output, "{}", format_thinking(board, turn, info));
+                let _ = output.flush();
+            }
+        });
+        let Some(engine_move) = engine_move else {
+            self.send_result();
+            return;
+        };
+        let notation = move_to_uci(
+            self.game.board(),
+            engine_move.best_move,
+            engine_move.promotion,
+        );
+        let (fr, to) = engine_move.best_move;
+        if let Err(err) = self.game.play(fr, to, engine_move.promotion) {
+            self.send(&format!("Error ({err:?}): {notation}"));
+            self.engine_color = None;
+            return;
+        }
+        self.send(&format!("move {notation}"));
+        self.send_result();
+    }
+
+    /// tells the interface when the game is over and stops playing; returns whether it is
+    fn send_result(&mut self) -> bool {
+        let Some(outcome) = self.game.outcome() else {
+            return false;
+        };
+        let reason = match (outcome.termination, outcome.result.winner()) {
+            (Termination::Checkmate, Some(PieceColor::White)) => "White mates".to_string(),
+            (Termination::Checkmate, _) => "Black mates".to_string(),
+            (Termination::Stalemate, _) => "Stalemate".to_string(),
+            (Termination::ThreefoldRepetition, _) => "Draw by repetition".to_string(),
+            (Termination::FiftyMoves, _) => "Draw by fifty move rule".to_string(),
+            (Termination::InsufficientMaterial, _) => "Insufficient material".to_string(),
+            (termination, _) => termination.to_string(),
+        };
+        self.send(&format!("{} {{{reason}}}", outcome.result));
+        self.engine_color = None;
+        true
+    }
+
+    fn send(&mut self, line: &str) {
+        let _ = writeln!(self.output, "{line}");
+        let _ = self.output.flush();
+    }
+}
+
+/// the "post" output: depth, score in centipawns, time in centiseconds, nodes and the pv
+fn format_thinking(board: &Board, turn: Turn, info: &SearchInfo) -> String {
+    // by convention a mate in n moves is sent as 100000 + n
+    let plies_to_mate = MATE_SCORE - info.score.abs();
+    let score = if plies_to_mate < 1000 {
+        (100_000 + (plies_to_mate + 1) / 2) * info.score.signum()
+    } else {
+        info.score
+    };
+    format!(
+        "{} {} {} {} {}",
+        info.depth,
+        score,
+        info.time.as_millis() / 10,
+        info.nodes,
+        format_moves(board, turn, &info.pv),
+    )
+}
+
+fn parse_centiseconds(s: &str) -> Option<Duration> {
+    s.parse::<u64>()
+        .ok()
+        .map(|centiseconds| Duration::from_millis(centiseconds * 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci::play_uci_move;
+
+    fn transcript(commands: &[&str]) -> Vec<String> {
+        let input = commands.join("\n");
+        let mut output = vec![];
+        run(input.as_bytes(), &mut output);
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// the moves the engine played
+    fn engine_moves(lines: &[String]) -> Vec<&str> {
+        lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("move "))
+            .collect()
+    }
+
+    #[test]
+    fn new_game_engine_answers_a_user_move() {
+        let lines = transcript(&[
+            "xboard",
+            "protover 2",
+            "new",
+            "sd 2",
+            "usermove e2e4",
+            "ping 7",
+        ]);
+        assert!(lines[0].starts_with("feature ") && lines[0].ends_with(" done=1"));
+        let replies = engine_moves(&lines);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(lines.last().unwrap(), "pong 7");
+
+        let (mut board, mut turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        for mv in ["e2e4", replies[0]] {
+            play_uci_move(&mut board, &mut turn, mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn go_plays_the_side_to_move() {
+        let lines = transcript(&[
+            "new",
+            "force",
+            "setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "sd 3",
+            "post",
+            "go",
+        ]);
+        assert_eq!(engine_moves(&lines), ["a1a8"]);
+        assert_eq!(lines.last().unwrap(), "1-0 {White mates}");
+        // depth, score, time, nodes and the pv; a mate in one is 100001. the search stops
+        // once it finds it
+        let thinking: Vec<&str> = lines[lines.len() - 3].split_whitespace().collect();
+        assert_eq!(thinking[1], "100001");
+        assert_eq!(thinking[4], "a1a8");
+    }
+
+    #[test]
+    fn force_mode_only_plays_the_user_moves() {
+        let lines = transcript(&["new", "force", "usermove e2e4", "usermove e7e5"]);
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn undo_and_remove_take_moves_back() {
+        let lines = transcript(&[
+            "new",
+            "force",
+            "usermove e2e4",
+            "usermove e7e5",
+            // black to move again
+            "undo",
+            "usermove d7d5",
+            // back to the start
+            "remove",
+            "remove",
+            "usermove d7d5",
+            "usermove d2d4",
+        ]);
+        assert_eq!(lines, ["Illegal move: d7d5"]);
+    }
+
+    #[test]
+    fn remove_lets_the_engine_replay() {
+        let lines = transcript(&["new", "sd 1", "usermove e2e4", "remove", "usermove d2d4"]);
+        let replies = engine_moves(&lines);
+        assert_eq!(replies.len(), 2);
+        let (mut board, mut turn) = Board::parse_fen(STARTING_FEN).unwrap();
+        for mv in ["d2d4", replies[1]] {
+            play_uci_move(&mut board, &mut turn, mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn setboard_rejects_a_bad_position() {
+        let lines = transcript(&["new", "sd 1", "setboard 8/8/8 w - - 0 1", "usermove e2e4"]);
+        assert!(lines[0].starts_with("tellusererror Illegal position"));
+        // the position didn't change and the engine answers from the start position
+        assert_eq!(engine_moves(&lines).len(), 1);
+    }
+
+    /// the knights going out and back twice; the last move repeats the start position a
+    /// third time
+    const KNIGHTS: [&str; 8] = [
+        "usermove g1f3",
+        "usermove g8f6",
+        "usermove f3g1",
+        "usermove f6g8",
+        "usermove g1f3",
+        "usermove g8f6",
+        "usermove f3g1",
+        "usermove f6g8",
+    ];
+
+    #[test]
+    fn draws_are_sent_as_results() {
+        let mut commands = vec!["new", "force"];
+        commands.extend(KNIGHTS);
+        assert_eq!(transcript(&commands), ["1/2-1/2 {Draw by repetition}"]);
+
+        let lines = transcript(&[
+            "new",
+            "force",
+            "setboard 8/8/8/4k3/8/8/8/R3K3 w - - 99 80",
+            "usermove a1a2",
+        ]);
+        assert_eq!(lines, ["1/2-1/2 {Draw by fifty move rule}"]);
+
+        let lines = transcript(&[
+            "new",
+            "force",
+            "setboard 8/8/8/4k3/8/8/8/3rK2N w - - 0 1",
+            "usermove e1d1",
+        ]);
+        assert_eq!(lines, ["1/2-1/2 {Insufficient material}"]);
+    }
+
+    #[test]
+    fn the_engine_stops_once_the_game_is_over() {
+        let mut commands = vec!["new", "sd 1", "force"];
+        commands.extend(&KNIGHTS[..7]);
+        // the engine plays white and would answer the next move
+        commands.extend(["playother", KNIGHTS[7], "usermove e2e4"]);
+        assert_eq!(transcript(&commands), ["1/2-1/2 {Draw by repetition}"]);
+    }
+
+    #[test]
+    fn go_in_a_finished_game_sends_the_result() {
+        let lines = transcript(&[
+            "new",
+            "force",
+            "setboard 8/8/8/4k3/8/8/8/4K2N w - - 0 1",
+            "sd 2",
+            "go",
+        ]);
+        assert_eq!(lines, ["1/2-1/2 {Insufficient material}"]);
+    }
+}
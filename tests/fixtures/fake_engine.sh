@@ -0,0 +1,24 @@
+#!/bin/sh
+# a UCI engine for the tests of src/computer/external.rs
+#
+# usage: fake_engine.sh <log> <move>
+# writes every command it's sent to <log> and answers every "go" with <move>
+
+log="$1"
+move="$2"
+: > "$log"
+while read -r line; do
+    echo "$line" >> "$log"
+    case "$line" in
+        uci)
+            echo "id name Fake"
+            echo "uciok"
+            ;;
+        isready) echo "readyok" ;;
+        go*)
+            echo "info depth 1 score cp 12 pv $move"
+            echo "bestmove $move"
+            ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,590 @@
+#!/usr/bin/env python3
+"""writes the small Syzygy tables the tests of src/computer/syzygy.rs probe
+
+the official tables can't be fetched while the tests run, so these are worked out here
+from scratch: a retrograde analysis of KRvK, KQvK and KPvK, and KBvK and KNvK, which are
+all draws but are needed once a pawn underpromotes. the files follow the layout of the
+official ones: the same header, piece order, position numbering, canonical Huffman code,
+sparse index and blocks; the values are not paired into longer symbols and DTZ values
+are stored in plies without a map, which the format allows but the official generator
+doesn't do.
+
+run it from anywhere; the tables are written next to this script
+"""
+
+import heapq
+import os
+from collections import Counter, deque
+
+WDL_MAGIC = bytes([0x71, 0xE8, 0x23, 0x5D])
+DTZ_MAGIC = bytes([0xD7, 0x66, 0x0C, 0xA5])
+
+FLAG_WIN_PLIES = 4
+FLAG_LOSS_PLIES = 8
+FLAG_SINGLE_VALUE = 128
+
+# piece codes of the tables; 1 to 6 for a white pawn to a white king, 9 to 14 for black
+W_PAWN, W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN, W_KING = 1, 2, 3, 4, 5, 6
+B_KING = 14
+
+LOG2_BLOCK_SIZE = 5
+LOG2_SPAN = 8
+
+WHITE, BLACK = 0, 1
+LOSS, DRAW, WIN = -2, 0, 2
+
+
+def rank(square):
+    return square >> 3
+
+
+def file(square):
+    return square & 7
+
+
+def off_diagonal(square):
+    return rank(square) - file(square)
+
+
+def touching(a, b):
+    return abs(rank(a) - rank(b)) <= 1 and abs(file(a) - file(b)) <= 1
+
+
+# move generation; white has a king and one piece, black a bare king
+
+KING_STEPS = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)]
+KNIGHT_STEPS = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)]
+ROOK_RAYS = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+BISHOP_RAYS = [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+
+
+def steps(square, deltas):
+    for dr, df in deltas:
+        r, f = rank(square) + dr, file(square) + df
+        if 0 <= r < 8 and 0 <= f < 8:
+            yield 8 * r + f
+
+
+def ray_squares(square, rays, occupied):
+    """the squares a slider reaches, up to and including the first occupied one"""
+    for dr, df in rays:
+        r, f = rank(square) + dr, file(square) + df
+        while 0 <= r < 8 and 0 <= f < 8:
+            target = 8 * r + f
+            yield target
+            if target in occupied:
+                break
+            r, f = r + dr, f + df
+
+
+def piece_attacks(code, square, occupied):
+    if code == W_ROOK:
+        return set(ray_squares(square, ROOK_RAYS, occupied))
+    if code == W_QUEEN:
+        return set(ray_squares(square, ROOK_RAYS + BISHOP_RAYS, occupied))
+    if code == W_BISHOP:
+        return set(ray_squares(square, BISHOP_RAYS, occupied))
+    if code == W_KNIGHT:
+        return set(steps(square, KNIGHT_STEPS))
+    if code == W_PAWN:
+        return set(steps(square, [(1, -1), (1, 1)]))
+    raise ValueError(code)
+
+
+def black_in_check(wk, code, square, bk):
+    return touching(wk, bk) or bk in piece_attacks(code, square, {wk, square, bk})
+
+
+def legal(wk, code, square, bk, stm):
+    if len({wk, square, bk}) < 3 or touching(wk, bk):
+        return False
+    if code == W_PAWN and not 8 <= square < 56:
+        return False
+    # the side that just moved can't be left in check
+    return stm == BLACK or not black_in_check(wk, code, square, bk)
+
+
+def black_moves(wk, code, square, bk):
+    """(to, captures) of the black king"""
+    for to in steps(bk, KING_STEPS):
+        if to == wk or touching(to, wk):
+            continue
+        if to == square:
+            yield to, True
+        elif to not in piece_attacks(code, square, {wk, square, to}):
+            yield to, False
+
+
+def white_moves(wk, code, square, bk):
+    """(king, piece square, promotion) after every white move; the promotion is the new
+    piece code of a pawn reaching the last rank, or None"""
+    for to in steps(wk, KING_STEPS):
+        if to != square and to != bk and not touching(to, bk):
+            yield to, square, None
+    if code == W_PAWN:
+        ahead = square + 8
+        if ahead in (wk, bk):
+            return
+        if ahead >= 56:
+            for promotion in (W_QUEEN, W_ROOK, W_BISHOP, W_KNIGHT):
+                yield wk, ahead, promotion
+            return
+        yield wk, ahead, None
+        if rank(square) == 1 and square + 16 not in (wk, bk):
+            yield wk, square + 16, None
+        return
+    for to in piece_attacks(code, square, {wk, square, bk}):
+        if to not in (wk, bk):
+            yield wk, to, None
+
+
+# retrograde analysis; a position is (white king, piece square, black king, side to move)
+# and its value is (wdl, dtz) for the side to move
+
+
+def positions(code):
+    for wk in range(64):
+        for square in range(64):
+            for bk in range(64):
+                for stm in (WHITE, BLACK):
+                    if legal(wk, code, square, bk, stm):
+                        yield wk, square, bk, stm
+
+
+def solve(code, zeroing_value):
+    """the value of every legal position with white's piece `code`
+
+    `zeroing_value(wk, square, bk, promotion)` gives black's value, to move, after a white
+    pawn move; captures by black always draw. dtz is the plies to the next capture, pawn
+    move or mate, as the probing code counts them: 1 for a mate in one and -1 when mated
+    """
+    values = {}
+    # black positions and the number of their moves that aren't known to lose
+    remaining = {}
+    queue = deque()
+
+    for wk, square, bk, stm in positions(code):
+        if stm == BLACK:
+            moves = list(black_moves(wk, code, square, bk))
+            if any(capture for _, capture in moves):
+                values[(wk, square, bk, stm)] = (DRAW, 0)
+            elif not moves:
+                if black_in_check(wk, code, square, bk):
+                    values[(wk, square, bk, stm)] = (LOSS, -1)
+                    queue.append((wk, square, bk, stm))
+                else:
+                    values[(wk, square, bk, stm)] = (DRAW, 0)
+            else:
+                remaining[(wk, square, bk, stm)] = len(moves)
+
+    # a pawn move that wins is as quick as it gets
+    for wk, square, bk, stm in positions(code):
+        if stm == WHITE and code == W_PAWN:
+            for king, to, promotion in white_moves(wk, code, square, bk):
+                if to != square and zeroing_value(king, to, bk, promotion)[0] == LOSS:
+                    values[(wk, square, bk, stm)] = (WIN, 1)
+                    queue.append((wk, square, bk, stm))
+                    break
+
+    while queue:
+        wk, square, bk, stm = queue.popleft()
+        wdl, dtz = values[(wk, square, bk, stm)]
+        if stm == BLACK:
+            # white moved here; only king moves and moves of a piece stay in the table
+            for before in white_predecessors(wk, code, square, bk):
+                if before in values:
+                    continue
+                values[before] = (WIN, 1 if dtz == -1 else 1 - dtz)
+                queue.append(before)
+        else:
+            for before_bk in steps(bk, KING_STEPS):
+                before = (wk, square, before_bk, BLACK)
+                if before not in remaining or before in values:
+                    continue
+                remaining[before] -= 1
+                if remaining[before] == 0:
+                    # the last move to be known to lose is the one that lasts longest
+                    values[before] = (LOSS, -(dtz + 1))
+                    queue.append(before)
+
+    for position in positions(code):
+        values.setdefault(position, (DRAW, 0))
+    return values
+
+
+def white_predecessors(wk, code, square, bk):
+    """the white-to-move positions that reach the black-to-move one with a king move or a
+    move of the piece; pawns only go forward, which the layers of the solve take care of"""
+    for before in steps(wk, KING_STEPS):
+        if before not in (square, bk) and legal(before, code, square, bk, WHITE):
+            yield before, square, bk, WHITE
+    if code == W_PAWN:
+        return
+    # pieces move the same way back, through the squares that are empty
+    for before in piece_attacks(code, square, {wk, square, bk}):
+        if before not in (wk, bk) and legal(wk, code, before, bk, WHITE):
+            yield wk, before, bk, WHITE
+
+
+# the numbering of positions, as the probing code works it out
+
+
+def binomial(k, n):
+    if k > n:
+        return 0
+    result = 1
+    for i in range(k):
+        result = result * (n - i) // (i + 1)
+    return result
+
+
+def make_indices():
+    map_b1h1h7 = [0] * 64
+    code = 0
+    for square in range(64):
+        if off_diagonal(square) < 0:
+            map_b1h1h7[square] = code
+            code += 1
+
+    map_a1d1d4 = [0] * 64
+    code = 0
+    diagonal = []
+    for r in range(4):
+        for f in range(4):
+            square = 8 * r + f
+            if off_diagonal(square) < 0:
+                map_a1d1d4[square] = code
+                code += 1
+            elif off_diagonal(square) == 0:
+                diagonal.append(square)
+    for square in diagonal:
+        map_a1d1d4[square] = code
+        code += 1
+
+    map_pawns = [0] * 64
+    available = 47
+    for f in range(4):
+        for r in range(1, 7):
+            square = 8 * r + f
+            map_pawns[square] = available
+            map_pawns[square ^ 7] = available - 1
+            available -= 2
+    lead_pawn_idx = [0] * 64
+    for f in range(4):
+        idx = 0
+        for r in range(1, 7):
+            square = 8 * r + f
+            lead_pawn_idx[square] = idx
+            idx += binomial(0, map_pawns[square])
+    return map_b1h1h7, map_a1d1d4, map_pawns, lead_pawn_idx
+
+
+MAP_B1H1H7, MAP_A1D1D4, MAP_PAWNS, LEAD_PAWN_IDX = make_indices()
+
+
+def index(placed, order, group_idx, has_pawns):
+    """the index of the pieces (square, code) in a part whose pieces come in `order`;
+    only tables with three unique pieces or a single pawn are handled"""
+    placed = sorted(placed)
+    if has_pawns:
+        squares = [square for square, code in placed if code == order[0]]
+        codes = [order[0]]
+        rest = [(square, code) for square, code in placed if code != order[0]]
+    else:
+        squares, codes, rest = [], [], placed
+    squares += [square for square, _ in rest]
+    codes += [code for _, code in rest]
+    for i in range(len(squares)):
+        j = codes.index(order[i], i)
+        codes[i], codes[j] = codes[j], codes[i]
+        squares[i], squares[j] = squares[j], squares[i]
+
+    if file(squares[0]) > 3:
+        squares = [square ^ 7 for square in squares]
+    if has_pawns:
+        idx = LEAD_PAWN_IDX[squares[0]]
+    else:
+        if rank(squares[0]) > 3:
+            squares = [square ^ 56 for square in squares]
+        for i in range(3):
+            off = off_diagonal(squares[i])
+            if off == 0:
+                continue
+            if off > 0:
+                squares[i:] = [((s >> 3) | (s << 3)) & 63 for s in squares[i:]]
+            break
+        s0, s1, s2 = squares[:3]
+        adjust1 = int(s1 > s0)
+        adjust2 = int(s2 > s0) + int(s2 > s1)
+        if off_diagonal(s0) != 0:
+            idx = (MAP_A1D1D4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+        elif off_diagonal(s1) != 0:
+            idx = (6 * 63 + rank(s0) * 28 + MAP_B1H1H7[s1]) * 62 + s2 - adjust2
+        elif off_diagonal(s2) != 0:
+            idx = (
+                6 * 63 * 62
+                + 4 * 28 * 62
+                + rank(s0) * 7 * 28
+                + (rank(s1) - adjust1) * 28
+                + MAP_B1H1H7[s2]
+            )
+        else:
+            idx = (
+                6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank(s0) * 7 * 6
+                + (rank(s1) - adjust1) * 6
+                + (rank(s2) - adjust2)
+            )
+        return idx
+
+    # a pawn table: the kings are groups of their own after the pawn
+    idx *= group_idx[0]
+    for i in (1, 2):
+        adjust = sum(1 for square in squares[:i] if squares[i] > square)
+        idx += (squares[i] - adjust) * group_idx[i]
+    return idx
+
+
+def part_size(has_pawns, lead_file):
+    if has_pawns:
+        return 6 * 63 * 62
+    return 31332
+
+
+def group_idx(has_pawns):
+    # the lead group first, then each king on the squares left
+    return [1, 6, 6 * 63] if has_pawns else [1]
+
+
+# compression
+
+
+def huffman_lengths(counts):
+    """the code length of every symbol"""
+    heap = [(count, i, [symbol]) for i, (symbol, count) in enumerate(sorted(counts.items()))]
+    heapq.heapify(heap)
+    lengths = {symbol: 0 for symbol in counts}
+    tiebreak = len(heap)
+    while len(heap) > 1:
+        count1, _, symbols1 = heapq.heappop(heap)
+        count2, _, symbols2 = heapq.heappop(heap)
+        for symbol in symbols1 + symbols2:
+            lengths[symbol] += 1
+        heapq.heappush(heap, (count1 + count2, tiebreak, symbols1 + symbols2))
+        tiebreak += 1
+    assert max(lengths.values()) <= 32
+    return lengths
+
+
+def pairs_data(values, flags):
+    """the header of a part and its sparse index, block lengths and blocks"""
+    counts = Counter(values)
+    if len(counts) == 1:
+        return bytes([flags | FLAG_SINGLE_VALUE, values[0]]), b"", b"", b""
+
+    lengths = huffman_lengths(counts)
+    min_len, max_len = min(lengths.values()), max(lengths.values())
+    # longer codes get the lower symbol numbers and the lower code values
+    symbols = sorted(counts, key=lambda value: (-lengths[value], value))
+    number = {value: i for i, value in enumerate(symbols)}
+    lowest_sym = [0] * (max_len - min_len + 1)
+    base = [0] * (max_len - min_len + 1)
+    for length in range(max_len, min_len - 1, -1):
+        i = length - min_len
+        with_length = [value for value in symbols if lengths[value] == length]
+        lowest_sym[i] = number[with_length[0]] if with_length else (
+            lowest_sym[i + 1] + sum(1 for v in symbols if lengths[v] == length + 1)
+        )
+        if length < max_len:
+            longer = sum(1 for value in symbols if lengths[value] == length + 1)
+            assert (base[i + 1] + longer) % 2 == 0
+            base[i] = (base[i + 1] + longer) // 2
+    code = {}
+    for value in symbols:
+        i = lengths[value] - min_len
+        code[value] = (base[i] + number[value] - lowest_sym[i], lengths[value])
+
+    header = bytearray([flags, LOG2_BLOCK_SIZE, LOG2_SPAN, 0])
+    block_bits = 8 << LOG2_BLOCK_SIZE
+    blocks, block_starts, block_counts = [], [], []
+    bits, bit_count, start = 0, 0, 0
+    for i, value in enumerate(values):
+        code_value, length = code[value]
+        if bit_count + length > block_bits:
+            blocks.append(bits << (block_bits - bit_count))
+            block_starts.append(start)
+            block_counts.append(i - start)
+            bits, bit_count, start = 0, 0, i
+        bits = (bits << length) | code_value
+        bit_count += length
+    blocks.append(bits << (block_bits - bit_count))
+    block_starts.append(start)
+    block_counts.append(len(values) - start)
+
+    header += len(blocks).to_bytes(4, "little")
+    header += bytes([max_len, min_len])
+    for lowest in lowest_sym:
+        header += lowest.to_bytes(2, "little")
+    header += len(symbols).to_bytes(2, "little")
+    for value in symbols:
+        # a leaf: the value on the left and 0xFFF on the right
+        header += bytes([value & 0xFF, (value >> 8) | 0xF0, 0xFF])
+    if len(symbols) % 2:
+        header.append(0)
+
+    span = 1 << LOG2_SPAN
+    sparse = bytearray()
+    block = 0
+    for k in range((len(values) + span - 1) // span):
+        position = k * span + span // 2
+        while block + 1 < len(blocks) and block_starts[block + 1] <= position:
+            block += 1
+        sparse += block.to_bytes(4, "little")
+        sparse += (position - block_starts[block]).to_bytes(2, "little")
+    lengths_bytes = b"".join((count - 1).to_bytes(2, "little") for count in block_counts)
+    data = b"".join(bits.to_bytes(block_bits // 8, "big") for bits in blocks)
+    return bytes(header), bytes(sparse), lengths_bytes, data
+
+
+def write_table(path, magic, has_pawns, order, parts):
+    """`parts` is [file][side] of (flags, values)"""
+    out = bytearray(magic)
+    out.append(1 | (2 if has_pawns else 0))
+    for _ in parts:
+        out.append(0)
+        for code in order:
+            out.append(code | code << 4)
+    out += b"\0" * (len(out) & 1)
+
+    encoded = [[pairs_data(values, flags) for flags, values in sides] for sides in parts]
+    for sides in encoded:
+        for header, _, _, _ in sides:
+            out += header
+    if magic == DTZ_MAGIC:
+        out += b"\0" * (len(out) & 1)
+    for section in (1, 2):
+        for sides in encoded:
+            for part in sides:
+                out += part[section]
+    for sides in encoded:
+        for _, _, _, blocks in sides:
+            out += b"\0" * (-len(out) % 64)
+            out += blocks
+    # the decoder reads a little past the last block
+    out += b"\0" * 64
+    with open(path, "wb") as f:
+        f.write(out)
+
+
+def fill(values, size):
+    """the positions no legal placement maps to get the most common value"""
+    common = Counter(value for value in values if value is not None).most_common(1)[0][0]
+    return [common if value is None else value for value in values]
+
+
+def table_values(code, solved, has_pawns, order, stm, lead_file, value_of):
+    values = [None] * part_size(has_pawns, lead_file)
+    for (wk, square, bk, side), value in solved.items():
+        if side != stm:
+            continue
+        if has_pawns and min(file(square), 7 - file(square)) != lead_file:
+            continue
+        stored = value_of(value)
+        if stored is None:
+            continue
+        placed = [(wk, W_KING), (square, code), (bk, B_KING)]
+        idx = index(placed, order, group_idx(has_pawns), has_pawns)
+        assert values[idx] in (None, stored), (wk, square, bk, side)
+        values[idx] = stored
+    return fill(values, len(values))
+
+
+def wdl_value(value):
+    return value[0] + 2
+
+
+def dtz_value(value):
+    # only the wins of the side to move are read from the table; draws go by the WDL
+    wdl, dtz = value
+    return abs(dtz) - 1 if wdl != DRAW else None
+
+
+def write_pawnless(directory, name, code, solved):
+    order = [W_KING, B_KING, code]
+    if solved is None:
+        # every position is a draw
+        parts = [[(0, [2]), (0, [2])]]
+        write_table(os.path.join(directory, name + ".rtbw"), WDL_MAGIC, False, order, parts)
+        return
+    parts = [[(0, table_values(code, solved, False, order, stm, 0, wdl_value))
+              for stm in (WHITE, BLACK)]]
+    write_table(os.path.join(directory, name + ".rtbw"), WDL_MAGIC, False, order, parts)
+    flags = FLAG_WIN_PLIES | FLAG_LOSS_PLIES
+    parts = [[(flags, table_values(code, solved, False, order, WHITE, 0, dtz_value))]]
+    write_table(os.path.join(directory, name + ".rtbz"), DTZ_MAGIC, False, order, parts)
+
+
+def write_pawn(directory, solved):
+    order = [W_PAWN, W_KING, B_KING]
+    parts = [
+        [(0, table_values(W_PAWN, solved, True, order, stm, f, wdl_value))
+         for stm in (WHITE, BLACK)]
+        for f in range(4)
+    ]
+    write_table(os.path.join(directory, "KPvK.rtbw"), WDL_MAGIC, True, order, parts)
+    flags = FLAG_WIN_PLIES | FLAG_LOSS_PLIES
+    parts = [
+        [(flags, table_values(W_PAWN, solved, True, order, WHITE, f, dtz_value))]
+        for f in range(4)
+    ]
+    write_table(os.path.join(directory, "KPvK.rtbz"), DTZ_MAGIC, True, order, parts)
+
+
+def main():
+    directory = os.path.dirname(os.path.abspath(__file__))
+    no_pawn_moves = lambda *_: None
+    queen = solve(W_QUEEN, no_pawn_moves)
+    rook = solve(W_ROOK, no_pawn_moves)
+    promoted = {W_QUEEN: queen, W_ROOK: rook}
+
+    def pawn_move(wk, square, bk, promotion):
+        if promotion is None:
+            return pawn[(wk, square, bk, BLACK)]
+        if promotion in promoted:
+            return promoted[promotion][(wk, square, bk, BLACK)]
+        return (DRAW, 0)
+
+    # the pawn moves lead to the positions of a pawn further up, so those are solved first
+    pawn = {}
+    for pawn_rank in range(6, 0, -1):
+        layer = solve_layer(pawn_rank, pawn_move)
+        pawn.update(layer)
+
+    write_pawnless(directory, "KQvK", W_QUEEN, queen)
+    write_pawnless(directory, "KRvK", W_ROOK, rook)
+    write_pawnless(directory, "KBvK", W_BISHOP, None)
+    write_pawnless(directory, "KNvK", W_KNIGHT, None)
+    write_pawn(directory, pawn)
+
+
+def solve_layer(pawn_rank, pawn_move):
+    """the KPvK positions with the pawn on `pawn_rank`"""
+    global positions
+    every_position = positions
+
+    def layer_positions(code):
+        for position in every_position(code):
+            if rank(position[1]) == pawn_rank:
+                yield position
+
+    positions = layer_positions
+    try:
+        return solve(W_PAWN, pawn_move)
+    finally:
+        positions = every_position
+
+
+if __name__ == "__main__":
+    main()